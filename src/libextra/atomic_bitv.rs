@@ -0,0 +1,175 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A concurrent bitset over a fixed universe whose words are updated with
+ * atomic fetch-or/fetch-and, so multiple tasks can mark and test bits
+ * simultaneously (e.g. visited sets in a parallel graph traversal). All
+ * mutating operations take `&self`, which lets the set be shared behind
+ * an `arc::ARC` without a lock.
+ */
+
+use bitv;
+use bitv::Bitv;
+
+use std::cast;
+use std::uint;
+use std::unstable::intrinsics;
+use std::vec;
+
+/// The concurrent bitset type. The capacity is fixed at creation; bits
+/// outside the universe cannot be set.
+pub struct AtomicBitvSet {
+    /// Packed storage, updated through atomic intrinsics only
+    priv storage: ~[int],
+    /// The number of valid bits
+    priv nbits: uint
+}
+
+impl AtomicBitvSet {
+    /// Create a set over the universe `[0, nbits)` with all bits clear
+    pub fn new(nbits: uint) -> AtomicBitvSet {
+        let nwords = uint::div_ceil(nbits, uint::bits);
+        AtomicBitvSet{storage: vec::from_elem(nwords, 0), nbits: nbits}
+    }
+
+    /// The number of bits in the universe
+    pub fn capacity(&self) -> uint { self.nbits }
+
+    /// An aliasable mutable view of one storage word, for handing to the
+    /// atomic intrinsics
+    #[inline]
+    fn word_mut<'a>(&'a self, w: uint) -> &'a mut int {
+        unsafe { cast::transmute_mut(&self.storage[w]) }
+    }
+
+    /// Atomically read the value of bit `i`
+    pub fn get(&self, i: uint) -> bool {
+        assert!(i < self.nbits);
+        let w = unsafe { intrinsics::atomic_load(&self.storage[i / uint::bits]) };
+        w as uint & (1 << (i % uint::bits)) != 0
+    }
+
+    /// Atomically set bit `i`, returning its previous value
+    pub fn test_and_set(&self, i: uint) -> bool {
+        assert!(i < self.nbits);
+        let flag = 1 << (i % uint::bits);
+        let old = unsafe {
+            intrinsics::atomic_or(self.word_mut(i / uint::bits), flag as int)
+        };
+        old as uint & flag != 0
+    }
+
+    /// Atomically clear bit `i`, returning its previous value
+    pub fn test_and_clear(&self, i: uint) -> bool {
+        assert!(i < self.nbits);
+        let flag = 1 << (i % uint::bits);
+        let old = unsafe {
+            intrinsics::atomic_and(self.word_mut(i / uint::bits),
+                                   !flag as int)
+        };
+        old as uint & flag != 0
+    }
+
+    /// Add a value to the set. Return true if the value was not already
+    /// present in the set.
+    pub fn insert(&self, value: uint) -> bool {
+        !self.test_and_set(value)
+    }
+
+    /// Remove a value from the set. Return true if the value was
+    /// present in the set.
+    pub fn remove(&self, value: &uint) -> bool {
+        self.test_and_clear(*value)
+    }
+
+    /// Copy the current contents into an ordinary Bitv. Each word is read
+    /// atomically, but the snapshot as a whole is not a consistent cut if
+    /// other tasks are writing concurrently.
+    pub fn snapshot(&self) -> Bitv {
+        let words = do vec::from_fn(self.storage.len()) |w| {
+            unsafe { intrinsics::atomic_load(&self.storage[w]) as uint }
+        };
+        let full = bitv::from_words(words);
+        bitv::from_fn(self.nbits, |i| full[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arc;
+
+    use std::comm;
+    use std::task;
+    use std::uint;
+
+    #[test]
+    fn test_basic() {
+        let s = AtomicBitvSet::new(1000);
+        assert_eq!(s.capacity(), 1000);
+        assert!(!s.get(3));
+        assert!(!s.test_and_set(3));
+        assert!(s.get(3));
+        assert!(s.test_and_set(3));
+        assert!(s.test_and_clear(3));
+        assert!(!s.get(3));
+        assert!(!s.test_and_clear(3));
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let s = AtomicBitvSet::new(100);
+        assert!(s.insert(42));
+        assert!(!s.insert(42));
+        assert!(s.remove(&42));
+        assert!(!s.remove(&42));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let s = AtomicBitvSet::new(130);
+        assert!(s.insert(0));
+        assert!(s.insert(129));
+        let b = s.snapshot();
+        assert!(b[0]);
+        assert!(b[129]);
+        assert!(!b[64]);
+    }
+
+    #[test]
+    fn test_shared_across_tasks() {
+        static NTASKS: uint = 8;
+        static PER_TASK: uint = 64;
+
+        let set = arc::ARC(AtomicBitvSet::new(NTASKS * PER_TASK));
+        let (port, chan) = comm::stream();
+        let chan = comm::SharedChan::new(chan);
+
+        for uint::range(0, NTASKS) |t| {
+            let set = set.clone();
+            let chan = chan.clone();
+            do task::spawn {
+                for uint::range(t * PER_TASK, (t + 1) * PER_TASK) |i| {
+                    assert!(!set.get().test_and_set(i));
+                }
+                chan.send(());
+            }
+        }
+        for NTASKS.times {
+            port.recv();
+        }
+
+        for uint::range(0, NTASKS * PER_TASK) |i| {
+            assert!(set.get().get(i));
+        }
+    }
+}
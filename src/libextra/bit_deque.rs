@@ -0,0 +1,271 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A double-ended queue of bits, stored as a ring buffer of words with a
+ * bit-level head offset. Bits can be pushed and popped at either end in
+ * O(1), and a whole `Bitv` can be appended, which suits sliding-window
+ * protocols and other bit-level FIFOs.
+ */
+
+use bitv::Bitv;
+
+use std::uint;
+use std::vec;
+
+/// The bit deque type
+pub struct BitDeque {
+    /// The ring buffer; the capacity in bits is always a whole number
+    /// of words
+    priv storage: ~[uint],
+    /// The ring position of the front bit
+    priv head: uint,
+    /// The number of bits in the deque
+    priv nbits: uint
+}
+
+impl Container for BitDeque {
+    /// Return the number of bits in the deque
+    fn len(&self) -> uint { self.nbits }
+
+    /// Return true if the deque contains no bits
+    fn is_empty(&self) -> bool { self.nbits == 0 }
+}
+
+impl Mutable for BitDeque {
+    /// Clear the deque, removing all bits
+    fn clear(&mut self) {
+        for self.storage.mut_iter().advance |w| { *w = 0; }
+        self.head = 0;
+        self.nbits = 0;
+    }
+}
+
+impl BitDeque {
+    /// Create an empty BitDeque
+    pub fn new() -> BitDeque {
+        BitDeque{storage: ~[0], head: 0, nbits: 0}
+    }
+
+    /// Create an empty BitDeque with room for at least `nbits` bits
+    /// before it has to grow
+    pub fn with_capacity(nbits: uint) -> BitDeque {
+        let nwords = uint::max(1, uint::div_ceil(nbits, uint::bits));
+        BitDeque{storage: vec::from_elem(nwords, 0), head: 0, nbits: 0}
+    }
+
+    /// The number of bits the deque can hold without growing
+    pub fn capacity(&self) -> uint {
+        self.storage.len() * uint::bits
+    }
+
+    /// Read the bit at ring position `pos`
+    #[inline]
+    fn get_ring(&self, pos: uint) -> bool {
+        self.storage[pos / uint::bits] & (1 << (pos % uint::bits)) != 0
+    }
+
+    /// Write the bit at ring position `pos`
+    #[inline]
+    fn set_ring(&mut self, pos: uint, bit: bool) {
+        let flag = 1 << (pos % uint::bits);
+        if bit {
+            self.storage[pos / uint::bits] |= flag;
+        } else {
+            self.storage[pos / uint::bits] &= !flag;
+        }
+    }
+
+    /// Get the bit at index `i`, counting from the front
+    pub fn get(&self, i: uint) -> bool {
+        assert!(i < self.nbits);
+        self.get_ring((self.head + i) % self.capacity())
+    }
+
+    /// The bit at the front of the deque; fails if the deque is empty
+    pub fn peek_front(&self) -> bool { self.get(0) }
+
+    /// The bit at the back of the deque; fails if the deque is empty
+    pub fn peek_back(&self) -> bool { self.get(self.nbits - 1) }
+
+    /// Reserve capacity for at least `nbits` bits, straightening the
+    /// ring out so the front bit lands at offset zero
+    pub fn reserve(&mut self, nbits: uint) {
+        if nbits <= self.capacity() {
+            return;
+        }
+        let nwords = uint::div_ceil(
+            uint::max(nbits, self.capacity() * 2), uint::bits);
+        let mut storage = vec::from_elem(nwords, 0u);
+        for uint::range(0, self.nbits) |i| {
+            if self.get(i) {
+                storage[i / uint::bits] |= 1 << (i % uint::bits);
+            }
+        }
+        self.storage = storage;
+        self.head = 0;
+    }
+
+    /// Prepend a bit to the deque
+    pub fn add_front(&mut self, bit: bool) {
+        if self.nbits == self.capacity() {
+            self.reserve(self.nbits + 1);
+        }
+        let cap = self.capacity();
+        self.head = (self.head + cap - 1) % cap;
+        let head = self.head;
+        self.set_ring(head, bit);
+        self.nbits += 1;
+    }
+
+    /// Append a bit to the deque
+    pub fn add_back(&mut self, bit: bool) {
+        if self.nbits == self.capacity() {
+            self.reserve(self.nbits + 1);
+        }
+        let pos = (self.head + self.nbits) % self.capacity();
+        self.set_ring(pos, bit);
+        self.nbits += 1;
+    }
+
+    /// Append every bit of a Bitv to the back of the deque
+    pub fn add_bitv(&mut self, bits: &Bitv) {
+        self.reserve(self.nbits + bits.len());
+        for bits.each |bit| {
+            self.add_back(bit);
+        }
+    }
+
+    /// Remove and return the front bit; fails if the deque is empty
+    pub fn pop_front(&mut self) -> bool {
+        let bit = self.peek_front();
+        self.head = (self.head + 1) % self.capacity();
+        self.nbits -= 1;
+        bit
+    }
+
+    /// Remove and return the back bit; fails if the deque is empty
+    pub fn pop_back(&mut self) -> bool {
+        let bit = self.peek_back();
+        self.nbits -= 1;
+        bit
+    }
+
+    /// Visit each bit from front to back
+    pub fn each(&self, f: &fn(bool) -> bool) -> bool {
+        for uint::range(0, self.nbits) |i| {
+            if !f(self.get(i)) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Copy the contents into a Bitv, front bit first
+    pub fn to_bitv(&self) -> Bitv {
+        let mut bits = Bitv::new(self.nbits, false);
+        for uint::range(0, self.nbits) |i| {
+            bits.set(i, self.get(i));
+        }
+        bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bitv;
+    use bitv::Bitv;
+
+    use std::uint;
+
+    #[test]
+    fn test_basic() {
+        let mut d = BitDeque::new();
+        assert!(d.is_empty());
+        d.add_back(true);
+        d.add_back(false);
+        d.add_front(true);
+        assert_eq!(d.len(), 3);
+        assert!(d.get(0));
+        assert!(d.get(1));
+        assert!(!d.get(2));
+        assert!(d.peek_front());
+        assert!(!d.peek_back());
+
+        assert!(d.pop_front());
+        assert!(!d.pop_back());
+        assert!(d.pop_front());
+        assert!(d.is_empty());
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_pop_empty_fails() {
+        let mut d = BitDeque::new();
+        d.pop_front();
+    }
+
+    #[test]
+    fn test_growth_preserves_order() {
+        let mut d = BitDeque::new();
+        // force the head away from offset zero before growing
+        d.add_back(true);
+        assert!(d.pop_front());
+        for uint::range(0, 200) |i| {
+            d.add_back(i % 3 == 0);
+        }
+        assert_eq!(d.len(), 200);
+        for uint::range(0, 200) |i| {
+            assert_eq!(d.get(i), i % 3 == 0);
+        }
+        for uint::range(0, 200) |i| {
+            assert_eq!(d.pop_front(), i % 3 == 0);
+        }
+    }
+
+    #[test]
+    fn test_front_back_interleaved() {
+        let mut d = BitDeque::with_capacity(4);
+        for 100u.times {
+            d.add_front(true);
+            d.add_back(false);
+        }
+        assert_eq!(d.len(), 200);
+        assert!(d.pop_front());
+        assert!(!d.pop_back());
+        for uint::range(0, 198) |i| {
+            assert_eq!(d.get(i), i < 99);
+        }
+    }
+
+    #[test]
+    fn test_add_bitv_and_to_bitv() {
+        let mut d = BitDeque::new();
+        d.add_back(true);
+        let b = bitv::from_bools([false, true, true]);
+        d.add_bitv(&b);
+        assert_eq!(d.len(), 4);
+        let out = d.to_bitv();
+        assert_eq!(out.to_bools(), ~[true, false, true, true]);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut d = BitDeque::new();
+        d.add_back(true);
+        d.add_front(true);
+        d.clear();
+        assert!(d.is_empty());
+        d.add_back(false);
+        assert!(!d.peek_front());
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * Bit-granularity reading and writing layered over the byte-oriented
+ * `io::Reader` and `io::Writer` interfaces. Bits are packed most
+ * significant first within each byte, matching the convention of
+ * `bitv::from_bytes`.
+ */
+
+use std::io::{Reader, Writer};
+use std::uint;
+
+/// Reads individual bits from an underlying byte reader
+pub struct BitReader {
+    priv reader: @Reader,
+    /// The byte currently being consumed
+    priv byte: uint,
+    /// The number of unread bits left in `byte`
+    priv remaining: uint
+}
+
+impl BitReader {
+    /// Create a bit reader wrapping the given byte reader
+    pub fn new(reader: @Reader) -> BitReader {
+        BitReader{reader: reader, byte: 0, remaining: 0}
+    }
+
+    /// Read a single bit, or None once the underlying reader is exhausted
+    pub fn read_bit(&mut self) -> Option<bool> {
+        if self.remaining == 0 {
+            let b = self.reader.read_byte();
+            if b < 0 {
+                return None;
+            }
+            self.byte = b as uint;
+            self.remaining = 8;
+        }
+        self.remaining -= 1;
+        Some(self.byte & (1 << self.remaining) != 0)
+    }
+
+    /// Read `nbits` bits (at most `uint::bits`) into the low bits of a
+    /// uint, first bit read in the most significant position. Returns
+    /// None if the stream ends before all bits could be read.
+    pub fn read_bits(&mut self, nbits: uint) -> Option<uint> {
+        assert!(nbits <= uint::bits);
+        let mut value = 0;
+        for nbits.times {
+            match self.read_bit() {
+                Some(b) => {
+                    value = (value << 1) | (b as uint);
+                }
+                None => return None
+            }
+        }
+        Some(value)
+    }
+
+    /// Discard any unread bits of the current byte, so the next read
+    /// starts on a byte boundary
+    pub fn align(&mut self) {
+        self.remaining = 0;
+    }
+}
+
+/// Writes individual bits to an underlying byte writer
+pub struct BitWriter {
+    priv writer: @Writer,
+    /// The byte currently being assembled
+    priv byte: uint,
+    /// The number of bits already filled in `byte`
+    priv filled: uint
+}
+
+impl BitWriter {
+    /// Create a bit writer wrapping the given byte writer
+    pub fn new(writer: @Writer) -> BitWriter {
+        BitWriter{writer: writer, byte: 0, filled: 0}
+    }
+
+    /// Write a single bit
+    pub fn write_bit(&mut self, bit: bool) {
+        self.byte = (self.byte << 1) | (bit as uint);
+        self.filled += 1;
+        if self.filled == 8 {
+            self.writer.write([self.byte as u8]);
+            self.byte = 0;
+            self.filled = 0;
+        }
+    }
+
+    /// Write the low `nbits` bits of `value`, most significant first
+    pub fn write_bits(&mut self, value: uint, nbits: uint) {
+        assert!(nbits <= uint::bits);
+        let mut i = nbits;
+        while i > 0 {
+            i -= 1;
+            self.write_bit(value & (1 << i) != 0);
+        }
+    }
+
+    /// Pad the current byte with zero bits and write it out. A no-op when
+    /// already on a byte boundary. Must be called before dropping the
+    /// writer or trailing bits are lost.
+    pub fn flush(&mut self) {
+        if self.filled > 0 {
+            self.byte <<= 8 - self.filled;
+            self.writer.write([self.byte as u8]);
+            self.byte = 0;
+            self.filled = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use io_util::BufReader;
+
+    use std::io;
+
+    #[test]
+    fn test_read_bits() {
+        let rd = @BufReader::new(~[0b10110110, 0b11000000]) as @io::Reader;
+        let mut bits = BitReader::new(rd);
+        assert_eq!(bits.read_bit(), Some(true));
+        assert_eq!(bits.read_bit(), Some(false));
+        assert_eq!(bits.read_bits(4), Some(0b1101));
+        assert_eq!(bits.read_bits(4), Some(0b1011));
+        // six bits left in the second byte
+        assert_eq!(bits.read_bits(6), Some(0));
+        assert_eq!(bits.read_bit(), None);
+    }
+
+    #[test]
+    fn test_read_align() {
+        let rd = @BufReader::new(~[0b11110000, 0b10101010]) as @io::Reader;
+        let mut bits = BitReader::new(rd);
+        assert_eq!(bits.read_bits(3), Some(0b111));
+        bits.align();
+        assert_eq!(bits.read_bits(8), Some(0b10101010));
+    }
+
+    #[test]
+    fn test_write_bits() {
+        let bytes = do io::with_bytes_writer |wr| {
+            let mut bits = BitWriter::new(wr);
+            bits.write_bit(true);
+            bits.write_bit(false);
+            bits.write_bits(0b1101, 4);
+            bits.write_bits(0b1011, 4);
+            bits.flush();
+        };
+        assert_eq!(bytes, ~[0b10110110, 0b11000000]);
+    }
+
+    #[test]
+    fn test_flush_on_boundary_is_noop() {
+        let bytes = do io::with_bytes_writer |wr| {
+            let mut bits = BitWriter::new(wr);
+            bits.write_bits(0b10100101, 8);
+            bits.flush();
+            bits.flush();
+        };
+        assert_eq!(bytes, ~[0b10100101]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bytes = do io::with_bytes_writer |wr| {
+            let mut bits = BitWriter::new(wr);
+            bits.write_bits(0x3a, 7);
+            bits.write_bits(0x1f5, 9);
+            bits.flush();
+        };
+        let rd = @BufReader::new(bytes) as @io::Reader;
+        let mut bits = BitReader::new(rd);
+        assert_eq!(bits.read_bits(7), Some(0x3a));
+        assert_eq!(bits.read_bits(9), Some(0x1f5));
+    }
+}
@@ -0,0 +1,518 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A two-dimensional packed boolean matrix. Rows are stored contiguously,
+ * each padded to a word boundary, so whole-row operations (union, copy)
+ * proceed a word at a time. Useful for graph adjacency and dataflow
+ * gen/kill tables that are otherwise emulated with `~[Bitv]`.
+ */
+
+use bitv;
+use bitv::{Bitv, BitvSet};
+use smallintmap::SmallIntMap;
+
+use std::uint;
+use std::vec;
+
+/// The packed boolean matrix type
+pub struct BitMatrix {
+    /// The number of rows
+    priv nrows: uint,
+    /// The number of columns in each row
+    priv ncols: uint,
+    /// Row-major packed storage; each row occupies a whole number of words
+    priv storage: ~[uint]
+}
+
+impl BitMatrix {
+    /// Create a matrix of the given dimensions with every bit set to `init`
+    pub fn new(nrows: uint, ncols: uint, init: bool) -> BitMatrix {
+        let words_per_row = uint::div_ceil(ncols, uint::bits);
+        let elem = if init {!0u} else {0u};
+        let mut m = BitMatrix{
+            nrows: nrows,
+            ncols: ncols,
+            storage: vec::from_elem(nrows * words_per_row, elem)
+        };
+        if init {
+            m.mask_tails();
+        }
+        m
+    }
+
+    /// The number of rows
+    pub fn row_len(&self) -> uint { self.nrows }
+
+    /// The number of columns
+    pub fn col_len(&self) -> uint { self.ncols }
+
+    /// The number of words used to store one row
+    fn words_per_row(&self) -> uint {
+        uint::div_ceil(self.ncols, uint::bits)
+    }
+
+    /// A mask covering the defined bits of the last word of a row
+    fn tail_mask(&self) -> uint {
+        let rmd = self.ncols % uint::bits;
+        if rmd == 0 {!0} else {(1 << rmd) - 1}
+    }
+
+    /// Clear the undefined bits in the final word of every row
+    fn mask_tails(&mut self) {
+        let wpr = self.words_per_row();
+        if wpr == 0 { return; }
+        let mask = self.tail_mask();
+        for uint::range(0, self.nrows) |r| {
+            self.storage[r * wpr + wpr - 1] &= mask;
+        }
+    }
+
+    /// Retrieve the value at row `r`, column `c`
+    pub fn get(&self, r: uint, c: uint) -> bool {
+        assert!(r < self.nrows);
+        assert!(c < self.ncols);
+        let w = r * self.words_per_row() + c / uint::bits;
+        self.storage[w] & (1 << (c % uint::bits)) != 0
+    }
+
+    /// Set the value at row `r`, column `c`
+    pub fn set(&mut self, r: uint, c: uint, x: bool) {
+        assert!(r < self.nrows);
+        assert!(c < self.ncols);
+        let w = r * self.words_per_row() + c / uint::bits;
+        let flag = 1 << (c % uint::bits);
+        self.storage[w] = if x { self.storage[w] | flag }
+                          else { self.storage[w] & !flag };
+    }
+
+    /// Extract row `r` as a freshly allocated Bitv
+    pub fn row(&self, r: uint) -> Bitv {
+        assert!(r < self.nrows);
+        bitv::from_fn(self.ncols, |c| self.get(r, c))
+    }
+
+    /// Store the contents of a Bitv into row `r`. The vector must have
+    /// exactly as many bits as the matrix has columns.
+    pub fn set_row(&mut self, r: uint, row: &Bitv) {
+        assert!(r < self.nrows);
+        let mut c = 0;
+        for row.each |b| {
+            self.set(r, c, b);
+            c += 1;
+        }
+        assert_eq!(c, self.ncols);
+    }
+
+    /// Union row `src` into row `dest` a word at a time. Returns true if
+    /// `dest` changed.
+    pub fn union_row(&mut self, dest: uint, src: uint) -> bool {
+        assert!(dest < self.nrows);
+        assert!(src < self.nrows);
+        let wpr = self.words_per_row();
+        let mut changed = false;
+        for uint::range(0, wpr) |i| {
+            let w = self.storage[dest * wpr + i] | self.storage[src * wpr + i];
+            if w != self.storage[dest * wpr + i] {
+                changed = true;
+                self.storage[dest * wpr + i] = w;
+            }
+        }
+        changed
+    }
+
+    /// Overwrite row `dest` with a copy of row `src`
+    pub fn copy_row(&mut self, dest: uint, src: uint) {
+        assert!(dest < self.nrows);
+        assert!(src < self.nrows);
+        let wpr = self.words_per_row();
+        for uint::range(0, wpr) |i| {
+            self.storage[dest * wpr + i] = self.storage[src * wpr + i];
+        }
+    }
+
+    /// Return the transposed matrix
+    pub fn transpose(&self) -> BitMatrix {
+        let mut m = BitMatrix::new(self.ncols, self.nrows, false);
+        for uint::range(0, self.nrows) |r| {
+            for uint::range(0, self.ncols) |c| {
+                if self.get(r, c) {
+                    m.set(c, r, true);
+                }
+            }
+        }
+        m
+    }
+
+    /// Boolean matrix product: `result[i][j]` is set iff there is some `k`
+    /// with `self[i][k]` and `other[k][j]`. `self` must have as many
+    /// columns as `other` has rows. Rows of `other` are ORed into the
+    /// result a word at a time, one union per set bit of a row of `self`.
+    pub fn multiply(&self, other: &BitMatrix) -> BitMatrix {
+        assert_eq!(self.ncols, other.nrows);
+        let mut result = BitMatrix::new(self.nrows, other.ncols, false);
+        let wpr = result.words_per_row();
+        for uint::range(0, self.nrows) |i| {
+            for uint::range(0, self.ncols) |k| {
+                if self.get(i, k) {
+                    for uint::range(0, wpr) |w| {
+                        result.storage[i * wpr + w] |=
+                            other.storage[k * wpr + w];
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// The transitive closure of a square matrix viewed as an adjacency
+    /// relation, computed with Warshall's algorithm using word-parallel
+    /// row unions. A node reaches itself only via an actual cycle.
+    pub fn transitive_closure(&self) -> BitMatrix {
+        assert_eq!(self.nrows, self.ncols);
+        let mut closure = self.clone();
+        for uint::range(0, closure.nrows) |k| {
+            for uint::range(0, closure.nrows) |i| {
+                if closure.get(i, k) {
+                    closure.union_row(i, k);
+                }
+            }
+        }
+        closure
+    }
+
+    /// The set of columns reachable from `row` by following one or more
+    /// edges, as a Bitv with one bit per column. `row` itself is included
+    /// only when it lies on a cycle.
+    pub fn reachable_from(&self, row: uint) -> Bitv {
+        assert_eq!(self.nrows, self.ncols);
+        assert!(row < self.nrows);
+        let wpr = self.words_per_row();
+        let mut reached = vec::from_elem(wpr, 0u);
+        let mut worklist = ~[row];
+        while !worklist.is_empty() {
+            let n = worklist.pop();
+            for uint::range(0, wpr) |w| {
+                let new = self.storage[n * wpr + w] & !reached[w];
+                if new != 0 {
+                    reached[w] |= new;
+                    for bitv::iterate_bits(w * uint::bits, new) |b| {
+                        worklist.push(b);
+                    }
+                }
+            }
+        }
+        bitv::from_fn(self.ncols, |c| {
+            reached[c / uint::bits] & (1 << (c % uint::bits)) != 0
+        })
+    }
+
+    /// Visit every element in row-major order
+    pub fn each(&self, f: &fn(uint, uint, bool) -> bool) -> bool {
+        for uint::range(0, self.nrows) |r| {
+            for uint::range(0, self.ncols) |c| {
+                if !f(r, c, self.get(r, c)) {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+
+    /// Visit each row in order as a freshly extracted Bitv
+    pub fn each_row(&self, f: &fn(uint, &Bitv) -> bool) -> bool {
+        for uint::range(0, self.nrows) |r| {
+            if !f(r, &self.row(r)) {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+impl Clone for BitMatrix {
+    fn clone(&self) -> BitMatrix {
+        BitMatrix{
+            nrows: self.nrows,
+            ncols: self.ncols,
+            storage: self.storage.clone()
+        }
+    }
+}
+
+/// A boolean matrix that stores only its non-empty rows, each as a
+/// `BitvSet`. Row and column indices are unbounded. This is the usual
+/// shape for reachability and points-to analyses, where most rows stay
+/// empty and the interesting operation is unioning one row into another
+/// until nothing changes.
+pub struct SparseBitMatrix {
+    /// The non-empty rows, keyed by row index
+    priv rows: SmallIntMap<BitvSet>
+}
+
+impl SparseBitMatrix {
+    /// Create an empty matrix
+    pub fn new() -> SparseBitMatrix {
+        SparseBitMatrix{rows: SmallIntMap::new()}
+    }
+
+    /// The number of rows with at least one set cell
+    pub fn row_count(&self) -> uint { self.rows.len() }
+
+    /// Set the cell at (`row`, `col`). Return true if it was not
+    /// already set.
+    pub fn add(&mut self, row: uint, col: uint) -> bool {
+        match self.rows.find_mut(&row) {
+            Some(bits) => return bits.insert(col),
+            None => ()
+        }
+        let mut bits = BitvSet::new();
+        bits.insert(col);
+        self.rows.insert(row, bits);
+        true
+    }
+
+    /// Return true if the cell at (`row`, `col`) is set
+    pub fn contains(&self, row: uint, col: uint) -> bool {
+        match self.rows.find(&row) {
+            Some(bits) => bits.contains(&col),
+            None => false
+        }
+    }
+
+    /// Borrow a row's set of columns, if the row is non-empty
+    pub fn row<'a>(&'a self, row: uint) -> Option<&'a BitvSet> {
+        self.rows.find(&row)
+    }
+
+    /// Union row `src` into row `dst`. Return true if `dst` gained any
+    /// cells.
+    pub fn union_rows(&mut self, src: uint, dst: uint) -> bool {
+        if src == dst {
+            return false;
+        }
+        // take the source row out so both rows can be touched at once
+        match self.rows.pop(&src) {
+            None => false,
+            Some(srow) => {
+                if !self.rows.contains_key(&dst) {
+                    self.rows.insert(dst, BitvSet::new());
+                }
+                let changed;
+                {
+                    let drow = self.rows.find_mut(&dst).unwrap();
+                    let before = drow.len();
+                    drow.union_with(&srow);
+                    changed = drow.len() != before;
+                }
+                self.rows.insert(src, srow);
+                changed
+            }
+        }
+    }
+
+    /// Visit every set cell in row-major order
+    pub fn each_cell(&self, f: &fn(uint, uint) -> bool) -> bool {
+        for self.rows.each |&r, bits| {
+            for bits.each |&c| {
+                if !f(r, c) {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic() {
+        let mut m = BitMatrix::new(3, 70, false);
+        assert_eq!(m.row_len(), 3);
+        assert_eq!(m.col_len(), 70);
+        assert!(!m.get(0, 0));
+        m.set(0, 0, true);
+        m.set(2, 69, true);
+        assert!(m.get(0, 0));
+        assert!(m.get(2, 69));
+        assert!(!m.get(1, 35));
+        m.set(0, 0, false);
+        assert!(!m.get(0, 0));
+    }
+
+    #[test]
+    fn test_new_init_true() {
+        let m = BitMatrix::new(2, 70, true);
+        for m.each |_r, _c, b| {
+            assert!(b);
+        }
+    }
+
+    #[test]
+    fn test_row_round_trip() {
+        let mut m = BitMatrix::new(2, 10, false);
+        m.set(1, 3, true);
+        m.set(1, 9, true);
+        let row = m.row(1);
+        assert!(row.eq_vec(~[0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 1u]));
+        m.set_row(0, &row);
+        assert!(m.get(0, 3));
+        assert!(m.get(0, 9));
+    }
+
+    #[test]
+    fn test_union_and_copy_row() {
+        let mut m = BitMatrix::new(3, 100, false);
+        m.set(0, 1, true);
+        m.set(1, 99, true);
+        assert!(m.union_row(0, 1));
+        assert!(m.get(0, 1));
+        assert!(m.get(0, 99));
+        // unioning again changes nothing
+        assert!(!m.union_row(0, 1));
+
+        m.copy_row(2, 0);
+        assert!(m.get(2, 1));
+        assert!(m.get(2, 99));
+    }
+
+    #[test]
+    fn test_multiply() {
+        // 0 -> 1, 1 -> 2 composed with itself gives 0 -> 2
+        let mut m = BitMatrix::new(3, 3, false);
+        m.set(0, 1, true);
+        m.set(1, 2, true);
+        let sq = m.multiply(&m);
+        assert!(sq.get(0, 2));
+        assert!(!sq.get(0, 1));
+        assert!(!sq.get(1, 2));
+    }
+
+    #[test]
+    fn test_transitive_closure() {
+        let mut m = BitMatrix::new(4, 4, false);
+        m.set(0, 1, true);
+        m.set(1, 2, true);
+        m.set(3, 3, true);
+        let c = m.transitive_closure();
+        assert!(c.get(0, 1));
+        assert!(c.get(0, 2));
+        assert!(c.get(1, 2));
+        assert!(!c.get(2, 0));
+        assert!(!c.get(0, 0));
+        assert!(c.get(3, 3));
+    }
+
+    #[test]
+    fn test_reachable_from() {
+        let mut m = BitMatrix::new(70, 70, false);
+        m.set(0, 1, true);
+        m.set(1, 65, true);
+        m.set(65, 0, true);
+        m.set(2, 3, true);
+        let r = m.reachable_from(0);
+        assert!(r[1]);
+        assert!(r[65]);
+        assert!(r[0]); // on a cycle
+        assert!(!r[2]);
+        assert!(!r[3]);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let mut m = BitMatrix::new(2, 5, false);
+        m.set(0, 4, true);
+        m.set(1, 2, true);
+        let t = m.transpose();
+        assert_eq!(t.row_len(), 5);
+        assert_eq!(t.col_len(), 2);
+        assert!(t.get(4, 0));
+        assert!(t.get(2, 1));
+        assert!(!t.get(0, 0));
+    }
+}
+
+#[cfg(test)]
+mod sparse_tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_contains() {
+        let mut m = SparseBitMatrix::new();
+        assert_eq!(m.row_count(), 0);
+        assert!(m.add(3, 1000));
+        assert!(!m.add(3, 1000));
+        assert!(m.add(3, 5));
+        assert!(m.add(90, 0));
+        assert!(m.contains(3, 1000));
+        assert!(m.contains(90, 0));
+        assert!(!m.contains(3, 999));
+        assert!(!m.contains(4, 1000));
+        assert_eq!(m.row_count(), 2);
+    }
+
+    #[test]
+    fn test_union_rows_reports_changes() {
+        let mut m = SparseBitMatrix::new();
+        assert!(m.add(0, 1));
+        assert!(m.add(0, 2));
+        assert!(m.add(1, 2));
+
+        assert!(m.union_rows(0, 1));
+        assert!(m.contains(1, 1));
+        assert!(m.contains(1, 2));
+        assert!(!m.union_rows(0, 1));
+        assert!(!m.union_rows(0, 0));
+        // the source row is untouched
+        assert!(m.contains(0, 1));
+        assert!(!m.contains(0, 3));
+
+        // unioning into a previously empty row creates it
+        assert!(m.union_rows(0, 7));
+        assert!(m.contains(7, 1));
+
+        // unioning from an empty row is a no-op
+        assert!(!m.union_rows(50, 0));
+    }
+
+    #[test]
+    fn test_each_cell() {
+        let mut m = SparseBitMatrix::new();
+        assert!(m.add(5, 2));
+        assert!(m.add(1, 9));
+        assert!(m.add(5, 0));
+        let mut observed = ~[];
+        for m.each_cell |r, c| {
+            observed.push((r, c));
+        }
+        assert_eq!(observed, ~[(1u, 9u), (5u, 0u), (5u, 2u)]);
+    }
+
+    #[test]
+    fn test_transitive_closure_by_iteration() {
+        // 0 -> 1 -> 2; repeatedly union successor rows into predecessor
+        // rows until a fixed point, as a points-to loop would
+        let mut m = SparseBitMatrix::new();
+        assert!(m.add(0, 1));
+        assert!(m.add(1, 2));
+        let mut changed = true;
+        while changed {
+            changed = false;
+            // edges: propagate row of each target into its source
+            if m.union_rows(1, 0) { changed = true; }
+            if m.union_rows(2, 1) { changed = true; }
+        }
+        assert!(m.contains(0, 2));
+        assert!(!m.contains(2, 0));
+    }
+}
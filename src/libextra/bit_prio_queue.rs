@@ -0,0 +1,184 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A priority queue for bounded integer priorities. Each priority has a
+ * FIFO bucket of items; a two-level bitmap (one bit per priority, plus
+ * a summary bit per occupancy word) lets `pop_min` locate the lowest
+ * occupied priority with two word scans instead of a heap traversal.
+ * Items of equal priority come out in insertion order.
+ */
+
+use std::uint;
+use std::vec;
+
+/// The radix priority queue type
+pub struct BitPriorityQueue<T> {
+    /// One FIFO of items per priority
+    priv buckets: ~[~[T]],
+    /// One bit per priority: set while its bucket is non-empty
+    priv occupied: ~[uint],
+    /// One bit per word of `occupied`: set while the word is non-zero
+    priv summary: ~[uint],
+    /// The number of queued items
+    priv size: uint
+}
+
+/// The index of the lowest set bit of a nonzero word
+fn lowest_bit(w: uint) -> uint {
+    let mut i = 0;
+    while w & (1 << i) == 0 { i += 1; }
+    i
+}
+
+impl<T> Container for BitPriorityQueue<T> {
+    /// Return the number of queued items
+    fn len(&self) -> uint { self.size }
+
+    /// Return true if the queue holds no items
+    fn is_empty(&self) -> bool { self.size == 0 }
+}
+
+impl<T> Mutable for BitPriorityQueue<T> {
+    /// Drop all queued items
+    fn clear(&mut self) {
+        for self.buckets.mut_iter().advance |bucket| {
+            bucket.clear();
+        }
+        for self.occupied.mut_iter().advance |w| { *w = 0; }
+        for self.summary.mut_iter().advance |w| { *w = 0; }
+        self.size = 0;
+    }
+}
+
+impl<T> BitPriorityQueue<T> {
+    /// Create a queue accepting priorities in `[0, nprios)`
+    pub fn new(nprios: uint) -> BitPriorityQueue<T> {
+        let nwords = uint::div_ceil(nprios, uint::bits);
+        BitPriorityQueue{
+            buckets: vec::from_fn(nprios, |_| ~[]),
+            occupied: vec::from_elem(nwords, 0),
+            summary: vec::from_elem(uint::div_ceil(nwords, uint::bits), 0),
+            size: 0
+        }
+    }
+
+    /// The number of priorities the queue accepts
+    pub fn prio_limit(&self) -> uint { self.buckets.len() }
+
+    /// Queue an item at the given priority, behind any items already
+    /// queued there
+    pub fn push(&mut self, prio: uint, item: T) {
+        assert!(prio < self.buckets.len());
+        self.buckets[prio].push(item);
+        let w = prio / uint::bits;
+        self.occupied[w] |= 1 << (prio % uint::bits);
+        self.summary[w / uint::bits] |= 1 << (w % uint::bits);
+        self.size += 1;
+    }
+
+    /// The lowest priority with a queued item, if any
+    pub fn min_prio(&self) -> Option<uint> {
+        for self.summary.iter().enumerate().advance |(i, &s)| {
+            if s != 0 {
+                let w = i * uint::bits + lowest_bit(s);
+                return Some(w * uint::bits + lowest_bit(self.occupied[w]));
+            }
+        }
+        None
+    }
+
+    /// Remove and return the oldest item at the lowest occupied
+    /// priority, along with that priority
+    pub fn pop_min(&mut self) -> Option<(uint, T)> {
+        let prio = match self.min_prio() {
+            None => return None,
+            Some(prio) => prio
+        };
+        let item = self.buckets[prio].shift();
+        if self.buckets[prio].is_empty() {
+            let w = prio / uint::bits;
+            self.occupied[w] &= !(1 << (prio % uint::bits));
+            if self.occupied[w] == 0 {
+                self.summary[w / uint::bits] &= !(1 << (w % uint::bits));
+            }
+        }
+        self.size -= 1;
+        Some((prio, item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::uint;
+
+    #[test]
+    fn test_pops_in_priority_order() {
+        let mut q = BitPriorityQueue::new(1000);
+        q.push(500, ~"c");
+        q.push(3, ~"a");
+        q.push(900, ~"d");
+        q.push(77, ~"b");
+        assert_eq!(q.len(), 4);
+        assert_eq!(q.min_prio(), Some(3));
+        assert_eq!(q.pop_min(), Some((3, ~"a")));
+        assert_eq!(q.pop_min(), Some((77, ~"b")));
+        assert_eq!(q.pop_min(), Some((500, ~"c")));
+        assert_eq!(q.pop_min(), Some((900, ~"d")));
+        assert_eq!(q.pop_min(), None);
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn test_equal_priority_is_fifo() {
+        let mut q = BitPriorityQueue::new(8);
+        q.push(4, 1);
+        q.push(4, 2);
+        q.push(4, 3);
+        assert_eq!(q.pop_min(), Some((4, 1)));
+        q.push(4, 4);
+        assert_eq!(q.pop_min(), Some((4, 2)));
+        assert_eq!(q.pop_min(), Some((4, 3)));
+        assert_eq!(q.pop_min(), Some((4, 4)));
+    }
+
+    #[test]
+    fn test_refill_emptied_bucket() {
+        let mut q = BitPriorityQueue::new(2 * uint::bits);
+        q.push(uint::bits + 1, 'x');
+        assert_eq!(q.pop_min(), Some((uint::bits + 1, 'x')));
+        assert_eq!(q.min_prio(), None);
+        q.push(uint::bits + 1, 'y');
+        q.push(0, 'z');
+        assert_eq!(q.pop_min(), Some((0, 'z')));
+        assert_eq!(q.pop_min(), Some((uint::bits + 1, 'y')));
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_out_of_range_priority_fails() {
+        let mut q = BitPriorityQueue::new(4);
+        q.push(4, 0);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut q = BitPriorityQueue::new(64);
+        q.push(10, 1);
+        q.push(20, 2);
+        q.clear();
+        assert!(q.is_empty());
+        assert_eq!(q.pop_min(), None);
+        q.push(30, 3);
+        assert_eq!(q.pop_min(), Some((30, 3)));
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A bit-sliced index over fixed-width record signatures: slice `b`
+ * holds one bit per record, set when bit `b` of that record's
+ * signature is set. Multi-attribute queries then reduce to ANDing or
+ * ORing a few slices, visiting each candidate record once per word
+ * instead of once per record — the classic bitmap-index layout.
+ */
+
+use bitv::BitvSet;
+
+use std::uint;
+
+/// The bit-sliced index type
+pub struct BitSlicedIndex {
+    /// The signature width in bits
+    priv width: uint,
+    /// One record set per signature bit position
+    priv slices: ~[BitvSet],
+    /// Every record inserted so far
+    priv records: BitvSet
+}
+
+/// Copies a BitvSet; the set type itself offers no clone
+fn copy_set(s: &BitvSet) -> BitvSet {
+    let mut r = BitvSet::new();
+    r.union_with(s);
+    r
+}
+
+impl Container for BitSlicedIndex {
+    /// Return the number of indexed records
+    fn len(&self) -> uint { self.records.len() }
+
+    /// Return true if no records are indexed
+    fn is_empty(&self) -> bool { self.records.is_empty() }
+}
+
+impl Mutable for BitSlicedIndex {
+    /// Remove every record, keeping the signature width
+    fn clear(&mut self) {
+        for self.slices.mut_iter().advance |slice| {
+            slice.clear();
+        }
+        self.records.clear();
+    }
+}
+
+impl BitSlicedIndex {
+    /// Create an index over signatures of `width` bits
+    pub fn new(width: uint) -> BitSlicedIndex {
+        assert!(width > 0 && width <= uint::bits);
+        let mut slices = ~[];
+        for width.times {
+            slices.push(BitvSet::new());
+        }
+        BitSlicedIndex{width: width, slices: slices, records: BitvSet::new()}
+    }
+
+    /// The signature width in bits
+    pub fn width(&self) -> uint { self.width }
+
+    /// Index a record under its signature, replacing any signature the
+    /// record was previously indexed under
+    pub fn insert(&mut self, record_id: uint, signature: uint) {
+        assert!(self.width == uint::bits ||
+                signature < (1 << self.width));
+        for uint::range(0, self.width) |b| {
+            if signature & (1 << b) != 0 {
+                self.slices[b].insert(record_id);
+            } else {
+                self.slices[b].remove(&record_id);
+            }
+        }
+        self.records.insert(record_id);
+    }
+
+    /// Remove a record from the index. Return true if it was present.
+    pub fn remove(&mut self, record_id: uint) -> bool {
+        if !self.records.remove(&record_id) {
+            return false;
+        }
+        for self.slices.mut_iter().advance |slice| {
+            slice.remove(&record_id);
+        }
+        true
+    }
+
+    /// The signature a record was indexed under, if any
+    pub fn signature(&self, record_id: uint) -> Option<uint> {
+        if !self.records.contains(&record_id) {
+            return None;
+        }
+        let mut sig = 0;
+        for uint::range(0, self.width) |b| {
+            if self.slices[b].contains(&record_id) {
+                sig |= 1 << b;
+            }
+        }
+        Some(sig)
+    }
+
+    /// The records whose signatures have every bit of `mask` set
+    pub fn with_all(&self, mask: uint) -> BitvSet {
+        let mut candidates = copy_set(&self.records);
+        for uint::range(0, self.width) |b| {
+            if mask & (1 << b) != 0 {
+                candidates.intersect_with(&self.slices[b]);
+            }
+        }
+        candidates
+    }
+
+    /// The records whose signatures have at least one bit of `mask` set
+    pub fn with_any(&self, mask: uint) -> BitvSet {
+        let mut candidates = BitvSet::new();
+        for uint::range(0, self.width) |b| {
+            if mask & (1 << b) != 0 {
+                candidates.union_with(&self.slices[b]);
+            }
+        }
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitv::BitvSet;
+
+    fn expect(set: &BitvSet, ids: &[uint]) {
+        let mut expected = BitvSet::new();
+        for ids.iter().advance |&id| {
+            expected.insert(id);
+        }
+        assert!(*set == expected);
+    }
+
+    #[test]
+    fn test_with_all() {
+        let mut ix = BitSlicedIndex::new(4);
+        ix.insert(1, 0b0011);
+        ix.insert(2, 0b0110);
+        ix.insert(3, 0b0111);
+        ix.insert(100, 0b1111);
+        assert_eq!(ix.len(), 4);
+        expect(&ix.with_all(0b0010), [1, 2, 3, 100]);
+        expect(&ix.with_all(0b0011), [1, 3, 100]);
+        expect(&ix.with_all(0b0110), [2, 3, 100]);
+        expect(&ix.with_all(0b1000), [100]);
+        expect(&ix.with_all(0), [1, 2, 3, 100]);
+    }
+
+    #[test]
+    fn test_with_any() {
+        let mut ix = BitSlicedIndex::new(3);
+        ix.insert(0, 0b001);
+        ix.insert(1, 0b010);
+        ix.insert(2, 0b100);
+        expect(&ix.with_any(0b011), [0, 1]);
+        expect(&ix.with_any(0b100), [2]);
+        expect(&ix.with_any(0), []);
+    }
+
+    #[test]
+    fn test_reinsert_replaces_signature() {
+        let mut ix = BitSlicedIndex::new(2);
+        ix.insert(7, 0b11);
+        ix.insert(7, 0b01);
+        assert_eq!(ix.len(), 1);
+        assert_eq!(ix.signature(7), Some(0b01));
+        expect(&ix.with_all(0b10), []);
+        expect(&ix.with_all(0b01), [7]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut ix = BitSlicedIndex::new(2);
+        ix.insert(5, 0b11);
+        assert!(ix.remove(5));
+        assert!(!ix.remove(5));
+        assert_eq!(ix.signature(5), None);
+        assert!(ix.is_empty());
+        expect(&ix.with_all(0b01), []);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut ix = BitSlicedIndex::new(3);
+        ix.insert(1, 0b101);
+        ix.clear();
+        assert!(ix.is_empty());
+        expect(&ix.with_any(0b111), []);
+        ix.insert(2, 0b010);
+        expect(&ix.with_all(0b010), [2]);
+    }
+}
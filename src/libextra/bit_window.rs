@@ -0,0 +1,241 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A sliding window over the last `capacity` bits of a stream. Pushing
+ * a bit evicts the oldest once the window is full, and a running count
+ * of set bits is maintained incrementally, so the popcount of the
+ * window is always a field read — the bookkeeping rate limiters and
+ * signal-quality monitors otherwise reinvent.
+ */
+
+use bitv::Bitv;
+
+use std::uint;
+use std::vec;
+
+/// The sliding bit window type
+pub struct BitWindow {
+    /// The bits, as a ring of words
+    priv storage: ~[uint],
+    /// The most bits the window holds
+    priv cap: uint,
+    /// Physical bit index of the oldest bit
+    priv head: uint,
+    /// The number of bits currently in the window
+    priv nbits: uint,
+    /// The number of set bits currently in the window
+    priv ones: uint
+}
+
+impl Container for BitWindow {
+    /// Return the number of bits in the window
+    fn len(&self) -> uint { self.nbits }
+
+    /// Return true if the window holds no bits
+    fn is_empty(&self) -> bool { self.nbits == 0 }
+}
+
+impl Mutable for BitWindow {
+    /// Empty the window
+    fn clear(&mut self) {
+        for self.storage.mut_iter().advance |w| { *w = 0; }
+        self.head = 0;
+        self.nbits = 0;
+        self.ones = 0;
+    }
+}
+
+impl BitWindow {
+    /// Create a window over the last `capacity` bits pushed
+    pub fn new(capacity: uint) -> BitWindow {
+        assert!(capacity > 0);
+        BitWindow{
+            storage: vec::from_elem(uint::div_ceil(capacity, uint::bits), 0),
+            cap: capacity,
+            head: 0,
+            nbits: 0,
+            ones: 0
+        }
+    }
+
+    /// The most bits the window holds
+    pub fn capacity(&self) -> uint { self.cap }
+
+    /// Return true if pushing another bit will evict the oldest
+    pub fn is_full(&self) -> bool { self.nbits == self.cap }
+
+    /// The number of set bits in the window
+    pub fn count_ones(&self) -> uint { self.ones }
+
+    /// The number of clear bits in the window
+    pub fn count_zeros(&self) -> uint { self.nbits - self.ones }
+
+    fn get_phys(&self, pos: uint) -> bool {
+        self.storage[pos / uint::bits] & (1 << (pos % uint::bits)) != 0
+    }
+
+    fn set_phys(&mut self, pos: uint, x: bool) {
+        let w = pos / uint::bits;
+        let b = 1 << (pos % uint::bits);
+        if x {
+            self.storage[w] |= b;
+        } else {
+            self.storage[w] &= !b;
+        }
+    }
+
+    /// Push a bit into the window, evicting and returning the oldest
+    /// bit if the window was full
+    pub fn push(&mut self, bit: bool) -> Option<bool> {
+        if self.nbits == self.cap {
+            // the slot after the newest bit is the oldest bit's
+            let evicted = self.get_phys(self.head);
+            if evicted {
+                self.ones -= 1;
+            }
+            self.set_phys(self.head, bit);
+            self.head = (self.head + 1) % self.cap;
+            if bit {
+                self.ones += 1;
+            }
+            Some(evicted)
+        } else {
+            let tail = (self.head + self.nbits) % self.cap;
+            self.set_phys(tail, bit);
+            self.nbits += 1;
+            if bit {
+                self.ones += 1;
+            }
+            None
+        }
+    }
+
+    /// The `i`th oldest bit in the window
+    pub fn get(&self, i: uint) -> bool {
+        assert!(i < self.nbits);
+        self.get_phys((self.head + i) % self.cap)
+    }
+
+    /// Visit the bits in the window, oldest first
+    pub fn each(&self, f: &fn(bool) -> bool) -> bool {
+        for uint::range(0, self.nbits) |i| {
+            if !f(self.get(i)) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Visit the window packed into words, oldest bit first in the low
+    /// bit of the first word. Each call gets `(word, nbits)` where
+    /// `nbits` is the number of valid low bits, a full word except
+    /// possibly at the end.
+    pub fn each_word(&self, f: &fn(uint, uint) -> bool) -> bool {
+        let mut i = 0;
+        while i < self.nbits {
+            let n = uint::min(uint::bits, self.nbits - i);
+            let mut word = 0;
+            for uint::range(0, n) |b| {
+                if self.get(i + b) {
+                    word |= 1 << b;
+                }
+            }
+            if !f(word, n) {
+                return false;
+            }
+            i += n;
+        }
+        return true;
+    }
+
+    /// Copy the window into a Bitv, oldest bit first
+    pub fn to_bitv(&self) -> Bitv {
+        let mut bitv = Bitv::new(self.nbits, false);
+        for uint::range(0, self.nbits) |i| {
+            bitv.set(i, self.get(i));
+        }
+        bitv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::uint;
+
+    #[test]
+    fn test_fill_and_evict() {
+        let mut w = BitWindow::new(3);
+        assert_eq!(w.push(true), None);
+        assert_eq!(w.push(false), None);
+        assert_eq!(w.push(true), None);
+        assert!(w.is_full());
+        assert_eq!(w.count_ones(), 2);
+        // evicts the initial true
+        assert_eq!(w.push(false), Some(true));
+        assert_eq!(w.count_ones(), 1);
+        assert_eq!(w.push(false), Some(false));
+        assert_eq!(w.push(true), Some(true));
+        assert_eq!(w.count_ones(), 1);
+        assert!(!w.get(0) && !w.get(1) && w.get(2));
+    }
+
+    #[test]
+    fn test_rolling_popcount_matches_recount() {
+        let mut w = BitWindow::new(100);
+        for uint::range(0, 1000) |i| {
+            w.push(i % 3 == 0);
+            let mut recount = 0;
+            for w.each |bit| {
+                if bit { recount += 1; }
+            }
+            assert_eq!(w.count_ones(), recount);
+            assert_eq!(w.count_zeros(), w.len() - recount);
+        }
+        assert_eq!(w.len(), 100);
+    }
+
+    #[test]
+    fn test_each_word() {
+        let mut w = BitWindow::new(uint::bits + 4);
+        for uint::range(0, uint::bits + 4) |i| {
+            w.push(i == 1 || i == uint::bits);
+        }
+        let mut observed = ~[];
+        for w.each_word |word, n| {
+            observed.push((word, n));
+        }
+        assert_eq!(observed, ~[(2u, uint::bits), (1u, 4u)]);
+    }
+
+    #[test]
+    fn test_to_bitv() {
+        let mut w = BitWindow::new(4);
+        for [true, true, false, true, false].iter().advance |&b| {
+            w.push(b);
+        }
+        // the first push has been evicted
+        assert!(w.to_bitv().eq_vec(~[1u, 0u, 1u, 0u]));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut w = BitWindow::new(8);
+        w.push(true);
+        w.push(true);
+        w.clear();
+        assert!(w.is_empty());
+        assert_eq!(w.count_ones(), 0);
+        w.push(true);
+        assert_eq!(w.count_ones(), 1);
+    }
+}
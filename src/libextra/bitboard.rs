@@ -0,0 +1,285 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * Bitboards for 8x8 domains. A board is a single u64 with one bit per
+ * square, indexed rank-major from a1 = 0 to h8 = 63, and the usual
+ * shifted-fill and attack-mask helpers that board game engines keep
+ * re-deriving: directional shifts that do not wrap across files, file
+ * fills, knight and king attack masks, and mirroring.
+ */
+
+use std::uint;
+
+/// The a-file
+pub static FILE_A: u64 = 0x0101010101010101;
+/// The h-file
+pub static FILE_H: u64 = 0x8080808080808080;
+/// The first rank
+pub static RANK_1: u64 = 0x00000000000000ff;
+/// The eighth rank
+pub static RANK_8: u64 = 0xff00000000000000;
+
+/// A set of squares on an 8x8 board
+#[deriving(Clone, Eq)]
+pub struct Bitboard {
+    /// One bit per square, rank-major with a1 in the low bit
+    bits: u64
+}
+
+/// The board containing exactly the square at (`file`, `rank`)
+pub fn square(file: uint, rank: uint) -> Bitboard {
+    assert!(file < 8 && rank < 8);
+    Bitboard{bits: 1u64 << (rank * 8 + file)}
+}
+
+impl Bitboard {
+    /// The empty board
+    pub fn empty() -> Bitboard { Bitboard{bits: 0} }
+
+    /// Return true if no squares are set
+    pub fn is_empty(&self) -> bool { self.bits == 0 }
+
+    /// The number of squares set
+    pub fn count(&self) -> uint {
+        let mut w = self.bits;
+        let mut n = 0;
+        while w != 0 {
+            w &= w - 1;
+            n += 1;
+        }
+        n
+    }
+
+    /// Return true if the square with the given index is set
+    pub fn contains(&self, sq: uint) -> bool {
+        assert!(sq < 64);
+        self.bits & (1u64 << sq) != 0
+    }
+
+    /// Set the square with the given index
+    pub fn set(&mut self, sq: uint) {
+        assert!(sq < 64);
+        self.bits |= 1u64 << sq;
+    }
+
+    /// Clear the square with the given index
+    pub fn clear(&mut self, sq: uint) {
+        assert!(sq < 64);
+        self.bits &= !(1u64 << sq);
+    }
+
+    /// Visit the index of each set square, from a1 upward
+    pub fn each_square(&self, f: &fn(uint) -> bool) -> bool {
+        for uint::range(0, 64) |sq| {
+            if self.bits & (1u64 << sq) != 0 {
+                if !f(sq) {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+
+    /// Every square shifted one rank up; bits fall off the top
+    pub fn north(&self) -> Bitboard { Bitboard{bits: self.bits << 8} }
+
+    /// Every square shifted one rank down
+    pub fn south(&self) -> Bitboard { Bitboard{bits: self.bits >> 8} }
+
+    /// Every square shifted one file right, without wrapping
+    pub fn east(&self) -> Bitboard {
+        Bitboard{bits: (self.bits << 1) & !FILE_A}
+    }
+
+    /// Every square shifted one file left, without wrapping
+    pub fn west(&self) -> Bitboard {
+        Bitboard{bits: (self.bits >> 1) & !FILE_H}
+    }
+
+    /// Diagonal shift toward h8
+    pub fn north_east(&self) -> Bitboard {
+        Bitboard{bits: (self.bits << 9) & !FILE_A}
+    }
+
+    /// Diagonal shift toward a8
+    pub fn north_west(&self) -> Bitboard {
+        Bitboard{bits: (self.bits << 7) & !FILE_H}
+    }
+
+    /// Diagonal shift toward h1
+    pub fn south_east(&self) -> Bitboard {
+        Bitboard{bits: (self.bits >> 7) & !FILE_A}
+    }
+
+    /// Diagonal shift toward a1
+    pub fn south_west(&self) -> Bitboard {
+        Bitboard{bits: (self.bits >> 9) & !FILE_H}
+    }
+
+    /// Each set square smeared up to the eighth rank
+    pub fn north_fill(&self) -> Bitboard {
+        let mut g = self.bits;
+        g |= g << 8;
+        g |= g << 16;
+        g |= g << 32;
+        Bitboard{bits: g}
+    }
+
+    /// Each set square smeared down to the first rank
+    pub fn south_fill(&self) -> Bitboard {
+        let mut g = self.bits;
+        g |= g >> 8;
+        g |= g >> 16;
+        g |= g >> 32;
+        Bitboard{bits: g}
+    }
+
+    /// Every file containing at least one set square, filled completely
+    pub fn file_fill(&self) -> Bitboard {
+        Bitboard{bits: self.north_fill().bits | self.south_fill().bits}
+    }
+
+    /// The squares a king on each set square attacks
+    pub fn king_attacks(&self) -> Bitboard {
+        let horiz = self.east().bits | self.west().bits;
+        let spread = self.bits | horiz;
+        Bitboard{bits: horiz | (spread << 8) | (spread >> 8)}
+    }
+
+    /// The squares a knight on each set square attacks
+    pub fn knight_attacks(&self) -> Bitboard {
+        let b = self.bits;
+        let l1 = (b >> 1) & !FILE_H;
+        let l2 = (b >> 2) & !(FILE_H | (FILE_H >> 1));
+        let r1 = (b << 1) & !FILE_A;
+        let r2 = (b << 2) & !(FILE_A | (FILE_A << 1));
+        let h1 = l1 | r1;
+        let h2 = l2 | r2;
+        Bitboard{bits: (h1 << 16) | (h1 >> 16) | (h2 << 8) | (h2 >> 8)}
+    }
+
+    /// The board flipped about the horizontal axis between ranks 4 and 5
+    pub fn flip_vertical(&self) -> Bitboard {
+        let b = self.bits;
+        Bitboard{bits: (b << 56)
+                     | ((b << 40) & 0x00ff000000000000)
+                     | ((b << 24) & 0x0000ff0000000000)
+                     | ((b << 8)  & 0x000000ff00000000)
+                     | ((b >> 8)  & 0x00000000ff000000)
+                     | ((b >> 24) & 0x0000000000ff0000)
+                     | ((b >> 40) & 0x000000000000ff00)
+                     | (b >> 56)}
+    }
+
+    /// The board mirrored about the vertical axis between the d and e
+    /// files
+    pub fn mirror_horizontal(&self) -> Bitboard {
+        let mut b = self.bits;
+        b = ((b >> 1) & 0x5555555555555555) |
+            ((b & 0x5555555555555555) << 1);
+        b = ((b >> 2) & 0x3333333333333333) |
+            ((b & 0x3333333333333333) << 2);
+        b = ((b >> 4) & 0x0f0f0f0f0f0f0f0f) |
+            ((b & 0x0f0f0f0f0f0f0f0f) << 4);
+        Bitboard{bits: b}
+    }
+}
+
+impl BitOr<Bitboard, Bitboard> for Bitboard {
+    fn bitor(&self, other: &Bitboard) -> Bitboard {
+        Bitboard{bits: self.bits | other.bits}
+    }
+}
+
+impl BitAnd<Bitboard, Bitboard> for Bitboard {
+    fn bitand(&self, other: &Bitboard) -> Bitboard {
+        Bitboard{bits: self.bits & other.bits}
+    }
+}
+
+impl BitXor<Bitboard, Bitboard> for Bitboard {
+    fn bitxor(&self, other: &Bitboard) -> Bitboard {
+        Bitboard{bits: self.bits ^ other.bits}
+    }
+}
+
+impl Not<Bitboard> for Bitboard {
+    fn not(&self) -> Bitboard {
+        Bitboard{bits: !self.bits}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square_indexing() {
+        let a1 = square(0, 0);
+        let h8 = square(7, 7);
+        assert!(a1.contains(0));
+        assert!(h8.contains(63));
+        assert_eq!(a1.count(), 1);
+        assert_eq!((a1 | h8).count(), 2);
+    }
+
+    #[test]
+    fn test_shifts_do_not_wrap() {
+        let h4 = square(7, 3);
+        assert!(h4.east().is_empty());
+        assert_eq!(h4.west(), square(6, 3));
+        assert!(h4.north_east().is_empty());
+        assert_eq!(h4.north(), square(7, 4));
+
+        let a8 = square(0, 7);
+        assert!(a8.west().is_empty());
+        assert!(a8.north().is_empty());
+        assert_eq!(a8.south_east(), square(1, 6));
+    }
+
+    #[test]
+    fn test_fills() {
+        let c3 = square(2, 2);
+        assert_eq!(c3.north_fill().count(), 6);
+        assert_eq!(c3.south_fill().count(), 3);
+        assert_eq!(c3.file_fill().count(), 8);
+        assert_eq!(c3.file_fill(), square(2, 0).file_fill());
+    }
+
+    #[test]
+    fn test_king_attacks() {
+        // a king in the middle attacks eight squares, in a corner three
+        assert_eq!(square(4, 4).king_attacks().count(), 8);
+        assert_eq!(square(0, 0).king_attacks().count(), 3);
+        assert_eq!(square(7, 7).king_attacks().count(), 3);
+        assert!(!square(4, 4).king_attacks().contains(4 * 8 + 4));
+    }
+
+    #[test]
+    fn test_knight_attacks() {
+        assert_eq!(square(4, 4).knight_attacks().count(), 8);
+        assert_eq!(square(0, 0).knight_attacks().count(), 2);
+        let from_b1 = square(1, 0).knight_attacks();
+        assert_eq!(from_b1.count(), 3);
+        assert!(from_b1.contains(2 * 8 + 0)); // a3
+        assert!(from_b1.contains(2 * 8 + 2)); // c3
+        assert!(from_b1.contains(1 * 8 + 3)); // d2
+    }
+
+    #[test]
+    fn test_mirroring() {
+        let b = square(1, 0) | square(3, 5);
+        assert_eq!(b.flip_vertical().flip_vertical(), b);
+        assert_eq!(b.mirror_horizontal().mirror_horizontal(), b);
+        assert_eq!(square(0, 0).mirror_horizontal(), square(7, 0));
+        assert_eq!(square(0, 0).flip_vertical(), square(0, 7));
+    }
+}
@@ -10,14 +10,27 @@
 
 #[allow(missing_doc)];
 
+use bigint::{BigDigit, BigUint};
+use morton;
+use serialize;
+use smallintmap::SmallIntSet;
+use treemap::TreeSet;
 
 use std::cmp;
+use std::hashmap::HashSet;
+use std::io;
+use std::num::Zero;
 use std::ops;
+use std::ptr;
+use std::rand;
+use std::rand::RngUtil;
+use std::sys;
 use std::uint;
 use std::vec;
 
 struct SmallBitv {
-    /// only the lowest nbits of this value are used. the rest is undefined.
+    /// only the lowest nbits of this value are used; the `Bitv` layer
+    /// keeps the rest zeroed (see `Bitv::normalize`)
     bits: uint
 }
 
@@ -65,6 +78,26 @@ impl SmallBitv {
         self.bits_op(s.bits, nbits, |u1, u2| u1 & !u2)
     }
 
+    #[inline]
+    pub fn nand(&mut self, s: &SmallBitv, nbits: uint) -> bool {
+        self.bits_op(s.bits, nbits, |u1, u2| !(u1 & u2))
+    }
+
+    #[inline]
+    pub fn nor(&mut self, s: &SmallBitv, nbits: uint) -> bool {
+        self.bits_op(s.bits, nbits, |u1, u2| !(u1 | u2))
+    }
+
+    #[inline]
+    pub fn xnor(&mut self, s: &SmallBitv, nbits: uint) -> bool {
+        self.bits_op(s.bits, nbits, |u1, u2| !(u1 ^ u2))
+    }
+
+    #[inline]
+    pub fn implies(&mut self, s: &SmallBitv, nbits: uint) -> bool {
+        self.bits_op(s.bits, nbits, |u1, u2| !u1 | u2)
+    }
+
     #[inline]
     pub fn get(&self, i: uint) -> bool {
         (self.bits & (1 << i)) != 0
@@ -106,7 +139,7 @@ impl SmallBitv {
     pub fn invert(&mut self) { self.bits = !self.bits; }
 }
 
-struct BigBitv {
+pub struct BigBitv {
     storage: ~[uint]
 }
 
@@ -181,6 +214,26 @@ impl BigBitv {
         self.process(b, nbits, |w1, w2| w1 & !w2)
     }
 
+    #[inline]
+    pub fn nand(&mut self, b: &BigBitv, nbits: uint) -> bool {
+        self.process(b, nbits, |w1, w2| !(w1 & w2))
+    }
+
+    #[inline]
+    pub fn nor(&mut self, b: &BigBitv, nbits: uint) -> bool {
+        self.process(b, nbits, |w1, w2| !(w1 | w2))
+    }
+
+    #[inline]
+    pub fn xnor(&mut self, b: &BigBitv, nbits: uint) -> bool {
+        self.process(b, nbits, |w1, w2| !(w1 ^ w2))
+    }
+
+    #[inline]
+    pub fn implies(&mut self, b: &BigBitv, nbits: uint) -> bool {
+        self.process(b, nbits, |w1, w2| !w1 | w2)
+    }
+
     #[inline]
     pub fn get(&self, i: uint) -> bool {
         let w = i / uint::bits;
@@ -213,7 +266,7 @@ impl BigBitv {
 
 enum BitvVariant { Big(~BigBitv), Small(~SmallBitv) }
 
-enum Op {Union, Intersect, Assign, Difference}
+enum Op {Union, Intersect, Assign, Difference, Nand, Nor, Xnor, Implies}
 
 /// The bitvector type
 pub struct Bitv {
@@ -227,6 +280,30 @@ fn die() -> ! {
     fail!("Tried to do operation on bit vectors with different sizes");
 }
 
+/// Call `f` with each (storage word index, mask) pair covering the bit
+/// range `[a, b)`: full-word masks in the interior of the range and
+/// partial masks for the boundary words
+fn range_masks(a: uint, b: uint, f: &fn(uint, uint) -> bool) -> bool {
+    if a == b {
+        return true;
+    }
+    let first = a / uint::bits;
+    let last = (b - 1) / uint::bits;
+    for uint::range(first, last + 1) |w| {
+        let mut mask = !0;
+        if w == first && a % uint::bits != 0 {
+            mask &= !0 << (a % uint::bits);
+        }
+        if w == last && b % uint::bits != 0 {
+            mask &= (1 << (b % uint::bits)) - 1;
+        }
+        if !f(w, mask) {
+            return false;
+        }
+    }
+    return true;
+}
+
 impl Bitv {
     #[inline]
     fn do_op(&mut self, op: Op, other: &Bitv) -> bool {
@@ -235,24 +312,57 @@ impl Bitv {
         }
         match self.rep {
           Small(ref mut s) => match other.rep {
-            Small(ref s1) => match op {
+            Small(ref s1) => return match op {
               Union      => s.union(*s1,      self.nbits),
               Intersect  => s.intersect(*s1,  self.nbits),
               Assign     => s.become(*s1,     self.nbits),
-              Difference => s.difference(*s1, self.nbits)
+              Difference => s.difference(*s1, self.nbits),
+              Nand       => s.nand(*s1,       self.nbits),
+              Nor        => s.nor(*s1,        self.nbits),
+              Xnor       => s.xnor(*s1,       self.nbits),
+              Implies    => s.implies(*s1,    self.nbits)
             },
-            Big(_) => die()
+            Big(_) => ()
           },
           Big(ref mut s) => match other.rep {
-            Small(_) => die(),
-            Big(ref s1) => match op {
+            Big(ref s1) if s.storage.len() == s1.storage.len() =>
+              return match op {
               Union      => s.union(*s1,      self.nbits),
               Intersect  => s.intersect(*s1,  self.nbits),
               Assign     => s.become(*s1,     self.nbits),
-              Difference => s.difference(*s1, self.nbits)
-            }
+              Difference => s.difference(*s1, self.nbits),
+              Nand       => s.nand(*s1,       self.nbits),
+              Nor        => s.nor(*s1,        self.nbits),
+              Xnor       => s.xnor(*s1,       self.nbits),
+              Implies    => s.implies(*s1,    self.nbits)
+            },
+            _ => ()
           }
         }
+        // equal-length vectors can still differ in representation or in
+        // word count (push, pop, truncate and reserve resize one
+        // vector's storage without regard to the other's); combine
+        // those a masked word at a time
+        let mut changed = false;
+        for uint::range(0, self.masked_word_count()) |i| {
+            let w0 = self.masked_word(i);
+            let w1 = other.masked_word(i);
+            let w = match op {
+                Union      => w0 | w1,
+                Intersect  => w0 & w1,
+                Assign     => w1,
+                Difference => w0 & !w1,
+                Nand       => !(w0 & w1),
+                Nor        => !(w0 | w1),
+                Xnor       => !(w0 ^ w1),
+                Implies    => !w0 | w1
+            };
+            self.set_word(i, w);
+            if self.masked_word(i) != w0 {
+                changed = true;
+            }
+        }
+        changed
     }
 
 }
@@ -269,7 +379,52 @@ impl Bitv {
             let s = vec::from_elem(nelems, elem);
             Big(~BigBitv::new(s))
         };
-        Bitv {rep: rep, nbits: nbits}
+        let mut bitv = Bitv {rep: rep, nbits: nbits};
+        if init {
+            bitv.normalize();
+        }
+        bitv
+    }
+
+    /**
+     * Constructs a primality bitmap for the numbers `[0, n]` with the
+     * sieve of Eratosthenes
+     *
+     * The returned bitvector has `n + 1` bits, and bit `i` is set if
+     * and only if `i` is prime. The working sieve only tracks odd
+     * numbers, so it needs half the bits of the result.
+     */
+    pub fn sieve_of_eratosthenes(n: uint) -> Bitv {
+        // in the working sieve, index k stands for the odd number 2k + 1
+        fn odd_index(i: uint) -> uint { (i - 1) / 2 }
+
+        let half = n / 2 + 1;
+        let mut composite = Bitv::new(half, false);
+        let mut p = 3;
+        while p * p <= n {
+            if !composite[odd_index(p)] {
+                // mark odd multiples of p from p * p on; stepping by 2p
+                // through the odds is a stride of p through the sieve
+                let mut m = odd_index(p * p);
+                while m < half {
+                    composite.set(m, true);
+                    m += p;
+                }
+            }
+            p += 2;
+        }
+        let mut primes = Bitv::new(n + 1, false);
+        if n >= 2 {
+            primes.set(2, true);
+        }
+        let mut k = 1;
+        while 2 * k + 1 <= n {
+            if !composite[k] {
+                primes.set(2 * k + 1, true);
+            }
+            k += 1;
+        }
+        primes
     }
 
     /**
@@ -325,6 +480,113 @@ impl Bitv {
       }
     }
 
+    /**
+     * Append a bit to the vector, growing it by one. A small vector is
+     * promoted to word storage when its length crosses `uint::bits`, so
+     * bit sequences can be built incrementally like a growable vector.
+     */
+    pub fn push(&mut self, value: bool) {
+        let i = self.nbits;
+        let promote = match self.rep {
+            Small(ref s) if i == uint::bits => Some(s.bits),
+            _ => None
+        };
+        match promote {
+            Some(bits) => self.rep = Big(~BigBitv::new(~[bits, 0])),
+            None => ()
+        }
+        match self.rep {
+            Big(ref mut b) => {
+                if i / uint::bits >= b.storage.len() {
+                    b.storage.push(0);
+                }
+            }
+            Small(_) => assert!(i < uint::bits)
+        }
+        self.nbits = i + 1;
+        self.set(i, value);
+    }
+
+    /**
+     * Remove and return the last bit, shrinking the vector by one.
+     * Fails on an empty vector. A vector that grew into word storage
+     * stays there even when popped back below `uint::bits`; comparisons
+     * and the binary operations do not depend on the representation.
+     */
+    pub fn pop(&mut self) -> bool {
+        assert!(self.nbits > 0);
+        let i = self.nbits - 1;
+        let value = self.get(i);
+        // clear the bit first so it is not left set above the new
+        // length
+        self.set(i, false);
+        self.nbits = i;
+        value
+    }
+
+    /**
+     * An empty vector whose storage can already hold `nbits` bits, so
+     * building a large vector through `push` does not reallocate the
+     * word storage along the way
+     */
+    pub fn with_capacity(nbits: uint) -> Bitv {
+        let mut bitv = Bitv::new(0, false);
+        bitv.reserve(nbits);
+        bitv
+    }
+
+    /**
+     * Reserve word storage for at least `nbits` bits, promoting a small
+     * vector to word storage when the request does not fit in one word.
+     * The length and contents are unchanged, and the promoted vector
+     * still compares and combines with one-word vectors of its length.
+     */
+    pub fn reserve(&mut self, nbits: uint) {
+        if nbits <= uint::bits {
+            return;
+        }
+        let words = uint::div_ceil(nbits, uint::bits);
+        let promote = match self.rep {
+            Small(ref s) => Some(s.bits),
+            Big(_) => None
+        };
+        match promote {
+            Some(bits) => {
+                let mut storage = vec::with_capacity(words);
+                storage.push(bits);
+                self.rep = Big(~BigBitv::new(storage));
+            }
+            None => ()
+        }
+        match self.rep {
+            Big(ref mut b) => b.storage.reserve(words),
+            Small(_) => ()
+        }
+    }
+
+    /**
+     * Shorten the vector to `len` bits, dropping the bits above. The
+     * last storage word is re-masked and whole words that become unused
+     * are dropped from word storage; a no-op when `len` is not below
+     * the current length. As with `pop`, the representation does not
+     * shrink back from Big to Small, but the truncated vector still
+     * compares and combines with small vectors of its length.
+     */
+    pub fn truncate(&mut self, len: uint) {
+        if len >= self.nbits {
+            return;
+        }
+        self.nbits = len;
+        match self.rep {
+            Small(_) => (),
+            Big(ref mut b) => {
+                let nwords = uint::max(1, uint::div_ceil(len, uint::bits));
+                b.storage.truncate(nwords);
+            }
+        }
+        self.normalize();
+    }
+
     /**
      * Compares two bitvectors
      *
@@ -333,17 +595,13 @@ impl Bitv {
      */
     #[inline]
     pub fn equal(&self, v1: &Bitv) -> bool {
-      if self.nbits != v1.nbits { return false; }
-      match self.rep {
-        Small(ref b) => match v1.rep {
-          Small(ref b1) => b.equals(*b1, self.nbits),
-          _ => false
-        },
-        Big(ref s) => match v1.rep {
-          Big(ref s1) => s.equals(*s1, self.nbits),
-          Small(_) => return false
+        if self.nbits != v1.nbits { return false; }
+        for uint::range(0, self.masked_word_count()) |i| {
+            if self.masked_word(i) != v1.masked_word(i) {
+                return false;
+            }
         }
-      }
+        true
     }
 
     /// Set all bits to 0
@@ -361,6 +619,7 @@ impl Bitv {
       match self.rep {
         Small(ref mut b) => b.set_all(),
         Big(ref mut s) => for s.each_storage() |w| { *w = !0u } }
+      self.normalize();
     }
 
     /// Invert all bits
@@ -369,6 +628,36 @@ impl Bitv {
       match self.rep {
         Small(ref mut b) => b.invert(),
         Big(ref mut s) => for s.each_storage() |w| { *w = !*w } }
+      self.normalize();
+    }
+
+    /**
+     * Zero the bits above `nbits` in the last storage word, putting the
+     * vector in the canonical form the word-level APIs rely on. The
+     * mutating operations all maintain this invariant, so only vectors
+     * whose words were filled in from outside — through `with_raw`, a
+     * foreign import, or similar — ever need an explicit call.
+     */
+    pub fn normalize(&mut self) {
+        let rmd = self.nbits % uint::bits;
+        match self.rep {
+            Small(ref mut s) => {
+                if self.nbits == 0 {
+                    s.bits = 0;
+                } else if rmd != 0 {
+                    s.bits &= (1 << rmd) - 1;
+                }
+            }
+            Big(ref mut b) => {
+                let nwords = uint::div_ceil(self.nbits, uint::bits);
+                if rmd != 0 {
+                    b.storage[nwords - 1] &= (1 << rmd) - 1;
+                }
+                for uint::range(nwords, b.storage.len()) |i| {
+                    b.storage[i] = 0;
+                }
+            }
+        }
     }
 
     /**
@@ -385,6 +674,62 @@ impl Bitv {
         self.do_op(Difference, v)
     }
 
+    /**
+     * Calculates the NAND of two bitvectors
+     *
+     * Sets `self` to the complement of the intersection of `self` and
+     * `v1`. Both bitvectors must be the same length. Returns `true` if
+     * `self` changed.
+     */
+    #[inline]
+    pub fn nand(&mut self, v1: &Bitv) -> bool {
+        let changed = self.do_op(Nand, v1);
+        self.normalize();
+        changed
+    }
+
+    /**
+     * Calculates the NOR of two bitvectors
+     *
+     * Sets `self` to the complement of the union of `self` and `v1`.
+     * Both bitvectors must be the same length. Returns `true` if `self`
+     * changed.
+     */
+    #[inline]
+    pub fn nor(&mut self, v1: &Bitv) -> bool {
+        let changed = self.do_op(Nor, v1);
+        self.normalize();
+        changed
+    }
+
+    /**
+     * Calculates the XNOR (equivalence) of two bitvectors
+     *
+     * Sets each bit of `self` to whether it agreed with the bit of `v1`
+     * at the same index. Both bitvectors must be the same length.
+     * Returns `true` if `self` changed.
+     */
+    #[inline]
+    pub fn xnor(&mut self, v1: &Bitv) -> bool {
+        let changed = self.do_op(Xnor, v1);
+        self.normalize();
+        changed
+    }
+
+    /**
+     * Calculates the implication of two bitvectors
+     *
+     * Sets each bit of `self` to `!self | v1` at the same index. Both
+     * bitvectors must be the same length. Returns `true` if `self`
+     * changed.
+     */
+    #[inline]
+    pub fn implies(&mut self, v1: &Bitv) -> bool {
+        let changed = self.do_op(Implies, v1);
+        self.normalize();
+        changed
+    }
+
     /// Returns true if all bits are 1
     #[inline]
     pub fn is_true(&self) -> bool {
@@ -505,915 +850,5055 @@ impl Bitv {
         true
     }
 
+    /// Visit the index of each 1 bit in order, a loaded word at a time
     pub fn ones(&self, f: &fn(uint) -> bool) -> bool {
-        uint::range(0, self.nbits, |i| !self.get(i) || f(i))
+        let mut iter = self.ones_iter();
+        iter.advance(f)
     }
 
-}
+    /// Visit the index of each 0 bit in order
+    pub fn zeros(&self, f: &fn(uint) -> bool) -> bool {
+        let mut iter = self.zeros_iter();
+        iter.advance(f)
+    }
 
-impl Clone for Bitv {
-    /// Makes a copy of a bitvector
-    #[inline]
-    fn clone(&self) -> Bitv {
-        match self.rep {
-          Small(ref b) => {
-            Bitv{nbits: self.nbits, rep: Small(~SmallBitv{bits: b.bits})}
-          }
-          Big(ref b) => {
-            let mut st = vec::from_elem(self.nbits / uint::bits + 1, 0);
-            let len = st.len();
-            for uint::range(0, len) |i| { st[i] = b.storage[i]; };
-            Bitv{nbits: self.nbits, rep: Big(~BigBitv{storage: st})}
-          }
+    /// The number of 1 bits in the vector, summed a storage word at a
+    /// time rather than by iterating `ones`
+    pub fn count_ones(&self) -> uint {
+        let mut count = 0;
+        for uint::range(0, self.masked_word_count()) |i| {
+            count += population_count(self.masked_word(i));
         }
+        count
     }
-}
-
-/**
- * Transform a byte-vector into a bitv. Each byte becomes 8 bits,
- * with the most significant bits of each byte coming first. Each
- * bit becomes true if equal to 1 or false if equal to 0.
- */
-pub fn from_bytes(bytes: &[u8]) -> Bitv {
-    from_fn(bytes.len() * 8, |i| {
-        let b = bytes[i / 8] as uint;
-        let offset = i % 8;
-        b >> (7 - offset) & 1 == 1
-    })
-}
-
-/**
- * Transform a [bool] into a bitv by converting each bool into a bit.
- */
-pub fn from_bools(bools: &[bool]) -> Bitv {
-    from_fn(bools.len(), |i| bools[i])
-}
 
-/**
- * Create a bitv of the specified length where the value at each
- * index is f(index).
- */
-pub fn from_fn(len: uint, f: &fn(index: uint) -> bool) -> Bitv {
-    let mut bitv = Bitv::new(len, false);
-    for uint::range(0, len) |i| {
-        bitv.set(i, f(i));
+    /// The number of 0 bits in the vector, so bit allocation maps can
+    /// ask for their free-slot count without doing the mask bookkeeping
+    /// themselves
+    pub fn count_zeros(&self) -> uint {
+        self.nbits - self.count_ones()
     }
-    bitv
-}
 
-impl ops::Index<uint,bool> for Bitv {
-    fn index(&self, i: &uint) -> bool {
-        self.get(*i)
+    /// The index of the first 1 bit, found by scanning a storage word
+    /// at a time and counting the trailing zeros of the first nonzero
+    /// word — find-first-set for allocators and schedulers
+    pub fn first_one(&self) -> Option<uint> {
+        self.next_one(0)
     }
-}
 
-#[inline]
-fn iterate_bits(base: uint, bits: uint, f: &fn(uint) -> bool) -> bool {
-    if bits == 0 {
-        return true;
+    /// The index of the first 0 bit, or None in an all-ones vector
+    pub fn first_zero(&self) -> Option<uint> {
+        self.next_zero(0)
     }
-    for uint::range(0, uint::bits) |i| {
-        if bits & (1 << i) != 0 {
-            if !f(base + i) {
-                return false;
+
+    /// The index of the first 1 bit at or after `from`, so cursor-style
+    /// scans over a sparse vector can resume where they left off
+    /// instead of restarting from bit 0
+    pub fn next_one(&self, from: uint) -> Option<uint> {
+        if from >= self.nbits {
+            return None;
+        }
+        let first = from / uint::bits;
+        for uint::range(first, self.masked_word_count()) |i| {
+            let mut w = self.masked_word(i);
+            if i == first && from % uint::bits != 0 {
+                w &= !0 << (from % uint::bits);
+            }
+            if w != 0 {
+                let mut b = 0;
+                while w >> b & 1 == 0 { b += 1; }
+                return Some(i * uint::bits + b);
             }
         }
+        None
     }
-    return true;
-}
-
-/// An implementation of a set using a bit vector as an underlying
-/// representation for holding numerical elements.
-///
-/// It should also be noted that the amount of storage necessary for holding a
-/// set of objects is proportional to the maximum of the objects when viewed
-/// as a uint.
-pub struct BitvSet {
-    priv size: uint,
-
-    // In theory this is a Bitv instead of always a BigBitv, but knowing that
-    // there's an array of storage makes our lives a whole lot easier when
-    // performing union/intersection/etc operations
-    priv bitv: BigBitv
-}
 
-impl BitvSet {
-    /// Creates a new bit vector set with initially no contents
-    pub fn new() -> BitvSet {
-        BitvSet{ size: 0, bitv: BigBitv::new(~[0]) }
+    /// The number of consecutive 0 bits at the low end of the vector;
+    /// the whole length when no bit is set
+    pub fn trailing_zeros(&self) -> uint {
+        match self.first_one() {
+            Some(i) => i,
+            None => self.nbits
+        }
     }
 
-    /// Creates a new bit vector set from the given bit vector
-    pub fn from_bitv(bitv: Bitv) -> BitvSet {
-        let mut size = 0;
-        for bitv.ones |_| {
-            size += 1;
-        }
-        let Bitv{rep, _} = bitv;
-        match rep {
-            Big(~b) => BitvSet{ size: size, bitv: b },
-            Small(~SmallBitv{bits}) =>
-                BitvSet{ size: size, bitv: BigBitv{ storage: ~[bits] } },
+    /// The number of consecutive 1 bits at the low end of the vector
+    pub fn trailing_ones(&self) -> uint {
+        match self.first_zero() {
+            Some(i) => i,
+            None => self.nbits
         }
     }
 
-    /// Returns the capacity in bits for this bit vector. Inserting any
-    /// element less than this amount will not trigger a resizing.
-    pub fn capacity(&self) -> uint { self.bitv.storage.len() * uint::bits }
-
-    /// Consumes this set to return the underlying bit vector
-    pub fn unwrap(self) -> Bitv {
-        let cap = self.capacity();
-        let BitvSet{bitv, _} = self;
-        return Bitv{ nbits:cap, rep: Big(~bitv) };
+    /// The number of consecutive 0 bits at the high end of the vector,
+    /// scanning a word at a time from the top — the normalization
+    /// query for bit-encoded big numbers
+    pub fn leading_zeros(&self) -> uint {
+        let mut i = self.masked_word_count();
+        while i > 0 {
+            i -= 1;
+            let w = self.masked_word(i);
+            if w != 0 {
+                // b is one past the highest set bit of w
+                let mut b = uint::bits;
+                while w >> (b - 1) & 1 == 0 { b -= 1; }
+                return self.nbits - (i * uint::bits + b);
+            }
+        }
+        self.nbits
     }
 
-    #[inline]
-    fn other_op(&mut self, other: &BitvSet, f: &fn(uint, uint) -> uint) {
-        fn nbits(mut w: uint) -> uint {
-            let mut bits = 0;
-            for uint::bits.times {
-                if w == 0 {
-                    break;
-                }
-                bits += w & 1;
-                w >>= 1;
+    /// The number of consecutive 1 bits at the high end of the vector
+    pub fn leading_ones(&self) -> uint {
+        let mut i = self.masked_word_count();
+        while i > 0 {
+            i -= 1;
+            // the fully-set form of this word's in-range bits
+            let full = if (i + 1) * uint::bits <= self.nbits {
+                !0
+            } else {
+                (1 << (self.nbits % uint::bits)) - 1
+            };
+            let w = self.masked_word(i);
+            if w != full {
+                // set bits of inv mark the in-range zeros
+                let inv = w ^ full;
+                let mut b = uint::bits;
+                while inv >> (b - 1) & 1 == 0 { b -= 1; }
+                return self.nbits - (i * uint::bits + b);
             }
-            return bits;
         }
-        if self.capacity() < other.capacity() {
-            self.bitv.storage.grow(other.capacity() / uint::bits, &0);
+        self.nbits
+    }
+
+    /// The index of the first 0 bit at or after `from`
+    pub fn next_zero(&self, from: uint) -> Option<uint> {
+        if from >= self.nbits {
+            return None;
         }
-        for other.bitv.storage.iter().enumerate().advance |(i, &w)| {
-            let old = self.bitv.storage[i];
-            let new = f(old, w);
-            self.bitv.storage[i] = new;
-            self.size += nbits(new) - nbits(old);
+        let first = from / uint::bits;
+        for uint::range(first, self.masked_word_count()) |i| {
+            let mut w = !self.masked_word(i);
+            if i == first && from % uint::bits != 0 {
+                w &= !0 << (from % uint::bits);
+            }
+            if w != 0 {
+                let mut b = 0;
+                while w >> b & 1 == 0 { b += 1; }
+                let pos = i * uint::bits + b;
+                // in the last word the inverted padding looks clear
+                if pos < self.nbits {
+                    return Some(pos);
+                }
+                return None;
+            }
         }
+        None
     }
 
-    /// Union in-place with the specified other bit vector
-    pub fn union_with(&mut self, other: &BitvSet) {
-        self.other_op(other, |w1, w2| w1 | w2);
-    }
-
-    /// Intersect in-place with the specified other bit vector
-    pub fn intersect_with(&mut self, other: &BitvSet) {
-        self.other_op(other, |w1, w2| w1 & w2);
-    }
-
-    /// Difference in-place with the specified other bit vector
-    pub fn difference_with(&mut self, other: &BitvSet) {
-        self.other_op(other, |w1, w2| w1 & !w2);
-    }
-
-    /// Symmetric difference in-place with the specified other bit vector
-    pub fn symmetric_difference_with(&mut self, other: &BitvSet) {
-        self.other_op(other, |w1, w2| w1 ^ w2);
+    /// Returns the number of bytes of heap memory owned by this bitvector
+    pub fn memory_usage(&self) -> uint {
+        match self.rep {
+            Small(_) => sys::size_of::<SmallBitv>(),
+            Big(ref b) => sys::size_of::<BigBitv>() +
+                          b.storage.capacity() * uint::bytes
+        }
     }
 
-    pub fn each(&self, blk: &fn(v: &uint) -> bool) -> bool {
-        for self.bitv.storage.iter().enumerate().advance |(i, &w)| {
-            if !iterate_bits(i * uint::bits, w, |b| blk(&b)) {
-                return false;
+    /**
+     * Call `f` with a pointer to the backing words and their count, so
+     * the bitmap can be handed to foreign code (CPU masks and the like)
+     * in the platform's native word layout without a byte-by-byte copy.
+     * Bits past `nbits` in the last word are zero; callers that write
+     * through the pointer should `normalize` afterwards. The pointer
+     * is only valid for the duration of the call.
+     */
+    pub fn with_raw(&self, f: &fn(*uint, uint)) {
+        match self.rep {
+            Small(ref b) => f(ptr::to_unsafe_ptr(&b.bits), 1),
+            Big(ref b) => {
+                do b.storage.as_imm_buf |p, len| {
+                    f(p, len)
+                }
             }
         }
-        return true;
     }
-}
 
-impl cmp::Eq for BitvSet {
-    fn eq(&self, other: &BitvSet) -> bool {
-        if self.size != other.size {
-            return false;
-        }
-        for self.each_common(other) |_, w1, w2| {
-            if w1 != w2 {
-                return false;
+    /**
+     * Reverse the order of the bits within each group of 8, converting
+     * between the MSB0 and LSB0 byte interpretations in place. The
+     * length must be a multiple of 8.
+     */
+    pub fn reverse_bit_order_in_bytes(&mut self) {
+        assert!(self.nbits % 8 == 0);
+        let mut base = 0;
+        while base < self.nbits {
+            for uint::range(0, 4) |j| {
+                let lo = self.get(base + j);
+                let hi = self.get(base + 7 - j);
+                self.set(base + j, hi);
+                self.set(base + 7 - j, lo);
             }
+            base += 8;
         }
-        for self.each_outlier(other) |_, _, w| {
-            if w != 0 {
-                return false;
+    }
+
+    /**
+     * Tile a repeating pattern across the whole vector: bit `i`
+     * becomes bit `i % pattern_bits` of `pattern`. The tiling is done
+     * a storage word at a time, shifting the pattern to wherever it
+     * straddles a word boundary, so stippled masks and test fixtures
+     * need no per-bit loop.
+     */
+    pub fn fill_with_pattern(&mut self, pattern: uint, pattern_bits: uint) {
+        assert!(pattern_bits > 0);
+        assert!(pattern_bits <= uint::bits);
+        let mask = if pattern_bits == uint::bits {
+            !0
+        } else {
+            (1 << pattern_bits) - 1
+        };
+        let pattern = pattern & mask;
+        // the offset into the pattern at which each word begins
+        let mut phase = 0;
+        for uint::range(0, self.masked_word_count()) |i| {
+            let mut w = pattern >> phase;
+            let mut pos = pattern_bits - phase;
+            while pos < uint::bits {
+                w |= pattern << pos;
+                pos += pattern_bits;
             }
+            self.set_word(i, w);
+            phase = (phase + uint::bits) % pattern_bits;
         }
-        return true;
     }
 
-    fn ne(&self, other: &BitvSet) -> bool { !self.eq(other) }
-}
-
-impl Container for BitvSet {
-    fn len(&self) -> uint { self.size }
-    fn is_empty(&self) -> bool { self.size == 0 }
-}
-
-impl Mutable for BitvSet {
-    fn clear(&mut self) {
-        for self.bitv.each_storage |w| { *w = 0; }
-        self.size = 0;
+    /**
+     * Shift every bit `n` places toward the higher indices in place,
+     * filling with zeros at the bottom. Bits shifted past the end are
+     * lost. The work is done a storage word at a time, from the top
+     * down so each source word is read before it is overwritten.
+     */
+    pub fn shl_assign(&mut self, n: uint) {
+        let k = n / uint::bits;
+        let s = n % uint::bits;
+        let mut i = self.masked_word_count();
+        while i > 0 {
+            i -= 1;
+            let w = if i < k {
+                0
+            } else if s == 0 {
+                self.masked_word(i - k)
+            } else {
+                let carry = if i > k {
+                    self.masked_word(i - k - 1) >> (uint::bits - s)
+                } else {
+                    0
+                };
+                self.masked_word(i - k) << s | carry
+            };
+            self.set_word(i, w);
+        }
     }
-}
 
-impl Set<uint> for BitvSet {
-    fn contains(&self, value: &uint) -> bool {
-        *value < self.bitv.storage.len() * uint::bits && self.bitv.get(*value)
+    /**
+     * Shift every bit `n` places toward the lower indices in place,
+     * filling with zeros at the top. Bits shifted below index 0 are
+     * lost.
+     */
+    pub fn shr_assign(&mut self, n: uint) {
+        for uint::range(0, self.masked_word_count()) |i| {
+            let w = self.word_at(n + i * uint::bits);
+            self.set_word(i, w);
+        }
     }
 
-    fn insert(&mut self, value: uint) -> bool {
-        if self.contains(&value) {
-            return false;
-        }
-        let nbits = self.capacity();
-        if value >= nbits {
-            let newsize = uint::max(value, nbits * 2) / uint::bits + 1;
-            assert!(newsize > self.bitv.storage.len());
-            self.bitv.storage.grow(newsize, &0);
+    /**
+     * Set every bit in `[a, b)`, whole words at a time in the interior
+     * of the range, so marking a large contiguous region of an
+     * allocation bitmap does not cost one call per bit
+     */
+    pub fn set_range(&mut self, a: uint, b: uint) {
+        assert!(a <= b);
+        assert!(b <= self.nbits);
+        for range_masks(a, b) |w, mask| {
+            self.apply_word_mask(w, mask, true);
         }
-        self.size += 1;
-        self.bitv.set(value, true);
-        return true;
     }
 
-    fn remove(&mut self, value: &uint) -> bool {
-        if !self.contains(value) {
-            return false;
+    /// Clear every bit in `[a, b)`, whole words at a time in the
+    /// interior of the range
+    pub fn clear_range(&mut self, a: uint, b: uint) {
+        assert!(a <= b);
+        assert!(b <= self.nbits);
+        for range_masks(a, b) |w, mask| {
+            self.apply_word_mask(w, mask, false);
         }
-        self.size -= 1;
-        self.bitv.set(*value, false);
+    }
 
-        // Attempt to truncate our storage
-        let mut i = self.bitv.storage.len();
-        while i > 1 && self.bitv.storage[i - 1] == 0 {
-            i -= 1;
+    /// Toggle every bit in `[a, b)`, whole words at a time in the
+    /// interior of the range
+    pub fn flip_range(&mut self, a: uint, b: uint) {
+        assert!(a <= b);
+        assert!(b <= self.nbits);
+        for range_masks(a, b) |w, mask| {
+            match self.rep {
+                Small(ref mut s) => s.bits ^= mask,
+                Big(ref mut big) => big.storage[w] ^= mask
+            }
         }
-        self.bitv.storage.truncate(i);
-
-        return true;
     }
 
-    fn is_disjoint(&self, other: &BitvSet) -> bool {
-        for self.intersection(other) |_| {
-            return false;
+    /**
+     * Copy `len` bits of `src` starting at `src_off` into `self`
+     * starting at `dst_off` — a bit-block transfer done with shifted
+     * word copies, the core primitive for composing packed binary
+     * structures. Both ranges must lie within their vectors.
+     */
+    pub fn copy_bits(&mut self, dst_off: uint, src: &Bitv,
+                     src_off: uint, len: uint) {
+        assert!(dst_off + len <= self.nbits);
+        assert!(src_off + len <= src.nbits);
+        for range_masks(dst_off, dst_off + len) |w, mask| {
+            let sw = if w * uint::bits >= dst_off {
+                src.word_at(src_off + w * uint::bits - dst_off)
+            } else {
+                src.word_at(src_off) << (dst_off - w * uint::bits)
+            };
+            let old = self.masked_word(w);
+            self.set_word(w, (old & !mask) | (sw & mask));
         }
-        return true;
     }
 
-    fn is_subset(&self, other: &BitvSet) -> bool {
-        for self.each_common(other) |_, w1, w2| {
-            if w1 & w2 != w1 {
-                return false;
+    /**
+     * Toggle the bit at index `i` with a single XOR on its containing
+     * word, returning the previous value — cheaper than the two
+     * representation dispatches of `set(i, !get(i))`
+     */
+    pub fn flip(&mut self, i: uint) -> bool {
+        assert!(i < self.nbits);
+        let flag = 1 << (i % uint::bits);
+        match self.rep {
+            Small(ref mut s) => {
+                let old = s.bits & flag != 0;
+                s.bits ^= flag;
+                old
             }
-        }
-        /* If anything is not ours, then everything is not ours so we're
-           definitely a subset in that case. Otherwise if there's any stray
-           ones that 'other' doesn't have, we're not a subset. */
-        for self.each_outlier(other) |mine, _, w| {
-            if !mine {
-                return true;
-            } else if w != 0 {
-                return false;
+            Big(ref mut b) => {
+                let w = i / uint::bits;
+                let old = b.storage[w] & flag != 0;
+                b.storage[w] ^= flag;
+                old
             }
         }
-        return true;
     }
 
-    fn is_superset(&self, other: &BitvSet) -> bool {
-        other.is_subset(self)
+    /**
+     * Exchange the bits at indices `i` and `j` in place, so bit
+     * permutations and sorting-network passes need no three-call
+     * get/set dance with a temporary
+     */
+    pub fn swap(&mut self, i: uint, j: uint) {
+        assert!(i < self.nbits);
+        assert!(j < self.nbits);
+        let a = self.get(i);
+        let b = self.get(j);
+        if a != b {
+            self.set(i, b);
+            self.set(j, a);
+        }
     }
 
-    fn difference(&self, other: &BitvSet, f: &fn(&uint) -> bool) -> bool {
-        for self.each_common(other) |i, w1, w2| {
-            if !iterate_bits(i, w1 & !w2, |b| f(&b)) {
-                return false;
+    /**
+     * Split the vector in two at `at`: `self` keeps the bits below it
+     * and the bits at or above it come back as a new vector. A
+     * word-aligned split moves whole storage words across instead of
+     * shifting, making chunked processing of long bit streams cheap.
+     */
+    pub fn split_off(&mut self, at: uint) -> Bitv {
+        assert!(at <= self.nbits);
+        let tail_len = self.nbits - at;
+        let tail = match self.rep {
+            Big(ref b) if at % uint::bits == 0 &&
+                          tail_len > uint::bits => {
+                let words = b.storage.slice(
+                    at / uint::bits,
+                    uint::div_ceil(self.nbits, uint::bits)).to_owned();
+                Bitv{nbits: tail_len, rep: Big(~BigBitv::new(words))}
             }
+            _ => self.slice_bits(at, self.nbits)
+        };
+        self.truncate(at);
+        tail
+    }
+
+    /**
+     * Insert a bit at index `i`, shifting every bit at or above it one
+     * place toward the higher indices and growing the vector by one —
+     * the bit vector analogue of vector insert. The tail is moved a
+     * word at a time, from the top down.
+     */
+    pub fn insert_at(&mut self, i: uint, value: bool) {
+        assert!(i <= self.nbits);
+        self.push(false);
+        let first = i / uint::bits;
+        let o = i % uint::bits;
+        let low_mask = if o == 0 { 0 } else { (1 << o) - 1 };
+        let mut d = self.masked_word_count();
+        while d > first {
+            d -= 1;
+            let w = self.masked_word(d);
+            let shifted = if d == first {
+                (w & !low_mask) << 1 | (w & low_mask)
+            } else {
+                w << 1 | self.masked_word(d - 1) >> (uint::bits - 1)
+            };
+            self.set_word(d, shifted);
         }
-        /* everything we have that they don't also shows up */
-        self.each_outlier(other, |mine, i, w|
-            !mine || iterate_bits(i, w, |b| f(&b))
-        )
+        self.set(i, value);
     }
 
-    fn symmetric_difference(&self, other: &BitvSet,
-                            f: &fn(&uint) -> bool) -> bool {
-        for self.each_common(other) |i, w1, w2| {
-            if !iterate_bits(i, w1 ^ w2, |b| f(&b)) {
-                return false;
-            }
+    /**
+     * Remove and return the bit at index `i`, shifting every bit above
+     * it one place toward the lower indices and shrinking the vector by
+     * one — the bit vector analogue of vector remove
+     */
+    pub fn remove_at(&mut self, i: uint) -> bool {
+        assert!(i < self.nbits);
+        let value = self.get(i);
+        let first = i / uint::bits;
+        let o = i % uint::bits;
+        let low_mask = if o == 0 { 0 } else { (1 << o) - 1 };
+        for uint::range(first, self.masked_word_count()) |d| {
+            let w = self.masked_word(d);
+            let carry = self.masked_word(d + 1) << (uint::bits - 1);
+            let shifted = if d == first {
+                (w & low_mask) | (w >> 1 & !low_mask) | carry
+            } else {
+                w >> 1 | carry
+            };
+            self.set_word(d, shifted);
         }
-        self.each_outlier(other, |_, i, w| iterate_bits(i, w, |b| f(&b)))
+        self.pop();
+        value
     }
 
-    fn intersection(&self, other: &BitvSet, f: &fn(&uint) -> bool) -> bool {
-        self.each_common(other, |i, w1, w2| iterate_bits(i, w1 & w2, |b| f(&b)))
+    /**
+     * Extract the bits `[start, end)` into a new vector using
+     * word-level shifts, so pulling a field out of a large bit vector
+     * does not walk it bit by bit
+     */
+    pub fn slice_bits(&self, start: uint, end: uint) -> Bitv {
+        assert!(start <= end);
+        assert!(end <= self.nbits);
+        let mut result = Bitv::new(end - start, false);
+        for uint::range(0, result.masked_word_count()) |i| {
+            result.set_word(i, self.word_at(start + i * uint::bits));
+        }
+        result
     }
 
-    fn union(&self, other: &BitvSet, f: &fn(&uint) -> bool) -> bool {
-        for self.each_common(other) |i, w1, w2| {
-            if !iterate_bits(i, w1 | w2, |b| f(&b)) {
-                return false;
+    /**
+     * Append the bits of `other` after the bits of `self`, growing it
+     * by `other`'s length. The copy is done a word at a time, shifted
+     * to wherever `self`'s length falls inside its last word, so
+     * protocol frames can be built from header and payload bit fields
+     * without a per-bit loop.
+     */
+    pub fn append(&mut self, other: &Bitv) {
+        let base = self.nbits;
+        let new_len = base + other.nbits;
+        self.reserve(new_len);
+        match self.rep {
+            Small(_) => assert!(new_len <= uint::bits),
+            Big(ref mut b) => {
+                let words = uint::div_ceil(new_len, uint::bits);
+                let len = b.storage.len();
+                if len < words {
+                    b.storage.grow(words - len, &0);
+                }
             }
         }
-        self.each_outlier(other, |_, i, w| iterate_bits(i, w, |b| f(&b)))
+        self.nbits = new_len;
+        let start_word = base / uint::bits;
+        let o = base % uint::bits;
+        for uint::range(start_word, self.masked_word_count()) |d| {
+            let w = if d == start_word {
+                self.masked_word(d) | other.word_at(0) << o
+            } else {
+                other.word_at((d - start_word) * uint::bits - o)
+            };
+            self.set_word(d, w);
+        }
     }
-}
 
-impl BitvSet {
-    /// Visits each of the words that the two bit vectors (self and other)
-    /// both have in common. The three yielded arguments are (bit location,
-    /// w1, w2) where the bit location is the number of bits offset so far,
-    /// and w1/w2 are the words coming from the two vectors self, other.
-    fn each_common(&self, other: &BitvSet,
-                   f: &fn(uint, uint, uint) -> bool) -> bool {
-        let min = uint::min(self.bitv.storage.len(),
-                            other.bitv.storage.len());
-        self.bitv.storage.slice(0, min).iter().enumerate().advance(|(i, &w)| {
-            f(i * uint::bits, w, other.bitv.storage[i])
-        })
+    /**
+     * Rotate every bit `n % len` places toward the higher indices in
+     * place, the bits shifted off the top wrapping around to index 0 —
+     * `shl_assign` with wrap-around. The partially used last word takes
+     * no special casing here because the shifts keep it masked.
+     */
+    pub fn rotate_left(&mut self, n: uint) {
+        if self.nbits == 0 {
+            return;
+        }
+        let n = n % self.nbits;
+        if n == 0 {
+            return;
+        }
+        // the top n bits, moved down to the bottom
+        let mut wrapped = Bitv::new(self.nbits, false);
+        for uint::range(0, wrapped.masked_word_count()) |i| {
+            wrapped.set_word(i, self.masked_word(i));
+        }
+        wrapped.shr_assign(self.nbits - n);
+        self.shl_assign(n);
+        for uint::range(0, self.masked_word_count()) |i| {
+            let w = self.masked_word(i) | wrapped.masked_word(i);
+            self.set_word(i, w);
+        }
     }
 
-    /// Visits each word in self or other that extends beyond the other. This
-    /// will only iterate through one of the vectors, and it only iterates
-    /// over the portion that doesn't overlap with the other one.
-    ///
-    /// The yielded arguments are a bool, the bit offset, and a word. The bool
-    /// is true if the word comes from 'self', and false if it comes from
-    /// 'other'.
-    fn each_outlier(&self, other: &BitvSet,
-                    f: &fn(bool, uint, uint) -> bool) -> bool {
-        let len1 = self.bitv.storage.len();
-        let len2 = other.bitv.storage.len();
-        let min = uint::min(len1, len2);
-
-        /* only one of these loops will execute and that's the point */
-        for self.bitv.storage.slice(min, len1).iter().enumerate().advance |(i, &w)| {
-            if !f(true, (i + min) * uint::bits, w) {
-                return false;
-            }
+    /**
+     * Rotate every bit `n % len` places toward the lower indices in
+     * place, the bits shifted off the bottom wrapping around to the
+     * top
+     */
+    pub fn rotate_right(&mut self, n: uint) {
+        if self.nbits == 0 {
+            return;
         }
-        for other.bitv.storage.slice(min, len2).iter().enumerate().advance |(i, &w)| {
-            if !f(false, (i + min) * uint::bits, w) {
-                return false;
-            }
+        let n = n % self.nbits;
+        if n == 0 {
+            return;
         }
-        return true;
+        self.rotate_left(self.nbits - n);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use extra::test::BenchHarness;
-
-    use bitv::*;
-    use bitv;
 
-    use std::uint;
-    use std::vec;
-    use std::rand;
-    use std::rand::Rng;
-
-    static BENCH_BITS : uint = 1 << 14;
+    /// A uniformly random vector of `nbits` bits, drawn a word at a
+    /// time rather than through `nbits` calls to `set`
+    pub fn random<R: rand::Rng>(nbits: uint, rng: &mut R) -> Bitv {
+        let mut v = Bitv::new(nbits, false);
+        for uint::range(0, uint::div_ceil(nbits, uint::bits)) |i| {
+            v.set_word(i, rng.gen());
+        }
+        v
+    }
 
-    #[test]
-    fn test_to_str() {
-        let zerolen = Bitv::new(0u, false);
-        assert_eq!(zerolen.to_str(), ~"");
+}
 
-        let eightbits = Bitv::new(8u, false);
-        assert_eq!(eightbits.to_str(), ~"00000000");
+impl Clone for Bitv {
+    /// Makes a copy of a bitvector
+    #[inline]
+    fn clone(&self) -> Bitv {
+        match self.rep {
+          Small(ref b) => {
+            Bitv{nbits: self.nbits, rep: Small(~SmallBitv{bits: b.bits})}
+          }
+          Big(ref b) => {
+            let mut st = vec::from_elem(b.storage.len(), 0);
+            let len = st.len();
+            for uint::range(0, len) |i| { st[i] = b.storage[i]; };
+            Bitv{nbits: self.nbits, rep: Big(~BigBitv{storage: st})}
+          }
+        }
     }
+}
 
-    #[test]
-    fn test_0_elements() {
+/**
+ * Transform a byte-vector into a bitv. Each byte becomes 8 bits,
+ * with the most significant bits of each byte coming first. Each
+ * bit becomes true if equal to 1 or false if equal to 0.
+ */
+pub fn from_bytes(bytes: &[u8]) -> Bitv {
+    from_fn(bytes.len() * 8, |i| {
+        let b = bytes[i / 8] as uint;
+        let offset = i % 8;
+        b >> (7 - offset) & 1 == 1
+    })
+}
+
+/// Reverse the order of the bits within a byte, exchanging the MSB0
+/// and LSB0 interpretations of it
+pub fn reverse_bits_in_byte(b: u8) -> u8 {
+    let b = (b & 0xF0) >> 4 | (b & 0x0F) << 4;
+    let b = (b & 0xCC) >> 2 | (b & 0x33) << 2;
+    (b & 0xAA) >> 1 | (b & 0x55) << 1
+}
+
+/**
+ * Transform a byte-vector into a bitv, reading each byte least
+ * significant bit first — the order used by LSB0-based formats, where
+ * `from_bytes` reads MSB first.
+ */
+pub fn from_bytes_lsb0(bytes: &[u8]) -> Bitv {
+    from_fn(bytes.len() * 8, |i| {
+        let b = bytes[i / 8] as uint;
+        b >> (i % 8) & 1 == 1
+    })
+}
+
+/**
+ * Transform a [bool] into a bitv by converting each bool into a bit.
+ */
+pub fn from_bools(bools: &[bool]) -> Bitv {
+    from_fn(bools.len(), |i| bools[i])
+}
+
+/**
+ * Transform a vector of machine words into a bitv, using the platform's
+ * native bit order: bit `i` of the bitv is bit `i % uint::bits` of word
+ * `i / uint::bits`. The length of the result is the full capacity of the
+ * given words.
+ */
+pub fn from_words(words: ~[uint]) -> Bitv {
+    let nbits = words.len() * uint::bits;
+    // a single word gets the small representation, as `Bitv::new` would
+    // choose for the same length
+    let rep = if nbits <= uint::bits {
+        Small(~SmallBitv::new(if words.is_empty() { 0 } else { words[0] }))
+    } else {
+        Big(~BigBitv::new(words))
+    };
+    Bitv{nbits: nbits, rep: rep}
+}
+
+/**
+ * Build a bitv of `nbits` bits by copying `words` machine words out of
+ * raw memory, in the same native bit order `from_words` uses. Foreign
+ * bitmaps (CPU masks and the like) can be imported this way without
+ * going through a byte vector. Any bits past `nbits` in the source are
+ * dropped, keeping the result canonical.
+ *
+ * Unsafe because `ptr` must point at `words` readable words.
+ */
+pub unsafe fn from_raw_parts(ptr: *uint, words: uint, nbits: uint) -> Bitv {
+    assert!(nbits <= words * uint::bits);
+    let storage = vec::raw::from_buf_raw(ptr, words);
+    let mut bitv = Bitv{nbits: nbits, rep: Big(~BigBitv::new(storage))};
+    bitv.normalize();
+    bitv
+}
+
+/**
+ * Join two bit vectors end to end into a new vector, `a`'s bits coming
+ * first; see `Bitv::append` for the in-place form.
+ */
+pub fn concat(a: &Bitv, b: &Bitv) -> Bitv {
+    let mut result = Bitv::new(a.nbits, false);
+    for uint::range(0, result.masked_word_count()) |i| {
+        result.set_word(i, a.masked_word(i));
+    }
+    result.append(b);
+    result
+}
+
+/**
+ * Interleave two equal-length bit vectors: bit `i` of `a` lands at
+ * position `2 * i` of the result and bit `i` of `b` at `2 * i + 1`.
+ * The work is done half a storage word at a time with the Morton
+ * scatter sequence rather than bit by bit; see `morton::interleave_bitv`
+ * for the general k-channel form.
+ */
+pub fn interleave(a: &Bitv, b: &Bitv) -> Bitv {
+    assert_eq!(a.nbits, b.nbits);
+    let half = uint::bits / 2;
+    let half_mask = (1 << half) - 1;
+    let mut out = Bitv::new(2 * a.nbits, false);
+    for uint::range(0, out.masked_word_count()) |i| {
+        let av = a.word_at(i * half) & half_mask;
+        let bv = b.word_at(i * half) & half_mask;
+        out.set_word(i, (morton::scatter_2(av as u64) |
+                         (morton::scatter_2(bv as u64) << 1)) as uint);
+    }
+    out
+}
+
+/// Split a bit vector built by `interleave` back into its two channels
+pub fn deinterleave(code: &Bitv) -> (Bitv, Bitv) {
+    assert!(code.nbits % 2 == 0);
+    let n = code.nbits / 2;
+    let half = uint::bits / 2;
+    let mut a = Bitv::new(n, false);
+    let mut b = Bitv::new(n, false);
+    for uint::range(0, a.masked_word_count()) |j| {
+        let lo = code.masked_word(2 * j) as u64;
+        let hi = code.masked_word(2 * j + 1) as u64;
+        a.set_word(j, (morton::gather_2(lo) |
+                       (morton::gather_2(hi) << half)) as uint);
+        b.set_word(j, (morton::gather_2(lo >> 1) |
+                       (morton::gather_2(hi >> 1) << half)) as uint);
+    }
+    (a, b)
+}
+
+/**
+ * Create a bitv of the specified length where the value at each
+ * index is f(index).
+ */
+pub fn from_fn(len: uint, f: &fn(index: uint) -> bool) -> Bitv {
+    let mut bitv = Bitv::new(len, false);
+    for uint::range(0, len) |i| {
+        bitv.set(i, f(i));
+    }
+    bitv
+}
+
+impl ops::Index<uint,bool> for Bitv {
+    fn index(&self, i: &uint) -> bool {
+        self.get(*i)
+    }
+}
+
+impl ops::Shl<uint, Bitv> for Bitv {
+    /// Return a copy of the vector with every bit shifted `n` places
+    /// toward the higher indices; see `shl_assign`
+    fn shl(&self, n: &uint) -> Bitv {
+        let mut result = Bitv::new(self.nbits, false);
+        for uint::range(0, result.masked_word_count()) |i| {
+            result.set_word(i, self.masked_word(i));
+        }
+        result.shl_assign(*n);
+        result
+    }
+}
+
+impl ops::Shr<uint, Bitv> for Bitv {
+    /// Return a copy of the vector with every bit shifted `n` places
+    /// toward the lower indices; see `shr_assign`
+    fn shr(&self, n: &uint) -> Bitv {
+        let mut result = Bitv::new(self.nbits, false);
+        for uint::range(0, result.masked_word_count()) |i| {
+            result.set_word(i, self.masked_word(i));
+        }
+        result.shr_assign(*n);
+        result
+    }
+}
+
+/// The magic bytes opening the serialized form, "BITV"
+static SERIAL_MAGIC: [u8, ..4] = ['B' as u8, 'I' as u8, 'T' as u8,
+                                  'V' as u8];
+/// The version of the serialized layout this module reads and writes
+static SERIAL_VERSION: u8 = 1;
+
+/// Write the low `nbytes` bytes of `value`, least significant first
+fn write_le(writer: @io::Writer, value: u64, nbytes: uint) {
+    let mut v = value;
+    for nbytes.times {
+        writer.write([(v & 0xff) as u8]);
+        v >>= 8;
+    }
+}
+
+/// Read `nbytes` bytes into an integer of the given endianness,
+/// returning None at end of stream
+fn read_le(reader: @io::Reader, nbytes: uint, little: bool) -> Option<u64> {
+    let mut value = 0u64;
+    for uint::range(0, nbytes) |i| {
+        let byte = reader.read_byte();
+        if byte < 0 {
+            return None;
+        }
+        if little {
+            value |= (byte as u64) << (8 * i);
+        } else {
+            value = (value << 8) | byte as u64;
+        }
+    }
+    Some(value)
+}
+
+impl Container for Bitv {
+    /// Return the number of bits in the vector. This is its length in
+    /// the sequence-of-bools reading of a `Bitv`, not the population
+    /// count, which is available by iterating `ones`.
+    fn len(&self) -> uint { self.nbits }
+
+    /// Return true if the vector holds no bits at all. A vector of
+    /// zeros is not empty; see `is_false` for that test.
+    fn is_empty(&self) -> bool { self.nbits == 0 }
+}
+
+impl Mutable for Bitv {
+    /// Set all bits to 0, leaving the length unchanged
+    fn clear(&mut self) {
+        match self.rep {
+          Small(ref mut b) => b.clear(),
+          Big(ref mut s) => for s.each_storage() |w| { *w = 0u }
+        }
+    }
+}
+
+impl Bitv {
+    /// The `i`th storage word with any bits past `nbits` masked off;
+    /// words past the end of the storage read as zero
+    fn masked_word(&self, i: uint) -> uint {
+        let w = match self.rep {
+            Small(ref s) => if i == 0 { s.bits } else { 0 },
+            Big(ref b) => {
+                if i < b.storage.len() { b.storage[i] } else { 0 }
+            }
+        };
+        if (i + 1) * uint::bits <= self.nbits {
+            w
+        } else if i * uint::bits >= self.nbits {
+            0
+        } else {
+            w & ((1 << (self.nbits % uint::bits)) - 1)
+        }
+    }
+
+    /// The `i`th storage word inverted, with any bits past `nbits`
+    /// masked off — the 0 bits of the word, read as 1s
+    fn zeros_word(&self, i: uint) -> uint {
+        let full = if (i + 1) * uint::bits <= self.nbits {
+            !0
+        } else if i * uint::bits >= self.nbits {
+            0
+        } else {
+            (1 << (self.nbits % uint::bits)) - 1
+        };
+        !self.masked_word(i) & full
+    }
+
+    /// The number of words needed to hold `nbits` bits
+    fn masked_word_count(&self) -> uint {
+        uint::div_ceil(self.nbits, uint::bits)
+    }
+
+    /// A full word of bits starting at bit `pos`, assembled across the
+    /// word boundary; bits past the end read as zero
+    fn word_at(&self, pos: uint) -> uint {
+        let w = pos / uint::bits;
+        let o = pos % uint::bits;
+        if o == 0 {
+            self.masked_word(w)
+        } else {
+            self.masked_word(w) >> o |
+                self.masked_word(w + 1) << (uint::bits - o)
+        }
+    }
+
+    /// Set or clear every bit of the `w`th storage word named by `mask`
+    fn apply_word_mask(&mut self, w: uint, mask: uint, value: bool) {
+        match self.rep {
+            Small(ref mut s) => {
+                if value { s.bits |= mask } else { s.bits &= !mask }
+            }
+            Big(ref mut b) => {
+                if value {
+                    b.storage[w] |= mask;
+                } else {
+                    b.storage[w] &= !mask;
+                }
+            }
+        }
+    }
+
+    /**
+     * Set or clear every position yielded by an iterator. Positions
+     * falling in the same storage word are batched into one write, so
+     * clustered index lists update much faster than a loop of `set`
+     * calls. Every position must be less than the length.
+     */
+    pub fn set_from_iter<T: Iterator<uint>>(&mut self, indices: &mut T,
+                                            value: bool) {
+        let mut cur_word = 0;
+        let mut mask = 0;
+        for indices.advance |i| {
+            assert!(i < self.nbits);
+            let w = i / uint::bits;
+            if w != cur_word {
+                if mask != 0 {
+                    self.apply_word_mask(cur_word, mask, value);
+                }
+                cur_word = w;
+                mask = 0;
+            }
+            mask |= 1 << (i % uint::bits);
+        }
+        if mask != 0 {
+            self.apply_word_mask(cur_word, mask, value);
+        }
+    }
+
+    /// Overwrite the `i`th storage word, masking off any bits of `w`
+    /// that fall past `nbits` to keep the representation canonical
+    fn set_word(&mut self, i: uint, w: uint) {
+        let rmd = self.nbits % uint::bits;
+        let w = if rmd != 0 && i == self.nbits / uint::bits {
+            w & ((1 << rmd) - 1)
+        } else {
+            w
+        };
+        match self.rep {
+            Small(ref mut s) => {
+                assert_eq!(i, 0);
+                s.bits = w;
+            }
+            Big(ref mut b) => b.storage[i] = w
+        }
+    }
+
+    /**
+     * Visit successive runs of `chunk_bits` bits as bitvectors of their
+     * own, copying a word at a time rather than a bit at a time, so
+     * fixed-record bitstreams can be split without manual offset math.
+     * The final chunk is shorter when `chunk_bits` does not divide the
+     * length.
+     */
+    pub fn chunks(&self, chunk_bits: uint, f: &fn(&Bitv) -> bool) -> bool {
+        assert!(chunk_bits > 0);
+        let mut base = 0;
+        while base < self.nbits {
+            let len = uint::min(chunk_bits, self.nbits - base);
+            let mut chunk = Bitv::new(len, false);
+            for uint::range(0, uint::div_ceil(len, uint::bits)) |i| {
+                chunk.set_word(i, self.word_at(base + i * uint::bits));
+            }
+            if !f(&chunk) {
+                return false;
+            }
+            base += chunk_bits;
+        }
+        return true;
+    }
+
+    /**
+     * Visit every window of `width` consecutive bits as a uint whose
+     * bit `i` is the window's `i`th bit, sliding one bit at a time, for
+     * pattern scans over bit streams. `width` must fit in a word; use
+     * `windows_bitv` for wider windows. Vectors shorter than `width`
+     * have no windows.
+     */
+    pub fn windows(&self, width: uint, f: &fn(uint) -> bool) -> bool {
+        assert!(width > 0);
+        assert!(width <= uint::bits);
+        if width > self.nbits {
+            return true;
+        }
+        let mask = if width == uint::bits {
+            !0
+        } else {
+            (1 << width) - 1
+        };
+        for uint::range(0, self.nbits - width + 1) |pos| {
+            if !f(self.word_at(pos) & mask) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /**
+     * Visit every window of `width` consecutive bits as a bitvector of
+     * its own, for widths too large for `windows` to pack into a uint
+     */
+    pub fn windows_bitv(&self, width: uint,
+                        f: &fn(&Bitv) -> bool) -> bool {
+        assert!(width > 0);
+        if width > self.nbits {
+            return true;
+        }
+        for uint::range(0, self.nbits - width + 1) |pos| {
+            let mut window = Bitv::new(width, false);
+            for uint::range(0, uint::div_ceil(width, uint::bits)) |i| {
+                window.set_word(i, self.word_at(pos + i * uint::bits));
+            }
+            if !f(&window) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /**
+     * Find the first run of `k` consecutive clear bits starting at or
+     * after `from`, scanning a word at a time and carrying the run
+     * across word boundaries. Returns the index of the run's first bit,
+     * or None if no such run fits before the end of the vector — the
+     * core primitive for contiguous-block allocators built on bitmaps.
+     */
+    pub fn find_clear_run(&self, k: uint, from: uint) -> Option<uint> {
+        assert!(k > 0);
+        let mut start = from;
+        let mut pos = from;
+        while start + k <= self.nbits {
+            let w = self.word_at(pos);
+            if w == 0 {
+                pos += uint::bits;
+            } else {
+                // the lowest set bit ends the run beginning at `start`
+                let mut tz = 0;
+                while w >> tz & 1 == 0 { tz += 1; }
+                pos += tz;
+            }
+            if pos - start >= k {
+                return Some(start);
+            }
+            if w != 0 {
+                // step over the set bit and restart the run
+                pos += 1;
+                start = pos;
+            }
+        }
+        None
+    }
+
+    /**
+     * The longest contiguous run of `bit` values, as its start index
+     * and length, scanning a word at a time. Ties go to the earliest
+     * run; a vector with no `bit` values at all yields `(0, 0)`.
+     */
+    pub fn max_run(&self, bit: bool) -> (uint, uint) {
+        let mut best_start = 0;
+        let mut best_len = 0;
+        let mut start = 0;
+        let mut pos = 0;
+        while pos < self.nbits {
+            // bits set where the vector disagrees with `bit`
+            let w = if bit {
+                !self.word_at(pos)
+            } else {
+                self.word_at(pos)
+            };
+            if w == 0 {
+                pos += uint::bits;
+            } else {
+                // the lowest set bit ends the current run
+                let mut tz = 0;
+                while w >> tz & 1 == 0 { tz += 1; }
+                pos += tz;
+            }
+            let end = uint::min(pos, self.nbits);
+            if end - start > best_len {
+                best_start = start;
+                best_len = end - start;
+            }
+            if w != 0 {
+                // step over the mismatch and restart the run
+                pos = end + 1;
+                start = pos;
+            }
+        }
+        (best_start, best_len)
+    }
+
+    /**
+     * Serialize the vector in the canonical portable layout: the magic
+     * bytes `BITV`, a format version, the writer's word size in bits,
+     * an endianness flag, the bit length as a little-endian u64, and
+     * the storage words. Readers on any build can decode it; see
+     * `read_from`.
+     */
+    pub fn write_to(&self, writer: @io::Writer) {
+        writer.write(SERIAL_MAGIC);
+        writer.write([SERIAL_VERSION, uint::bits as u8, 1]);
+        write_le(writer, self.nbits as u64, 8);
+        for uint::range(0, self.masked_word_count()) |i| {
+            write_le(writer, self.masked_word(i) as u64, uint::bytes);
+        }
+    }
+
+    /**
+     * Decode a vector serialized by `write_to`, converting between
+     * word sizes and endiannesses as the header dictates. Returns None
+     * if the stream is truncated or not in the format.
+     */
+    pub fn read_from(reader: @io::Reader) -> Option<Bitv> {
+        let mut bitv = Bitv::new(0, false);
+        if bitv.read_from_into(reader) {
+            Some(bitv)
+        } else {
+            None
+        }
+    }
+
+    /**
+     * Decode a `write_to` stream into this vector in place, reusing
+     * the existing storage allocation like `from_bytes_into`. Returns
+     * false, leaving the contents unspecified, if the stream is
+     * truncated or not in the format.
+     */
+    pub fn read_from_into(&mut self, reader: @io::Reader) -> bool {
+        for uint::range(0, SERIAL_MAGIC.len()) |i| {
+            if reader.read_byte() != SERIAL_MAGIC[i] as int {
+                return false;
+            }
+        }
+        if reader.read_byte() != SERIAL_VERSION as int {
+            return false;
+        }
+        let word_bits = match reader.read_byte() {
+            8 => 8u, 16 => 16u, 32 => 32u, 64 => 64u,
+            _ => return false
+        };
+        let little = match reader.read_byte() {
+            0 => false,
+            1 => true,
+            _ => return false
+        };
+        let nbits = match read_le(reader, 8, little) {
+            None => return false,
+            Some(n) => n as uint
+        };
+        self.reset_to_length(nbits);
+        for uint::range(0, uint::div_ceil(nbits, word_bits)) |w| {
+            let word = match read_le(reader, word_bits / 8, little) {
+                None => return false,
+                Some(word) => word
+            };
+            for uint::range(0, word_bits) |b| {
+                let bit = w * word_bits + b;
+                if word & (1u64 << b) != 0 && bit < nbits {
+                    self.set(bit, true);
+                }
+            }
+        }
+        return true;
+    }
+
+    /// Re-shape the vector to hold `nbits` zeroed bits, reusing the
+    /// existing storage allocation where it is large enough
+    fn reset_to_length(&mut self, nbits: uint) {
+        let need = nbits / uint::bits +
+                   if nbits % uint::bits == 0 {0} else {1};
+        let mut rebuild = true;
+        match self.rep {
+            Small(ref mut s) if nbits <= uint::bits => {
+                s.bits = 0;
+                rebuild = false;
+            }
+            Big(ref mut b) if nbits > uint::bits => {
+                let len = b.storage.len();
+                if len < need {
+                    b.storage.grow(need - len, &0);
+                } else {
+                    b.storage.truncate(need);
+                }
+                for b.each_storage |w| { *w = 0; }
+                rebuild = false;
+            }
+            _ => ()
+        }
+        if rebuild {
+            // the representation has to change; allocate afresh
+            *self = Bitv::new(nbits, false);
+        } else {
+            self.nbits = nbits;
+        }
+    }
+
+    /**
+     * Refill the vector from a byte slice in the `from_bytes` layout,
+     * reusing the existing storage allocation, so streaming consumers
+     * that repeatedly decode bitmaps of similar size stop allocating
+     * per message.
+     */
+    pub fn from_bytes_into(&mut self, bytes: &[u8]) {
+        self.reset_to_length(bytes.len() * 8);
+        for uint::range(0, bytes.len()) |i| {
+            let b = bytes[i] as uint;
+            for uint::range(0, 8) |j| {
+                if b >> (7 - j) & 1 == 1 {
+                    self.set(i * 8 + j, true);
+                }
+            }
+        }
+    }
+
+    /**
+     * Reads the vector as an arbitrary-precision integer, with bit `i`
+     * contributing `2^i`
+     */
+    pub fn to_biguint(&self) -> BigUint {
+        let mut digits = ~[];
+        for uint::range(0, self.masked_word_count()) |i| {
+            let (hi, lo) = BigDigit::from_uint(self.masked_word(i));
+            digits.push(lo);
+            digits.push(hi);
+        }
+        BigUint::new(digits)
+    }
+
+    /**
+     * Writes an arbitrary-precision integer into a bitvector of length
+     * `nbits`, with bit `i` holding the `2^i` digit. Bits of `n` at or
+     * past `nbits` are discarded.
+     */
+    pub fn from_biguint(n: &BigUint, nbits: uint) -> Bitv {
+        let mut bitv = Bitv::new(nbits, false);
+        let modulus = BigUint::from_uint(1 << BigDigit::bits);
+        let mut cur = copy *n;
+        let mut base = 0;
+        while base < nbits && !cur.is_zero() {
+            let digit = (cur % modulus).to_uint();
+            for uint::range(0, BigDigit::bits) |b| {
+                if digit & (1 << b) != 0 && base + b < nbits {
+                    bitv.set(base + b, true);
+                }
+            }
+            cur = cur >> BigDigit::bits;
+            base += BigDigit::bits;
+        }
+        bitv
+    }
+
+    /// Fold a word-wise binary operation against `other` into a bit
+    /// visitor, treating either vector as zero past its length
+    fn binop_each(&self, other: &Bitv, op: &fn(uint, uint) -> uint,
+                  f: &fn(&uint) -> bool) -> bool {
+        let n = uint::max(self.masked_word_count(),
+                          other.masked_word_count());
+        for uint::range(0, n) |i| {
+            let w = op(self.masked_word(i), other.masked_word(i));
+            if !iterate_bits(i * uint::bits, w, |b| f(&b)) {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+/**
+ * A `Bitv` viewed as a `Set` over the fixed universe `[0, nbits)`:
+ * bit `i` is membership of `i`. Queries about values at or past the
+ * length simply answer "not present", but `insert` of such a value
+ * fails, since a bitvector cannot grow. Operands of the binary
+ * operations may have different lengths; the shorter is read as if
+ * padded with zeros.
+ */
+impl Set<uint> for Bitv {
+    /// Return true if `value` is below the vector's length and its
+    /// bit is set
+    fn contains(&self, value: &uint) -> bool {
+        *value < self.nbits && self.get(*value)
+    }
+
+    /// Set the bit for `value`, returning true if it was clear.
+    /// Fails if `value` is not below the vector's length.
+    fn insert(&mut self, value: uint) -> bool {
+        assert!(value < self.nbits);
+        if self.get(value) {
+            return false;
+        }
+        self.set(value, true);
+        return true;
+    }
+
+    /// Clear the bit for `value`, returning true if it was set
+    fn remove(&mut self, value: &uint) -> bool {
+        if !self.contains(value) {
+            return false;
+        }
+        self.set(*value, false);
+        return true;
+    }
+
+    /// Return true if no bit is set in both vectors
+    fn is_disjoint(&self, other: &Bitv) -> bool {
+        let n = uint::max(self.masked_word_count(),
+                          other.masked_word_count());
+        for uint::range(0, n) |i| {
+            if self.masked_word(i) & other.masked_word(i) != 0 {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Return true if every set bit of `self` is set in `other`
+    fn is_subset(&self, other: &Bitv) -> bool {
+        for uint::range(0, self.masked_word_count()) |i| {
+            if self.masked_word(i) & !other.masked_word(i) != 0 {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Return true if every set bit of `other` is set in `self`
+    fn is_superset(&self, other: &Bitv) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Visit the positions set in `self` but not in `other`
+    fn difference(&self, other: &Bitv, f: &fn(&uint) -> bool) -> bool {
+        self.binop_each(other, |w1, w2| w1 & !w2, f)
+    }
+
+    /// Visit the positions set in exactly one of the two vectors
+    fn symmetric_difference(&self, other: &Bitv,
+                            f: &fn(&uint) -> bool) -> bool {
+        self.binop_each(other, |w1, w2| w1 ^ w2, f)
+    }
+
+    /// Visit the positions set in both vectors
+    fn intersection(&self, other: &Bitv, f: &fn(&uint) -> bool) -> bool {
+        self.binop_each(other, |w1, w2| w1 & w2, f)
+    }
+
+    /// Visit the positions set in either vector
+    fn union(&self, other: &Bitv, f: &fn(&uint) -> bool) -> bool {
+        self.binop_each(other, |w1, w2| w1 | w2, f)
+    }
+}
+
+/**
+ * A read-only view of every `step`th bit of a bitvector, created with
+ * `Bitv::stride_view`. Lets interleaved channel data be examined per
+ * channel without copying it out first.
+ */
+pub struct BitvStride<'self> {
+    priv bitv: &'self Bitv,
+    priv offset: uint,
+    priv step: uint
+}
+
+impl Bitv {
+    /**
+     * A view of the bits at positions `offset`, `offset + step`,
+     * `offset + 2 * step`, ...
+     */
+    pub fn stride_view<'a>(&'a self, offset: uint, step: uint)
+                           -> BitvStride<'a> {
+        assert!(step > 0);
+        BitvStride{bitv: self, offset: offset, step: step}
+    }
+}
+
+impl<'self> BitvStride<'self> {
+    /// The number of bits visible through the view
+    pub fn len(&self) -> uint {
+        if self.offset >= self.bitv.nbits {
+            0
+        } else {
+            (self.bitv.nbits - self.offset - 1) / self.step + 1
+        }
+    }
+
+    /// The `i`th bit of the view
+    pub fn get(&self, i: uint) -> bool {
+        assert!(i < self.len());
+        self.bitv.get(self.offset + i * self.step)
+    }
+
+    /// Visit the viewed bits in order
+    pub fn each(&self, f: &fn(bool) -> bool) -> bool {
+        let mut pos = self.offset;
+        while pos < self.bitv.nbits {
+            if !f(self.bitv.get(pos)) {
+                return false;
+            }
+            pos += self.step;
+        }
+        return true;
+    }
+
+    /// The number of set bits visible through the view
+    pub fn count_ones(&self) -> uint {
+        let mut count = 0;
+        for self.each |bit| {
+            if bit {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Copy the viewed bits out into a bitvector of their own
+    pub fn to_bitv(&self) -> Bitv {
+        from_fn(self.len(), |i| self.get(i))
+    }
+}
+
+/**
+ * An external iterator over the bits of a bitvector in index order,
+ * created with `Bitv::iter`. Unlike the internal `each` protocol it
+ * composes with the standard iterator adaptors — enumerate, zip,
+ * filter, collect and the rest.
+ */
+pub struct BitvIterator<'self> {
+    priv bitv: &'self Bitv,
+    /// The index of the next bit to yield
+    priv idx: uint
+}
+
+impl Bitv {
+    /// An external iterator over the bits, lowest index first
+    pub fn iter<'a>(&'a self) -> BitvIterator<'a> {
+        BitvIterator{bitv: self, idx: 0}
+    }
+}
+
+impl<'self> Iterator<bool> for BitvIterator<'self> {
+    fn next(&mut self) -> Option<bool> {
+        if self.idx >= self.bitv.nbits {
+            None
+        } else {
+            let b = self.bitv.get(self.idx);
+            self.idx += 1;
+            Some(b)
+        }
+    }
+}
+
+/**
+ * An external iterator over the indices of a bitvector's 1 bits,
+ * created with `Bitv::ones_iter`. Each storage word is loaded once and
+ * its set bits extracted by trailing-zero counts, so sparse vectors
+ * iterate in time proportional to the words, not the bits.
+ */
+pub struct BitvOnesIterator<'self> {
+    priv bitv: &'self Bitv,
+    /// The loaded word, with already-yielded bits cleared
+    priv word: uint,
+    /// The storage index the loaded word came from
+    priv idx: uint
+}
+
+/// An external iterator over the indices of a bitvector's 0 bits,
+/// created with `Bitv::zeros_iter`
+pub struct BitvZerosIterator<'self> {
+    priv bitv: &'self Bitv,
+    /// The loaded word with its in-range bits inverted, so yielding
+    /// works by the same trailing-zero extraction as the ones iterator
+    priv word: uint,
+    /// The storage index the loaded word came from
+    priv idx: uint
+}
+
+impl Bitv {
+    /// An external iterator over the indices of the 1 bits, in order
+    pub fn ones_iter<'a>(&'a self) -> BitvOnesIterator<'a> {
+        BitvOnesIterator{bitv: self, word: self.masked_word(0), idx: 0}
+    }
+
+    /// An external iterator over the indices of the 0 bits, in order
+    pub fn zeros_iter<'a>(&'a self) -> BitvZerosIterator<'a> {
+        BitvZerosIterator{bitv: self, word: self.zeros_word(0), idx: 0}
+    }
+}
+
+impl<'self> Iterator<uint> for BitvOnesIterator<'self> {
+    fn next(&mut self) -> Option<uint> {
+        let nwords = self.bitv.masked_word_count();
+        while self.word == 0 {
+            self.idx += 1;
+            if self.idx >= nwords {
+                return None;
+            }
+            self.word = self.bitv.masked_word(self.idx);
+        }
+        let mut tz = 0;
+        while self.word >> tz & 1 == 0 { tz += 1; }
+        self.word &= !(1 << tz);
+        Some(self.idx * uint::bits + tz)
+    }
+}
+
+impl<'self> Iterator<uint> for BitvZerosIterator<'self> {
+    fn next(&mut self) -> Option<uint> {
+        let nwords = self.bitv.masked_word_count();
+        while self.word == 0 {
+            self.idx += 1;
+            if self.idx >= nwords {
+                return None;
+            }
+            self.word = self.bitv.zeros_word(self.idx);
+        }
+        let mut tz = 0;
+        while self.word >> tz & 1 == 0 { tz += 1; }
+        self.word &= !(1 << tz);
+        Some(self.idx * uint::bits + tz)
+    }
+}
+
+/// The bit numbering within each byte of a `BitvView`
+#[deriving(Eq)]
+pub enum BitOrder {
+    /// Bit 0 is the most significant bit of byte 0, as `from_bytes`
+    /// reads it
+    MsbFirst,
+    /// Bit 0 is the least significant bit of byte 0
+    LsbFirst
+}
+
+/**
+ * A read-only bitvector view of a borrowed byte slice. Parsers over
+ * mapped files or network buffers can inspect bits in place instead of
+ * paying the `from_bytes` copy first.
+ */
+pub struct BitvView<'self> {
+    priv bytes: &'self [u8],
+    priv nbits: uint,
+    priv order: BitOrder
+}
+
+impl<'self> BitvView<'self> {
+    /// View the first `nbits` bits of `bytes` in the given bit order
+    pub fn new(bytes: &'self [u8], nbits: uint, order: BitOrder)
+               -> BitvView<'self> {
+        assert!(nbits <= bytes.len() * 8);
+        BitvView{bytes: bytes, nbits: nbits, order: order}
+    }
+
+    /// The number of bits visible through the view
+    pub fn len(&self) -> uint { self.nbits }
+
+    /// The `i`th bit of the view
+    pub fn get(&self, i: uint) -> bool {
+        assert!(i < self.nbits);
+        let b = self.bytes[i / 8] as uint;
+        let shift = match self.order {
+            MsbFirst => 7 - i % 8,
+            LsbFirst => i % 8
+        };
+        b >> shift & 1 == 1
+    }
+
+    /// Visit the viewed bits in order
+    pub fn each(&self, f: &fn(bool) -> bool) -> bool {
+        for uint::range(0, self.nbits) |i| {
+            if !f(self.get(i)) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// The number of set bits visible through the view
+    pub fn count_ones(&self) -> uint {
+        let mut count = 0;
+        let full = self.nbits / 8;
+        for uint::range(0, full) |i| {
+            count += population_count(self.bytes[i] as uint);
+        }
+        // mask the partial last byte to the bits inside the view
+        let rem = self.nbits % 8;
+        if rem > 0 {
+            let mask = match self.order {
+                MsbFirst => 0xff << (8 - rem),
+                LsbFirst => (1 << rem) - 1
+            };
+            count += population_count(self.bytes[full] as uint & mask);
+        }
+        count
+    }
+
+    /// Return true if the view and `other` hold the same bit sequence
+    pub fn equal(&self, other: &Bitv) -> bool {
+        if self.nbits != other.nbits {
+            return false;
+        }
+        for uint::range(0, self.nbits) |i| {
+            if self.get(i) != other.get(i) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Copy the viewed bits out into a bitvector of their own
+    pub fn to_bitv(&self) -> Bitv {
+        from_fn(self.nbits, |i| self.get(i))
+    }
+}
+
+/**
+ * The operations shared by the uint-keyed bit collections, so generic
+ * algorithms (dataflow drivers, graph traversals) can be written once
+ * and run against whichever backing suits the workload. The method
+ * names avoid those of `Set` and the inherent methods to keep calls on
+ * the concrete types unambiguous.
+ *
+ * Implementors with a fixed width, like `Bitv`, fail when given a
+ * value at or past that width; the growable sets accept any value.
+ */
+pub trait BitCollection {
+    /// Return true if `value` is in the collection
+    fn has_bit(&self, value: uint) -> bool;
+    /// Add a value. Return true if it was not already present.
+    fn add_bit(&mut self, value: uint) -> bool;
+    /// Remove a value. Return true if it was present.
+    fn remove_bit(&mut self, value: uint) -> bool;
+    /// Add every value of `other`. Return true if `self` changed.
+    fn union_bits(&mut self, other: &Self) -> bool;
+    /// Drop every value not in `other`. Return true if `self` changed.
+    fn intersect_bits(&mut self, other: &Self) -> bool;
+    /// Visit the values in increasing order
+    fn each_bit(&self, f: &fn(uint) -> bool) -> bool;
+}
+
+impl BitCollection for Bitv {
+    fn has_bit(&self, value: uint) -> bool {
+        value < self.nbits && self.get(value)
+    }
+
+    fn add_bit(&mut self, value: uint) -> bool {
+        if self.get(value) {
+            false
+        } else {
+            self.set(value, true);
+            true
+        }
+    }
+
+    fn remove_bit(&mut self, value: uint) -> bool {
+        if self.get(value) {
+            self.set(value, false);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn union_bits(&mut self, other: &Bitv) -> bool {
+        self.union(other)
+    }
+
+    fn intersect_bits(&mut self, other: &Bitv) -> bool {
+        self.intersect(other)
+    }
+
+    fn each_bit(&self, f: &fn(uint) -> bool) -> bool {
+        self.ones(f)
+    }
+}
+
+impl BitCollection for BitvSet {
+    fn has_bit(&self, value: uint) -> bool {
+        self.contains(&value)
+    }
+
+    fn add_bit(&mut self, value: uint) -> bool {
+        self.insert(value)
+    }
+
+    fn remove_bit(&mut self, value: uint) -> bool {
+        self.remove(&value)
+    }
+
+    fn union_bits(&mut self, other: &BitvSet) -> bool {
+        // a union only grows, so the length says whether it changed
+        let before = self.len();
+        self.union_with(other);
+        self.len() != before
+    }
+
+    fn intersect_bits(&mut self, other: &BitvSet) -> bool {
+        let before = self.len();
+        self.intersect_with(other);
+        self.len() != before
+    }
+
+    fn each_bit(&self, f: &fn(uint) -> bool) -> bool {
+        self.each(|&v| f(v))
+    }
+}
+
+/**
+ * Count the set bits of a word. Not every platform this library builds
+ * on has a popcount instruction the compiler will emit, so this uses
+ * parallel (SWAR) summation; the constants are written as divisions of
+ * `!0` so the same code covers every word size.
+ */
+#[inline]
+pub fn population_count(w: uint) -> uint {
+    let w = w - ((w >> 1) & (!0 / 3));
+    let w = (w & (!0 / 5)) + ((w >> 2) & (!0 / 5));
+    let w = (w + (w >> 4)) & (!0 / 17);
+    (w * (!0 / 255)) >> (uint::bits - 8)
+}
+
+#[inline]
+pub fn iterate_bits(base: uint, bits: uint, f: &fn(uint) -> bool) -> bool {
+    if bits == 0 {
+        return true;
+    }
+    for uint::range(0, uint::bits) |i| {
+        if bits & (1 << i) != 0 {
+            if !f(base + i) {
+                return false;
+            }
+        }
+    }
+    return true;
+}
+
+/// An implementation of a set using a bit vector as an underlying
+/// representation for holding numerical elements.
+///
+/// It should also be noted that the amount of storage necessary for holding a
+/// set of objects is proportional to the maximum of the objects when viewed
+/// as a uint.
+pub struct BitvSet {
+    priv size: uint,
+
+    // In theory this is a Bitv instead of always a BigBitv, but knowing that
+    // there's an array of storage makes our lives a whole lot easier when
+    // performing union/intersection/etc operations
+    priv bitv: BigBitv
+}
+
+impl BitvSet {
+    /// Creates a new bit vector set with initially no contents
+    pub fn new() -> BitvSet {
+        BitvSet{ size: 0, bitv: BigBitv::new(~[0]) }
+    }
+
+    /// Creates a new bit vector set from the given bit vector
+    pub fn from_bitv(bitv: Bitv) -> BitvSet {
+        let size = bitv.count_ones();
+        let Bitv{rep, _} = bitv;
+        match rep {
+            Big(~b) => BitvSet{ size: size, bitv: b },
+            Small(~SmallBitv{bits}) =>
+                BitvSet{ size: size, bitv: BigBitv{ storage: ~[bits] } },
+        }
+    }
+
+    /// Returns the capacity in bits for this bit vector. Inserting any
+    /// element less than this amount will not trigger a resizing.
+    pub fn capacity(&self) -> uint { self.bitv.storage.len() * uint::bits }
+
+    /// Returns the number of bytes of heap memory owned by this set
+    pub fn memory_usage(&self) -> uint {
+        self.bitv.storage.capacity() * uint::bytes
+    }
+
+    /// Consumes this set to return the underlying bit vector
+    pub fn unwrap(self) -> Bitv {
+        let cap = self.capacity();
+        let BitvSet{bitv, _} = self;
+        return Bitv{ nbits:cap, rep: Big(~bitv) };
+    }
+
+    /// Creates a new bit vector set from a borrowed bit vector. Unlike
+    /// `from_bitv` the argument survives, so its logical length can be
+    /// handed back to `to_bitv` later for a lossless round trip.
+    pub fn from_bitv_ref(bitv: &Bitv) -> BitvSet {
+        let mut set = BitvSet::new();
+        for bitv.ones |i| {
+            set.insert(i);
+        }
+        set
+    }
+
+    /**
+     * A random set over `[0, universe)` where each element is a member
+     * independently with probability `density`. Each storage word is
+     * built from whole random words combined by the binary digits of
+     * the density, so no per-bit draws are made.
+     */
+    pub fn random<R: rand::Rng>(universe: uint, density: float,
+                                rng: &mut R) -> BitvSet {
+        assert!(0.0 <= density && density <= 1.0);
+        let scaled = (density * 65536.0) as uint;
+        let nwords = uint::div_ceil(universe, uint::bits);
+        let mut storage = vec::from_elem(uint::max(1, nwords), 0u);
+        let mut size = 0;
+        for uint::range(0, nwords) |i| {
+            let mut w = 0;
+            if scaled >= 65536 {
+                w = !0;
+            } else {
+                // Bernoulli(density) per bit: fold a random word in per
+                // binary digit, OR for a 1 digit and AND for a 0 digit,
+                // deepest digit first
+                for uint::range(0, 16) |b| {
+                    let r: uint = rng.gen();
+                    w = if scaled & (1 << b) != 0 { r | w } else { r & w };
+                }
+            }
+            if i == nwords - 1 && universe % uint::bits != 0 {
+                w &= (1 << (universe % uint::bits)) - 1;
+            }
+            storage[i] = w;
+            size += population_count(w);
+        }
+        BitvSet{size: size, bitv: BigBitv::new(storage)}
+    }
+
+    /// Copies the set out as a bit vector of exactly `nbits` bits,
+    /// unlike `unwrap` whose result is padded out to the capacity.
+    /// Members at or past `nbits` are discarded.
+    pub fn to_bitv(&self, nbits: uint) -> Bitv {
+        let mut bitv = Bitv::new(nbits, false);
+        for self.each |&i| {
+            if i >= nbits {
+                break;
+            }
+            bitv.set(i, true);
+        }
+        bitv
+    }
+
+    #[inline]
+    fn other_op(&mut self, other: &BitvSet, f: &fn(uint, uint) -> uint) {
+        if self.capacity() < other.capacity() {
+            self.bitv.storage.grow(other.capacity() / uint::bits, &0);
+        }
+        for other.bitv.storage.iter().enumerate().advance |(i, &w)| {
+            let old = self.bitv.storage[i];
+            let new = f(old, w);
+            self.bitv.storage[i] = new;
+            self.size += population_count(new) - population_count(old);
+        }
+    }
+
+    /// Union in-place with the specified other bit vector
+    pub fn union_with(&mut self, other: &BitvSet) {
+        self.other_op(other, |w1, w2| w1 | w2);
+    }
+
+    /// Intersect in-place with the specified other bit vector
+    pub fn intersect_with(&mut self, other: &BitvSet) {
+        self.other_op(other, |w1, w2| w1 & w2);
+    }
+
+    /// Difference in-place with the specified other bit vector
+    pub fn difference_with(&mut self, other: &BitvSet) {
+        self.other_op(other, |w1, w2| w1 & !w2);
+    }
+
+    /// Symmetric difference in-place with the specified other bit vector
+    pub fn symmetric_difference_with(&mut self, other: &BitvSet) {
+        self.other_op(other, |w1, w2| w1 ^ w2);
+    }
+
+    /// The `i`th storage word, reading past the capacity as zero
+    #[inline]
+    fn word_or_zero(&self, i: uint) -> uint {
+        if i < self.bitv.storage.len() { self.bitv.storage[i] } else { 0 }
+    }
+
+    /// Overwrite `dest` with `f` applied word-wise to `self` and
+    /// `other`, reusing `dest`'s storage and growing it only when it is
+    /// shorter than the operands
+    #[inline]
+    fn op_into(&self, other: &BitvSet, dest: &mut BitvSet,
+               f: &fn(uint, uint) -> uint) {
+        let nwords = uint::max(self.bitv.storage.len(),
+                               other.bitv.storage.len());
+        let len = dest.bitv.storage.len();
+        if len < nwords {
+            dest.bitv.storage.grow(nwords - len, &0);
+        }
+        let mut size = 0;
+        for uint::range(0, dest.bitv.storage.len()) |i| {
+            let w = if i < nwords {
+                f(self.word_or_zero(i), other.word_or_zero(i))
+            } else {
+                0
+            };
+            dest.bitv.storage[i] = w;
+            size += population_count(w);
+        }
+        dest.size = size;
+    }
+
+    /// Write the union of `self` and `other` into `dest`, overwriting
+    /// its previous contents without allocating, for fixpoint loops
+    /// that recompute the same combinations many times
+    pub fn union_into(&self, other: &BitvSet, dest: &mut BitvSet) {
+        self.op_into(other, dest, |w1, w2| w1 | w2);
+    }
+
+    /// Write the intersection of `self` and `other` into `dest`,
+    /// overwriting its previous contents without allocating
+    pub fn intersect_into(&self, other: &BitvSet, dest: &mut BitvSet) {
+        self.op_into(other, dest, |w1, w2| w1 & w2);
+    }
+
+    /// Write the difference of `self` and `other` into `dest`,
+    /// overwriting its previous contents without allocating
+    pub fn difference_into(&self, other: &BitvSet, dest: &mut BitvSet) {
+        self.op_into(other, dest, |w1, w2| w1 & !w2);
+    }
+
+    /// Write the symmetric difference of `self` and `other` into
+    /// `dest`, overwriting its previous contents without allocating
+    pub fn symmetric_difference_into(&self, other: &BitvSet,
+                                     dest: &mut BitvSet) {
+        self.op_into(other, dest, |w1, w2| w1 ^ w2);
+    }
+
+    /// Union in-place with a bitvector operand, so a mask built by
+    /// Bitv-level code can be applied without copying it into a second
+    /// BitvSet first
+    pub fn union_with_bitv(&mut self, other: &Bitv) {
+        let nwords = other.masked_word_count();
+        let len = self.bitv.storage.len();
+        if len < nwords {
+            self.bitv.storage.grow(nwords - len, &0);
+        }
+        for uint::range(0, nwords) |i| {
+            let old = self.bitv.storage[i];
+            let new = old | other.masked_word(i);
+            if new != old {
+                self.bitv.storage[i] = new;
+                self.size += population_count(new) -
+                             population_count(old);
+            }
+        }
+    }
+
+    /// Intersect in-place with a bitvector operand; members at or past
+    /// its length are dropped
+    pub fn intersect_with_bitv(&mut self, other: &Bitv) {
+        let len = self.bitv.storage.len();
+        for uint::range(0, len) |i| {
+            let old = self.bitv.storage[i];
+            let new = old & other.masked_word(i);
+            if new != old {
+                self.bitv.storage[i] = new;
+                self.size -= population_count(old) -
+                             population_count(new);
+            }
+        }
+    }
+
+    /// Union in-place with the values of any uint iterator, so operands
+    /// held in other set representations need not be converted first
+    pub fn union_with_iter<T: Iterator<uint>>(&mut self, iter: &mut T) {
+        for iter.advance |v| {
+            self.insert(v);
+        }
+    }
+
+    /// OR a mask of new members into the `w`th storage word, growing
+    /// the storage to reach it
+    fn apply_insert_mask(&mut self, w: uint, mask: uint) {
+        let len = self.bitv.storage.len();
+        if len <= w {
+            self.bitv.storage.grow(w - len + 1, &0);
+        }
+        let old = self.bitv.storage[w];
+        let new = old | mask;
+        if new != old {
+            self.bitv.storage[w] = new;
+            self.size += population_count(new) - population_count(old);
+        }
+    }
+
+    /// Clear a mask of members out of the `w`th storage word
+    fn apply_remove_mask(&mut self, w: uint, mask: uint) {
+        if w >= self.bitv.storage.len() {
+            return;
+        }
+        let old = self.bitv.storage[w];
+        let new = old & !mask;
+        if new != old {
+            self.bitv.storage[w] = new;
+            self.size -= population_count(old) - population_count(new);
+        }
+    }
+
+    /// Insert every value of an iterator, batching values that share a
+    /// storage word into a single write
+    pub fn insert_all<T: Iterator<uint>>(&mut self, iter: &mut T) {
+        let mut cur = 0;
+        let mut mask = 0;
+        for iter.advance |v| {
+            let w = v / uint::bits;
+            if w != cur {
+                if mask != 0 {
+                    self.apply_insert_mask(cur, mask);
+                }
+                cur = w;
+                mask = 0;
+            }
+            mask |= 1 << (v % uint::bits);
+        }
+        if mask != 0 {
+            self.apply_insert_mask(cur, mask);
+        }
+    }
+
+    /// Remove every value of an iterator, batching values that share a
+    /// storage word into a single write instead of calling `remove`
+    /// element by element
+    pub fn remove_all<T: Iterator<uint>>(&mut self, iter: &mut T) {
+        let mut cur = 0;
+        let mut mask = 0;
+        for iter.advance |v| {
+            let w = v / uint::bits;
+            if w != cur {
+                if mask != 0 {
+                    self.apply_remove_mask(cur, mask);
+                }
+                cur = w;
+                mask = 0;
+            }
+            mask |= 1 << (v % uint::bits);
+        }
+        if mask != 0 {
+            self.apply_remove_mask(cur, mask);
+        }
+    }
+
+    /// Difference in-place with the values of any uint iterator
+    pub fn difference_with_iter<T: Iterator<uint>>(&mut self, iter: &mut T) {
+        for iter.advance |v| {
+            self.remove(&v);
+        }
+    }
+
+    /// Intersect in-place with a strictly increasing uint iterator, such
+    /// as a `TreeSet<uint>` yields. Fails if the values ever decrease.
+    pub fn intersect_with_sorted_iter<T: Iterator<uint>>(&mut self,
+                                                         iter: &mut T) {
+        let mut kept = BitvSet::new();
+        let mut last = None;
+        for iter.advance |v| {
+            match last {
+                Some(prev) => assert!(prev < v),
+                None => ()
+            }
+            last = Some(v);
+            if self.contains(&v) {
+                kept.insert(v);
+            }
+        }
+        *self = kept;
+    }
+
+    /// Union in-place with a borrowed `TreeSet<uint>`
+    pub fn union_with_tree_set(&mut self, other: &TreeSet<uint>) {
+        self.union_with_iter(&mut other.iter().transform(|&v| v));
+    }
+
+    /// Intersect in-place with a borrowed `TreeSet<uint>`
+    pub fn intersect_with_tree_set(&mut self, other: &TreeSet<uint>) {
+        self.intersect_with_sorted_iter(
+            &mut other.iter().transform(|&v| v));
+    }
+
+    /// Union in-place with a borrowed `HashSet<uint>`
+    pub fn union_with_hash_set(&mut self, other: &HashSet<uint>) {
+        self.union_with_iter(&mut other.iter().transform(|&v| v));
+    }
+
+    /// Intersect in-place with a borrowed `HashSet<uint>`. The hash
+    /// order is not sorted, so this filters by membership instead.
+    pub fn intersect_with_hash_set(&mut self, other: &HashSet<uint>) {
+        let mut kept = BitvSet::new();
+        for self.each |&v| {
+            if other.contains(&v) {
+                kept.insert(v);
+            }
+        }
+        *self = kept;
+    }
+
+    /// Return true if the set is a subset of `other` and not equal to
+    /// it. The stored sizes rule most cases out without comparing words.
+    pub fn is_proper_subset(&self, other: &BitvSet) -> bool {
+        self.size < other.size && self.is_subset(other)
+    }
+
+    /// Return true if the set is a superset of `other` and not equal
+    /// to it
+    pub fn is_proper_superset(&self, other: &BitvSet) -> bool {
+        other.is_proper_subset(self)
+    }
+
+    /// Add `n` to every member, re-basing the whole set by shifting
+    /// the storage words up
+    pub fn shift_up(&mut self, n: uint) {
+        if n == 0 || self.size == 0 {
+            return;
+        }
+        let k = n / uint::bits;
+        let s = n % uint::bits;
+        let len = self.bitv.storage.len();
+        let mut new = vec::from_elem(len + k + 1, 0u);
+        for uint::range(0, len) |i| {
+            let w = self.bitv.storage[i];
+            if s == 0 {
+                new[i + k] = w;
+            } else {
+                new[i + k] |= w << s;
+                new[i + k + 1] = w >> (uint::bits - s);
+            }
+        }
+        self.bitv.storage = new;
+    }
+
+    /// Subtract `n` from every member, dropping members smaller than
+    /// `n`, by shifting the storage words down
+    pub fn shift_down(&mut self, n: uint) {
+        if n == 0 || self.size == 0 {
+            return;
+        }
+        let k = n / uint::bits;
+        let s = n % uint::bits;
+        let len = self.bitv.storage.len();
+        let kept = if k < len { len - k } else { 0 };
+        let mut new = vec::from_elem(uint::max(1, kept), 0u);
+        for uint::range(0, kept) |i| {
+            let lo = self.bitv.storage[i + k];
+            let hi = if i + k + 1 < len {
+                self.bitv.storage[i + k + 1]
+            } else {
+                0
+            };
+            new[i] = if s == 0 {
+                lo
+            } else {
+                lo >> s | hi << (uint::bits - s)
+            };
+        }
+        let mut size = 0;
+        for new.iter().advance |&w| {
+            size += population_count(w);
+        }
+        self.bitv.storage = new;
+        self.size = size;
+    }
+
+    /// Return true if any member falls in `[lo, hi)`, testing whole
+    /// storage words against masks rather than iterating the members
+    pub fn intersects_range(&self, lo: uint, hi: uint) -> bool {
+        assert!(lo <= hi);
+        let hi = uint::min(hi, self.capacity());
+        if lo >= hi {
+            return false;
+        }
+        let first = lo / uint::bits;
+        let last = (hi - 1) / uint::bits;
+        for uint::range(first, last + 1) |w| {
+            let mut mask = !0;
+            if w == first {
+                mask &= !0 << (lo % uint::bits);
+            }
+            if w == last && hi % uint::bits != 0 {
+                mask &= (1 << (hi % uint::bits)) - 1;
+            }
+            if self.bitv.storage[w] & mask != 0 {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    /// The smallest non-negative integer not in the set, found by
+    /// scanning for the first storage word with a zero bit. Grundy
+    /// number computations and ID assignment both want this without a
+    /// per-element probe loop.
+    pub fn mex(&self) -> uint {
+        for self.bitv.storage.iter().enumerate().advance |(i, &w)| {
+            if w != !0 {
+                let mut b = 0;
+                while w >> b & 1 == 1 { b += 1; }
+                return i * uint::bits + b;
+            }
+        }
+        self.capacity()
+    }
+
+    pub fn each(&self, blk: &fn(v: &uint) -> bool) -> bool {
+        for self.bitv.storage.iter().enumerate().advance |(i, &w)| {
+            if !iterate_bits(i * uint::bits, w, |b| blk(&b)) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Visit each word of storage in order, passing the bit offset of the
+    /// first bit of the word along with the word itself
+    pub fn each_word(&self, f: &fn(uint, uint) -> bool) -> bool {
+        self.bitv.storage.iter().enumerate().advance(|(i, &w)| {
+            f(i * uint::bits, w)
+        })
+    }
+
+    /// Create a bit vector set holding the same values as a SmallIntSet
+    pub fn from_small_int_set(set: &SmallIntSet) -> BitvSet {
+        set.to_bitv_set()
+    }
+
+    /// Convert this set to a SmallIntSet holding the same values
+    pub fn to_small_int_set(&self) -> SmallIntSet {
+        SmallIntSet::from_bitv_set(self)
+    }
+
+    /// Serialize the set in the same portable layout as
+    /// `Bitv::write_to`, with the capacity as the recorded length
+    pub fn write_to(&self, writer: @io::Writer) {
+        writer.write(SERIAL_MAGIC);
+        writer.write([SERIAL_VERSION, uint::bits as u8, 1]);
+        write_le(writer, self.capacity() as u64, 8);
+        for self.bitv.storage.iter().advance |&w| {
+            write_le(writer, w as u64, uint::bytes);
+        }
+    }
+
+    /// Decode a set serialized by `write_to` (or a serialized Bitv,
+    /// whose set bits become the members). Returns None if the stream
+    /// is truncated or not in the format.
+    pub fn read_from(reader: @io::Reader) -> Option<BitvSet> {
+        match Bitv::read_from(reader) {
+            Some(bitv) => Some(BitvSet::from_bitv(bitv)),
+            None => None
+        }
+    }
+}
+
+impl cmp::Eq for BitvSet {
+    fn eq(&self, other: &BitvSet) -> bool {
+        if self.size != other.size {
+            return false;
+        }
+        for self.each_common(other) |_, w1, w2| {
+            if w1 != w2 {
+                return false;
+            }
+        }
+        for self.each_outlier(other) |_, _, w| {
+            if w != 0 {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    fn ne(&self, other: &BitvSet) -> bool { !self.eq(other) }
+}
+
+impl Container for BitvSet {
+    fn len(&self) -> uint { self.size }
+    fn is_empty(&self) -> bool { self.size == 0 }
+}
+
+impl Mutable for BitvSet {
+    fn clear(&mut self) {
+        for self.bitv.each_storage |w| { *w = 0; }
+        self.size = 0;
+    }
+}
+
+impl Set<uint> for BitvSet {
+    fn contains(&self, value: &uint) -> bool {
+        *value < self.bitv.storage.len() * uint::bits && self.bitv.get(*value)
+    }
+
+    fn insert(&mut self, value: uint) -> bool {
+        if self.contains(&value) {
+            return false;
+        }
+        let nbits = self.capacity();
+        if value >= nbits {
+            let newsize = uint::max(value, nbits * 2) / uint::bits + 1;
+            assert!(newsize > self.bitv.storage.len());
+            self.bitv.storage.grow(newsize, &0);
+        }
+        self.size += 1;
+        self.bitv.set(value, true);
+        return true;
+    }
+
+    fn remove(&mut self, value: &uint) -> bool {
+        if !self.contains(value) {
+            return false;
+        }
+        self.size -= 1;
+        self.bitv.set(*value, false);
+
+        // Attempt to truncate our storage
+        let mut i = self.bitv.storage.len();
+        while i > 1 && self.bitv.storage[i - 1] == 0 {
+            i -= 1;
+        }
+        self.bitv.storage.truncate(i);
+
+        return true;
+    }
+
+    fn is_disjoint(&self, other: &BitvSet) -> bool {
+        for self.intersection(other) |_| {
+            return false;
+        }
+        return true;
+    }
+
+    fn is_subset(&self, other: &BitvSet) -> bool {
+        for self.each_common(other) |_, w1, w2| {
+            if w1 & w2 != w1 {
+                return false;
+            }
+        }
+        /* If anything is not ours, then everything is not ours so we're
+           definitely a subset in that case. Otherwise if there's any stray
+           ones that 'other' doesn't have, we're not a subset. */
+        for self.each_outlier(other) |mine, _, w| {
+            if !mine {
+                return true;
+            } else if w != 0 {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    fn is_superset(&self, other: &BitvSet) -> bool {
+        other.is_subset(self)
+    }
+
+    fn difference(&self, other: &BitvSet, f: &fn(&uint) -> bool) -> bool {
+        for self.each_common(other) |i, w1, w2| {
+            if !iterate_bits(i, w1 & !w2, |b| f(&b)) {
+                return false;
+            }
+        }
+        /* everything we have that they don't also shows up */
+        self.each_outlier(other, |mine, i, w|
+            !mine || iterate_bits(i, w, |b| f(&b))
+        )
+    }
+
+    fn symmetric_difference(&self, other: &BitvSet,
+                            f: &fn(&uint) -> bool) -> bool {
+        for self.each_common(other) |i, w1, w2| {
+            if !iterate_bits(i, w1 ^ w2, |b| f(&b)) {
+                return false;
+            }
+        }
+        self.each_outlier(other, |_, i, w| iterate_bits(i, w, |b| f(&b)))
+    }
+
+    fn intersection(&self, other: &BitvSet, f: &fn(&uint) -> bool) -> bool {
+        self.each_common(other, |i, w1, w2| iterate_bits(i, w1 & w2, |b| f(&b)))
+    }
+
+    fn union(&self, other: &BitvSet, f: &fn(&uint) -> bool) -> bool {
+        for self.each_common(other) |i, w1, w2| {
+            if !iterate_bits(i, w1 | w2, |b| f(&b)) {
+                return false;
+            }
+        }
+        self.each_outlier(other, |_, i, w| iterate_bits(i, w, |b| f(&b)))
+    }
+}
+
+impl BitvSet {
+    /// Visits each of the words that the two bit vectors (self and other)
+    /// both have in common. The three yielded arguments are (bit location,
+    /// w1, w2) where the bit location is the number of bits offset so far,
+    /// and w1/w2 are the words coming from the two vectors self, other.
+    fn each_common(&self, other: &BitvSet,
+                   f: &fn(uint, uint, uint) -> bool) -> bool {
+        let min = uint::min(self.bitv.storage.len(),
+                            other.bitv.storage.len());
+        self.bitv.storage.slice(0, min).iter().enumerate().advance(|(i, &w)| {
+            f(i * uint::bits, w, other.bitv.storage[i])
+        })
+    }
+
+    /// Visits each word in self or other that extends beyond the other. This
+    /// will only iterate through one of the vectors, and it only iterates
+    /// over the portion that doesn't overlap with the other one.
+    ///
+    /// The yielded arguments are a bool, the bit offset, and a word. The bool
+    /// is true if the word comes from 'self', and false if it comes from
+    /// 'other'.
+    fn each_outlier(&self, other: &BitvSet,
+                    f: &fn(bool, uint, uint) -> bool) -> bool {
+        let len1 = self.bitv.storage.len();
+        let len2 = other.bitv.storage.len();
+        let min = uint::min(len1, len2);
+
+        /* only one of these loops will execute and that's the point */
+        for self.bitv.storage.slice(min, len1).iter().enumerate().advance |(i, &w)| {
+            if !f(true, (i + min) * uint::bits, w) {
+                return false;
+            }
+        }
+        for other.bitv.storage.slice(min, len2).iter().enumerate().advance |(i, &w)| {
+            if !f(false, (i + min) * uint::bits, w) {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+impl<S: serialize::Encoder> serialize::Encodable<S> for Bitv {
+    /// Encodes as a string of '0' and '1' characters, which is both
+    /// compact and readable in interchange formats like JSON
+    fn encode(&self, s: &mut S) {
+        s.emit_str(self.to_str())
+    }
+}
+
+impl<D: serialize::Decoder> serialize::Decodable<D> for Bitv {
+    fn decode(d: &mut D) -> Bitv {
+        let text = d.read_str();
+        from_fn(text.len(), |i| text[i] == '1' as u8)
+    }
+}
+
+impl rand::Rand for Bitv {
+    /// A random vector of `uint::bits` bits; use `Bitv::random` to
+    /// choose the length
+    fn rand<R: rand::Rng>(rng: &mut R) -> Bitv {
+        Bitv::random(uint::bits, rng)
+    }
+}
+
+impl rand::Rand for BitvSet {
+    /// A random set over a word-sized universe at density 1/2; use
+    /// `BitvSet::random` to choose either
+    fn rand<R: rand::Rng>(rng: &mut R) -> BitvSet {
+        BitvSet::random(uint::bits, 0.5, rng)
+    }
+}
+
+/**
+ * An immutable snapshot of a `BitvSet`. It holds nothing but owned
+ * plain data, so it satisfies `Freeze + Send` and can sit behind
+ * `extra::arc::ARC` for many tasks to query one large membership set
+ * concurrently without cloning it per task. `freeze` and `thaw` convert
+ * to and from the mutable form without copying the storage.
+ */
+pub struct FrozenBitvSet {
+    priv size: uint,
+    priv storage: ~[uint]
+}
+
+impl BitvSet {
+    /// Freeze the set into an immutable, task-shareable snapshot
+    pub fn freeze(self) -> FrozenBitvSet {
+        let BitvSet{size, bitv} = self;
+        let BigBitv{storage} = bitv;
+        FrozenBitvSet{size: size, storage: storage}
+    }
+}
+
+impl Container for FrozenBitvSet {
+    /// Return the number of members in the set
+    fn len(&self) -> uint { self.size }
+
+    /// Return true if the set is empty
+    fn is_empty(&self) -> bool { self.size == 0 }
+}
+
+impl FrozenBitvSet {
+    /// Return true if `value` is a member
+    pub fn contains(&self, value: &uint) -> bool {
+        let w = *value / uint::bits;
+        w < self.storage.len() &&
+            self.storage[w] & (1 << (*value % uint::bits)) != 0
+    }
+
+    /// Visit the members in increasing order
+    pub fn each(&self, f: &fn(&uint) -> bool) -> bool {
+        for self.storage.iter().enumerate().advance |(i, &w)| {
+            if !iterate_bits(i * uint::bits, w, |b| f(&b)) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Turn the snapshot back into a mutable set, reusing the storage
+    pub fn thaw(self) -> BitvSet {
+        let FrozenBitvSet{size, storage} = self;
+        BitvSet{size: size, bitv: BigBitv::new(storage)}
+    }
+}
+
+impl ToStr for BitvSet {
+    /// Renders the members in increasing order, `{1, 5, 9}` style
+    fn to_str(&self) -> ~str {
+        let mut s = ~"{";
+        let mut first = true;
+        for self.each |v| {
+            if !first {
+                s.push_str(", ");
+            }
+            first = false;
+            s.push_str(v.to_str());
+        }
+        s.push_char('}');
+        s
+    }
+}
+
+impl FromStr for BitvSet {
+    /// Parses the `{1, 5, 9}` rendering produced by `to_str`, or the
+    /// same list of members without the braces. Returns `None` if any
+    /// member fails to parse as a uint.
+    fn from_str(s: &str) -> Option<BitvSet> {
+        let mut text = s.trim();
+        if text.starts_with("{") {
+            if !text.ends_with("}") {
+                return None;
+            }
+            text = text.slice(1, text.len() - 1).trim();
+        } else if text.ends_with("}") {
+            return None;
+        }
+        let mut set = BitvSet::new();
+        if text.is_empty() {
+            return Some(set);
+        }
+        for text.split_iter(',').advance |piece| {
+            match uint::from_str(piece.trim()) {
+                Some(value) => { set.insert(value); }
+                None => return None
+            }
+        }
+        Some(set)
+    }
+}
+
+impl<S: serialize::Encoder> serialize::Encodable<S> for BitvSet {
+    /// Encodes as a sequence of the member values in increasing order
+    fn encode(&self, s: &mut S) {
+        do s.emit_seq(self.len()) |s| {
+            let mut idx = 0;
+            for self.each |&v| {
+                do s.emit_seq_elt(idx) |s| {
+                    s.emit_uint(v);
+                }
+                idx += 1;
+            }
+        }
+    }
+}
+
+impl<D: serialize::Decoder> serialize::Decodable<D> for BitvSet {
+    fn decode(d: &mut D) -> BitvSet {
+        do d.read_seq |d, len| {
+            let mut set = BitvSet::new();
+            for uint::range(0, len) |i| {
+                set.insert(d.read_seq_elt(i, |d| d.read_uint()));
+            }
+            set
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use extra::test::BenchHarness;
+
+    use bitv::*;
+    use bitv;
+
+    use std::ptr;
+    use std::uint;
+    use std::vec;
+    use std::rand;
+    use std::rand::Rng;
+    use std::rand::RngUtil;
+
+    static BENCH_BITS : uint = 1 << 14;
+
+    #[test]
+    fn test_to_str() {
+        let zerolen = Bitv::new(0u, false);
+        assert_eq!(zerolen.to_str(), ~"");
+
+        let eightbits = Bitv::new(8u, false);
+        assert_eq!(eightbits.to_str(), ~"00000000");
+    }
+
+    #[test]
+    fn test_0_elements() {
         let act = Bitv::new(0u, false);
         let exp = vec::from_elem::<uint>(0u, 0u);
         assert!(act.eq_vec(exp));
     }
 
     #[test]
-    fn test_1_element() {
-        let mut act = Bitv::new(1u, false);
-        assert!(act.eq_vec(~[0u]));
-        act = Bitv::new(1u, true);
-        assert!(act.eq_vec(~[1u]));
+    fn test_1_element() {
+        let mut act = Bitv::new(1u, false);
+        assert!(act.eq_vec(~[0u]));
+        act = Bitv::new(1u, true);
+        assert!(act.eq_vec(~[1u]));
+    }
+
+    #[test]
+    fn test_2_elements() {
+        let mut b = bitv::Bitv::new(2, false);
+        b.set(0, true);
+        b.set(1, false);
+        assert_eq!(b.to_str(), ~"10");
+    }
+
+    #[test]
+    fn test_10_elements() {
+        let mut act;
+        // all 0
+
+        act = Bitv::new(10u, false);
+        assert!((act.eq_vec(~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u])));
+        // all 1
+
+        act = Bitv::new(10u, true);
+        assert!((act.eq_vec(~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u])));
+        // mixed
+
+        act = Bitv::new(10u, false);
+        act.set(0u, true);
+        act.set(1u, true);
+        act.set(2u, true);
+        act.set(3u, true);
+        act.set(4u, true);
+        assert!((act.eq_vec(~[1u, 1u, 1u, 1u, 1u, 0u, 0u, 0u, 0u, 0u])));
+        // mixed
+
+        act = Bitv::new(10u, false);
+        act.set(5u, true);
+        act.set(6u, true);
+        act.set(7u, true);
+        act.set(8u, true);
+        act.set(9u, true);
+        assert!((act.eq_vec(~[0u, 0u, 0u, 0u, 0u, 1u, 1u, 1u, 1u, 1u])));
+        // mixed
+
+        act = Bitv::new(10u, false);
+        act.set(0u, true);
+        act.set(3u, true);
+        act.set(6u, true);
+        act.set(9u, true);
+        assert!((act.eq_vec(~[1u, 0u, 0u, 1u, 0u, 0u, 1u, 0u, 0u, 1u])));
+    }
+
+    #[test]
+    fn test_31_elements() {
+        let mut act;
+        // all 0
+
+        act = Bitv::new(31u, false);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u]));
+        // all 1
+
+        act = Bitv::new(31u, true);
+        assert!(act.eq_vec(
+                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
+                        1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
+                        1u, 1u, 1u, 1u, 1u]));
+        // mixed
+
+        act = Bitv::new(31u, false);
+        act.set(0u, true);
+        act.set(1u, true);
+        act.set(2u, true);
+        act.set(3u, true);
+        act.set(4u, true);
+        act.set(5u, true);
+        act.set(6u, true);
+        act.set(7u, true);
+        assert!(act.eq_vec(
+                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u]));
+        // mixed
+
+        act = Bitv::new(31u, false);
+        act.set(16u, true);
+        act.set(17u, true);
+        act.set(18u, true);
+        act.set(19u, true);
+        act.set(20u, true);
+        act.set(21u, true);
+        act.set(22u, true);
+        act.set(23u, true);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u]));
+        // mixed
+
+        act = Bitv::new(31u, false);
+        act.set(24u, true);
+        act.set(25u, true);
+        act.set(26u, true);
+        act.set(27u, true);
+        act.set(28u, true);
+        act.set(29u, true);
+        act.set(30u, true);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 1u, 1u,
+                        1u, 1u, 1u, 1u, 1u]));
+        // mixed
+
+        act = Bitv::new(31u, false);
+        act.set(3u, true);
+        act.set(17u, true);
+        act.set(30u, true);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 1u]));
+    }
+
+    #[test]
+    fn test_32_elements() {
+        let mut act;
+        // all 0
+
+        act = Bitv::new(32u, false);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u]));
+        // all 1
+
+        act = Bitv::new(32u, true);
+        assert!(act.eq_vec(
+                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
+                        1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
+                        1u, 1u, 1u, 1u, 1u, 1u]));
+        // mixed
+
+        act = Bitv::new(32u, false);
+        act.set(0u, true);
+        act.set(1u, true);
+        act.set(2u, true);
+        act.set(3u, true);
+        act.set(4u, true);
+        act.set(5u, true);
+        act.set(6u, true);
+        act.set(7u, true);
+        assert!(act.eq_vec(
+                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u]));
+        // mixed
+
+        act = Bitv::new(32u, false);
+        act.set(16u, true);
+        act.set(17u, true);
+        act.set(18u, true);
+        act.set(19u, true);
+        act.set(20u, true);
+        act.set(21u, true);
+        act.set(22u, true);
+        act.set(23u, true);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u]));
+        // mixed
+
+        act = Bitv::new(32u, false);
+        act.set(24u, true);
+        act.set(25u, true);
+        act.set(26u, true);
+        act.set(27u, true);
+        act.set(28u, true);
+        act.set(29u, true);
+        act.set(30u, true);
+        act.set(31u, true);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 1u, 1u,
+                        1u, 1u, 1u, 1u, 1u, 1u]));
+        // mixed
+
+        act = Bitv::new(32u, false);
+        act.set(3u, true);
+        act.set(17u, true);
+        act.set(30u, true);
+        act.set(31u, true);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 1u, 1u]));
+    }
+
+    #[test]
+    fn test_33_elements() {
+        let mut act;
+        // all 0
+
+        act = Bitv::new(33u, false);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u]));
+        // all 1
+
+        act = Bitv::new(33u, true);
+        assert!(act.eq_vec(
+                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
+                        1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
+                        1u, 1u, 1u, 1u, 1u, 1u, 1u]));
+        // mixed
+
+        act = Bitv::new(33u, false);
+        act.set(0u, true);
+        act.set(1u, true);
+        act.set(2u, true);
+        act.set(3u, true);
+        act.set(4u, true);
+        act.set(5u, true);
+        act.set(6u, true);
+        act.set(7u, true);
+        assert!(act.eq_vec(
+                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u]));
+        // mixed
+
+        act = Bitv::new(33u, false);
+        act.set(16u, true);
+        act.set(17u, true);
+        act.set(18u, true);
+        act.set(19u, true);
+        act.set(20u, true);
+        act.set(21u, true);
+        act.set(22u, true);
+        act.set(23u, true);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u]));
+        // mixed
+
+        act = Bitv::new(33u, false);
+        act.set(24u, true);
+        act.set(25u, true);
+        act.set(26u, true);
+        act.set(27u, true);
+        act.set(28u, true);
+        act.set(29u, true);
+        act.set(30u, true);
+        act.set(31u, true);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 1u, 1u,
+                        1u, 1u, 1u, 1u, 1u, 1u, 0u]));
+        // mixed
+
+        act = Bitv::new(33u, false);
+        act.set(3u, true);
+        act.set(17u, true);
+        act.set(30u, true);
+        act.set(31u, true);
+        act.set(32u, true);
+        assert!(act.eq_vec(
+                       ~[0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
+                        0u, 0u, 0u, 0u, 1u, 1u, 1u]));
+    }
+
+    #[test]
+    fn test_equal_differing_sizes() {
+        let v0 = Bitv::new(10u, false);
+        let v1 = Bitv::new(11u, false);
+        assert!(!v0.equal(&v1));
+    }
+
+    #[test]
+    fn test_equal_greatly_differing_sizes() {
+        let v0 = Bitv::new(10u, false);
+        let v1 = Bitv::new(110u, false);
+        assert!(!v0.equal(&v1));
+    }
+
+    #[test]
+    fn test_equal_sneaky_small() {
+        let mut a = bitv::Bitv::new(1, false);
+        a.set(0, true);
+
+        let mut b = bitv::Bitv::new(1, true);
+        b.set(0, true);
+
+        assert!(a.equal(&b));
+    }
+
+    #[test]
+    fn test_equal_sneaky_big() {
+        let mut a = bitv::Bitv::new(100, false);
+        for uint::range(0, 100) |i| {
+            a.set(i, true);
+        }
+
+        let mut b = bitv::Bitv::new(100, true);
+        for uint::range(0, 100) |i| {
+            b.set(i, true);
+        }
+
+        assert!(a.equal(&b));
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let bitv = from_bytes([0b10110110, 0b00000000, 0b11111111]);
+        let str = ~"10110110" + "00000000" + "11111111";
+        assert_eq!(bitv.to_str(), str);
+    }
+
+    #[test]
+    fn test_to_bytes() {
+        let mut bv = Bitv::new(3, true);
+        bv.set(1, false);
+        assert_eq!(bv.to_bytes(), ~[0b10100000]);
+
+        let mut bv = Bitv::new(9, false);
+        bv.set(2, true);
+        bv.set(8, true);
+        assert_eq!(bv.to_bytes(), ~[0b00100000, 0b10000000]);
+    }
+
+    #[test]
+    fn test_from_bools() {
+        assert!(from_bools([true, false, true, true]).to_str() ==
+            ~"1011");
+    }
+
+    #[test]
+    fn test_to_bools() {
+        let bools = ~[false, false, true, false, false, true, true, false];
+        assert_eq!(from_bytes([0b00100110]).to_bools(), bools);
+    }
+
+    #[test]
+    fn test_small_difference() {
+        let mut b1 = Bitv::new(3, false);
+        let mut b2 = Bitv::new(3, false);
+        b1.set(0, true);
+        b1.set(1, true);
+        b2.set(1, true);
+        b2.set(2, true);
+        assert!(b1.difference(&b2));
+        assert!(b1[0]);
+        assert!(!b1[1]);
+        assert!(!b1[2]);
+    }
+
+    #[test]
+    fn test_big_difference() {
+        let mut b1 = Bitv::new(100, false);
+        let mut b2 = Bitv::new(100, false);
+        b1.set(0, true);
+        b1.set(40, true);
+        b2.set(40, true);
+        b2.set(80, true);
+        assert!(b1.difference(&b2));
+        assert!(b1[0]);
+        assert!(!b1[40]);
+        assert!(!b1[80]);
+    }
+
+    #[test]
+    fn test_small_nand_nor_xnor_implies() {
+        let a = from_bools([true, true, false, false]);
+        let b = from_bools([true, false, true, false]);
+
+        let mut v = a.clone();
+        assert!(v.nand(&b));
+        assert!(v.eq_vec(~[0u, 1, 1, 1]));
+
+        let mut v = a.clone();
+        assert!(v.nor(&b));
+        assert!(v.eq_vec(~[0u, 0, 0, 1]));
+
+        let mut v = a.clone();
+        assert!(v.xnor(&b));
+        assert!(v.eq_vec(~[1u, 0, 0, 1]));
+
+        let mut v = a.clone();
+        assert!(v.implies(&b));
+        assert!(v.eq_vec(~[1u, 0, 1, 1]));
+
+        // implication by itself is a tautology, so an all-true vector
+        // does not change again
+        let mut v = Bitv::new(4, true);
+        let w = v.clone();
+        assert!(!v.implies(&w));
+    }
+
+    #[test]
+    fn test_clone_word_multiple_length() {
+        // cloning must not read past the storage when the length is an
+        // exact word multiple
+        let mut v = Bitv::new(2 * uint::bits, false);
+        v.set(3, true);
+        v.set(uint::bits + 7, true);
+        let c = v.clone();
+        assert_eq!(c.len(), 2 * uint::bits);
+        assert!(c.equal(&v));
+        let w = from_words(~[0b101, !0]);
+        assert!(w.clone().equal(&w));
+    }
+
+    #[test]
+    fn test_big_nand_changed_bits() {
+        let mut b1 = Bitv::new(100, false);
+        let mut b2 = Bitv::new(100, false);
+        b1.set(0, true);
+        b1.set(40, true);
+        b2.set(40, true);
+        assert!(b1.nand(&b2));
+        assert!(b1[0]);
+        assert!(!b1[40]);
+        assert!(b1[99]);
+        // NAND with anything once all-true is a no-op against zeroes
+        assert!(b1.nand(&b2));
+        assert!(b1.is_true());
+        assert!(!b1.nand(&Bitv::new(100, false)));
+    }
+
+    #[test]
+    fn test_big_xnor() {
+        let mut b1 = Bitv::new(100, false);
+        let mut b2 = Bitv::new(100, false);
+        b1.set(3, true);
+        b2.set(3, true);
+        b2.set(50, true);
+        assert!(b1.xnor(&b2));
+        assert!(b1[3]);
+        assert!(!b1[50]);
+        assert!(b1[0]);
+        assert!(b1[99]);
+    }
+
+    #[test]
+    fn test_small_clear() {
+        let mut b = Bitv::new(14, true);
+        b.clear();
+        for b.ones |i| {
+            fail!("found 1 at %?", i);
+        }
+    }
+
+    #[test]
+    fn test_big_clear() {
+        let mut b = Bitv::new(140, true);
+        b.clear();
+        for b.ones |i| {
+            fail!("found 1 at %?", i);
+        }
+    }
+
+    #[test]
+    fn test_bitv_set_basic() {
+        let mut b = BitvSet::new();
+        assert!(b.insert(3));
+        assert!(!b.insert(3));
+        assert!(b.contains(&3));
+        assert!(b.insert(400));
+        assert!(!b.insert(400));
+        assert!(b.contains(&400));
+        assert_eq!(b.len(), 2);
+    }
+
+    #[test]
+    fn test_bitv_set_intersection() {
+        let mut a = BitvSet::new();
+        let mut b = BitvSet::new();
+
+        assert!(a.insert(11));
+        assert!(a.insert(1));
+        assert!(a.insert(3));
+        assert!(a.insert(77));
+        assert!(a.insert(103));
+        assert!(a.insert(5));
+
+        assert!(b.insert(2));
+        assert!(b.insert(11));
+        assert!(b.insert(77));
+        assert!(b.insert(5));
+        assert!(b.insert(3));
+
+        let mut i = 0;
+        let expected = [3, 5, 11, 77];
+        for a.intersection(&b) |x| {
+            assert_eq!(*x, expected[i]);
+            i += 1
+        }
+        assert_eq!(i, expected.len());
+    }
+
+    #[test]
+    fn test_bitv_set_difference() {
+        let mut a = BitvSet::new();
+        let mut b = BitvSet::new();
+
+        assert!(a.insert(1));
+        assert!(a.insert(3));
+        assert!(a.insert(5));
+        assert!(a.insert(200));
+        assert!(a.insert(500));
+
+        assert!(b.insert(3));
+        assert!(b.insert(200));
+
+        let mut i = 0;
+        let expected = [1, 5, 500];
+        for a.difference(&b) |x| {
+            assert_eq!(*x, expected[i]);
+            i += 1
+        }
+        assert_eq!(i, expected.len());
+    }
+
+    #[test]
+    fn test_bitv_set_symmetric_difference() {
+        let mut a = BitvSet::new();
+        let mut b = BitvSet::new();
+
+        assert!(a.insert(1));
+        assert!(a.insert(3));
+        assert!(a.insert(5));
+        assert!(a.insert(9));
+        assert!(a.insert(11));
+
+        assert!(b.insert(3));
+        assert!(b.insert(9));
+        assert!(b.insert(14));
+        assert!(b.insert(220));
+
+        let mut i = 0;
+        let expected = [1, 5, 11, 14, 220];
+        for a.symmetric_difference(&b) |x| {
+            assert_eq!(*x, expected[i]);
+            i += 1
+        }
+        assert_eq!(i, expected.len());
+    }
+
+    #[test]
+    fn test_bitv_set_union() {
+        let mut a = BitvSet::new();
+        let mut b = BitvSet::new();
+        assert!(a.insert(1));
+        assert!(a.insert(3));
+        assert!(a.insert(5));
+        assert!(a.insert(9));
+        assert!(a.insert(11));
+        assert!(a.insert(160));
+        assert!(a.insert(19));
+        assert!(a.insert(24));
+
+        assert!(b.insert(1));
+        assert!(b.insert(5));
+        assert!(b.insert(9));
+        assert!(b.insert(13));
+        assert!(b.insert(19));
+
+        let mut i = 0;
+        let expected = [1, 3, 5, 9, 11, 13, 19, 24, 160];
+        for a.union(&b) |x| {
+            assert_eq!(*x, expected[i]);
+            i += 1
+        }
+        assert_eq!(i, expected.len());
     }
 
     #[test]
-    fn test_2_elements() {
-        let mut b = bitv::Bitv::new(2, false);
-        b.set(0, true);
-        b.set(1, false);
-        assert_eq!(b.to_str(), ~"10");
+    fn test_bitv_set_mex() {
+        let mut s = BitvSet::new();
+        assert_eq!(s.mex(), 0);
+        s.insert(1);
+        s.insert(2);
+        assert_eq!(s.mex(), 0);
+        s.insert(0);
+        assert_eq!(s.mex(), 3);
+        // a fully packed prefix pushes the answer across words
+        for uint::range(0, 2 * uint::bits) |i| {
+            s.insert(i);
+        }
+        assert_eq!(s.mex(), 2 * uint::bits);
+        s.remove(&uint::bits);
+        assert_eq!(s.mex(), uint::bits);
+        // a set whose storage is entirely ones answers its capacity
+        s.insert(uint::bits);
+        let mut t = BitvSet::from_bitv(Bitv::new(s.capacity(), true));
+        assert_eq!(t.mex(), t.capacity());
+        t.remove(&0);
+        assert_eq!(t.mex(), 0);
     }
 
     #[test]
-    fn test_10_elements() {
-        let mut act;
-        // all 0
+    fn test_bitv_set_size_after_word_ops() {
+        fn count(s: &BitvSet) -> uint {
+            let mut n = 0;
+            for s.each |_| { n += 1; }
+            n
+        }
+        let mut a = BitvSet::new();
+        let mut b = BitvSet::new();
+        for uint::range(0, 300) |i| {
+            if i % 2 == 0 { a.insert(i); }
+            if i % 3 == 0 { b.insert(i); }
+        }
+        a.union_with(&b);
+        assert_eq!(a.len(), count(&a));
+        a.intersect_with(&b);
+        assert_eq!(a.len(), count(&a));
+        a.symmetric_difference_with(&b);
+        assert_eq!(a.len(), count(&a));
+        assert!(a.is_empty());
+        a.difference_with(&b);
+        assert_eq!(a.len(), count(&a));
+    }
 
-        act = Bitv::new(10u, false);
-        assert!((act.eq_vec(~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u])));
-        // all 1
+    #[test]
+    fn test_bitv_set_ops_into() {
+        let mut a = BitvSet::new();
+        let mut b = BitvSet::new();
+        for (~[1u, 5, 9, 80, 200]).iter().advance |&v| {
+            a.insert(v);
+        }
+        for (~[5u, 9, 13, 200, 300]).iter().advance |&v| {
+            b.insert(v);
+        }
 
-        act = Bitv::new(10u, true);
-        assert!((act.eq_vec(~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u])));
-        // mixed
+        let mut dest = BitvSet::new();
+        a.union_into(&b, &mut dest);
+        assert_eq!(dest.to_str(), ~"{1, 5, 9, 13, 80, 200, 300}");
+        assert_eq!(dest.len(), 7);
+
+        // the destination is overwritten, not merged into, and keeps
+        // its storage for smaller results
+        let cap = dest.capacity();
+        a.intersect_into(&b, &mut dest);
+        assert_eq!(dest.to_str(), ~"{5, 9, 200}");
+        assert_eq!(dest.len(), 3);
+        assert_eq!(dest.capacity(), cap);
+
+        a.difference_into(&b, &mut dest);
+        assert_eq!(dest.to_str(), ~"{1, 80}");
+
+        a.symmetric_difference_into(&b, &mut dest);
+        assert_eq!(dest.to_str(), ~"{1, 13, 80, 300}");
+        assert_eq!(dest.len(), 4);
+
+        // operands of different capacities, in both orders
+        b.difference_into(&a, &mut dest);
+        assert_eq!(dest.to_str(), ~"{13, 300}");
+    }
 
-        act = Bitv::new(10u, false);
-        act.set(0u, true);
-        act.set(1u, true);
-        act.set(2u, true);
-        act.set(3u, true);
-        act.set(4u, true);
-        assert!((act.eq_vec(~[1u, 1u, 1u, 1u, 1u, 0u, 0u, 0u, 0u, 0u])));
-        // mixed
+    #[test]
+    fn test_memory_usage() {
+        let small = Bitv::new(uint::bits, false);
+        let big = Bitv::new(1000, false);
+        assert!(small.memory_usage() < big.memory_usage());
+
+        let mut s = BitvSet::new();
+        let before = s.memory_usage();
+        assert!(s.insert(10000));
+        assert!(s.memory_usage() > before);
+    }
+
+    #[test]
+    fn test_bitv_remove() {
+        let mut a = BitvSet::new();
+
+        assert!(a.insert(1));
+        assert!(a.remove(&1));
+
+        assert!(a.insert(100));
+        assert!(a.remove(&100));
+
+        assert!(a.insert(1000));
+        assert!(a.remove(&1000));
+        assert_eq!(a.capacity(), uint::bits);
+    }
+
+    #[test]
+    fn test_json_bitv() {
+        use json;
+        use serialize::{Encodable, Decodable};
+        use std::io;
+
+        let v = from_bytes([0b10100000]);
+        let text = do io::with_str_writer |wr| {
+            let mut encoder = json::Encoder(wr);
+            v.encode(&mut encoder);
+        };
+        assert_eq!(text, ~"\"10100000\"");
+        let mut decoder = json::Decoder(json::from_str(text).unwrap());
+        let decoded: Bitv = Decodable::decode(&mut decoder);
+        assert!(decoded.equal(&v));
+    }
+
+    #[test]
+    fn test_json_bitv_set() {
+        use json;
+        use serialize::{Encodable, Decodable};
+        use std::io;
+
+        let mut s = BitvSet::new();
+        s.insert(1);
+        s.insert(2);
+        s.insert(60);
+        let text = do io::with_str_writer |wr| {
+            let mut encoder = json::Encoder(wr);
+            s.encode(&mut encoder);
+        };
+        assert_eq!(text, ~"[1,2,60]");
+        let mut decoder = json::Decoder(json::from_str(text).unwrap());
+        let decoded: BitvSet = Decodable::decode(&mut decoder);
+        assert!(decoded == s);
+    }
+
+    #[test]
+    fn test_population_count() {
+        assert_eq!(population_count(0), 0);
+        assert_eq!(population_count(1), 1);
+        assert_eq!(population_count(0b1011_0110), 5);
+        assert_eq!(population_count(!0), uint::bits);
+        assert_eq!(population_count(!0 / 3), uint::bits / 2);
+        // agree with one-at-a-time counting on assorted words
+        let mut r = rng();
+        for 100.times {
+            let w = (r.next() as uint) ^
+                    ((r.next() as uint) << (uint::bits / 2));
+            let mut expected = 0;
+            for uint::range(0, uint::bits) |i| {
+                if w & (1 << i) != 0 {
+                    expected += 1;
+                }
+            }
+            assert_eq!(population_count(w), expected);
+        }
+    }
+
+    #[test]
+    fn test_set_from_iter() {
+        let mut v = Bitv::new(100, false);
+        v.set_from_iter(&mut (~[3u, 5, 64, 99]).consume_iter(), true);
+        assert!(v.get(3));
+        assert!(v.get(5));
+        assert!(v.get(64));
+        assert!(v.get(99));
+        assert!(!v.get(4));
+        v.set_from_iter(&mut (~[5u, 99]).consume_iter(), false);
+        assert!(!v.get(5));
+        assert!(!v.get(99));
+        assert!(v.get(3));
+        assert!(v.get(64));
+        // unsorted and duplicated indices are fine
+        let mut small = Bitv::new(8, false);
+        small.set_from_iter(&mut (~[7u, 0, 7, 3]).consume_iter(), true);
+        assert!(small.eq_vec(~[1u, 0, 0, 1, 0, 0, 0, 1]));
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_set_from_iter_out_of_range() {
+        let mut v = Bitv::new(10, false);
+        v.set_from_iter(&mut (~[10u]).consume_iter(), true);
+    }
+
+    #[test]
+    fn test_chunks_even_split() {
+        let v = from_bytes([0b10110100, 0b01101001]);
+        let mut seen = ~[];
+        for v.chunks(4) |chunk| {
+            assert_eq!(chunk.nbits, 4);
+            seen.push(chunk.to_str());
+        }
+        assert_eq!(seen, ~[~"1011", ~"0100", ~"0110", ~"1001"]);
+    }
+
+    #[test]
+    fn test_chunks_ragged_tail() {
+        let v = from_bools([true, false, true, true, false]);
+        let mut seen = ~[];
+        for v.chunks(3) |chunk| {
+            seen.push(chunk.to_str());
+        }
+        assert_eq!(seen, ~[~"101", ~"10"]);
+    }
+
+    #[test]
+    fn test_chunks_spanning_words() {
+        // chunks wider than a word, starting unaligned within it
+        let mut v = Bitv::new(200, false);
+        v.set(0, true);
+        v.set(70, true);
+        v.set(140, true);
+        let mut bases = ~[];
+        let mut base = 0;
+        for v.chunks(70) |chunk| {
+            assert!(chunk.get(0));
+            for chunk.ones |i| {
+                assert_eq!(i, 0);
+            }
+            bases.push(base);
+            base += 70;
+        }
+        assert_eq!(bases, ~[0u, 70, 140]);
+    }
+
+    #[test]
+    fn test_windows_as_uints() {
+        let v = from_bools([true, false, true, true, false]);
+        let mut seen = ~[];
+        for v.windows(3) |w| {
+            seen.push(w);
+        }
+        // bit i of the window is the window's ith bit
+        assert_eq!(seen, ~[0b101u, 0b110, 0b011]);
+    }
+
+    #[test]
+    fn test_windows_counts_pattern() {
+        let v = from_bools([true, true, true, false, true, true]);
+        let mut pairs = 0;
+        for v.windows(2) |w| {
+            if w == 0b11 {
+                pairs += 1;
+            }
+        }
+        assert_eq!(pairs, 3);
+    }
+
+    #[test]
+    fn test_windows_too_wide() {
+        let v = Bitv::new(3, true);
+        assert!(v.windows(10, |_| fail!()));
+        assert!(v.windows_bitv(10, |_| fail!()));
+    }
+
+    #[test]
+    fn test_windows_bitv_spanning_words() {
+        let mut v = Bitv::new(150, false);
+        v.set(80, true);
+        let width = 100;
+        let mut pos = 0;
+        for v.windows_bitv(width) |w| {
+            assert_eq!(w.nbits, width);
+            let expected_ones = if pos <= 80 && 80 < pos + width {1} else {0};
+            let mut count = 0;
+            for w.ones |i| {
+                assert_eq!(pos + i, 80);
+                count += 1;
+            }
+            assert_eq!(count, expected_ones);
+            pos += 1;
+        }
+        assert_eq!(pos, 51);
+    }
+
+    #[test]
+    fn test_find_clear_run() {
+        let mut v = Bitv::new(32, false);
+        assert_eq!(v.find_clear_run(32, 0), Some(0));
+        assert_eq!(v.find_clear_run(33, 0), None);
+        v.set(3, true);
+        v.set(10, true);
+        assert_eq!(v.find_clear_run(3, 0), Some(0));
+        assert_eq!(v.find_clear_run(4, 0), Some(4));
+        assert_eq!(v.find_clear_run(4, 5), Some(5));
+        assert_eq!(v.find_clear_run(7, 0), Some(11));
+        assert_eq!(v.find_clear_run(21, 0), Some(11));
+        assert_eq!(v.find_clear_run(22, 0), None);
+    }
+
+    #[test]
+    fn test_find_clear_run_spanning_words() {
+        // a run straddling the word boundary, and one ending exactly
+        // at the end of the vector
+        let mut v = Bitv::new(3 * uint::bits, true);
+        for uint::range(uint::bits - 5, uint::bits + 5) |i| {
+            v.set(i, false);
+        }
+        for uint::range(2 * uint::bits, 3 * uint::bits) |i| {
+            v.set(i, false);
+        }
+        assert_eq!(v.find_clear_run(10, 0), Some(uint::bits - 5));
+        assert_eq!(v.find_clear_run(11, 0), Some(2 * uint::bits));
+        assert_eq!(v.find_clear_run(uint::bits, 0), Some(2 * uint::bits));
+        assert_eq!(v.find_clear_run(uint::bits + 1, 0), None);
+        assert_eq!(v.find_clear_run(5, 3 * uint::bits), None);
+    }
+
+    #[test]
+    fn test_find_clear_run_matches_naive() {
+        let v = from_fn(200, |i| i % 11 < 4);
+        for uint::range(1, 12) |k| {
+            let expected = {
+                let mut found = None;
+                let mut run = 0;
+                for uint::range(0, 200) |i| {
+                    if v[i] { run = 0; } else { run += 1; }
+                    if run == k && found.is_none() {
+                        found = Some(i + 1 - k);
+                    }
+                }
+                found
+            };
+            assert_eq!(v.find_clear_run(k, 0), expected);
+        }
+    }
+
+    #[test]
+    fn test_max_run() {
+        let v = from_bytes([0b11100111, 0b11000000]);
+        assert_eq!(v.max_run(true), (5u, 5u));
+        assert_eq!(v.max_run(false), (10u, 6u));
+
+        // a free extent spanning a word boundary
+        let mut v = Bitv::new(3 * uint::bits, true);
+        v.clear_range(uint::bits - 7, uint::bits + 20);
+        assert_eq!(v.max_run(false), (uint::bits - 7, 27u));
+        assert_eq!(v.max_run(true), (uint::bits + 20, 2 * uint::bits - 20));
+
+        // ties go to the earliest run
+        let v = from_bytes([0b01101100]);
+        assert_eq!(v.max_run(true), (1u, 2u));
+
+        // degenerate vectors
+        let zeros = Bitv::new(130, false);
+        assert_eq!(zeros.max_run(false), (0u, 130u));
+        assert_eq!(zeros.max_run(true), (0u, 0u));
+        assert_eq!(Bitv::new(0, false).max_run(false), (0u, 0u));
+    }
+
+    #[test]
+    fn test_max_run_matches_naive() {
+        let v = from_fn(300, |i| i * i % 23 < 11);
+        for [true, false].iter().advance |&bit| {
+            let expected = {
+                let mut best = (0u, 0u);
+                let mut start = 0;
+                for uint::range(0, 300) |i| {
+                    if v[i] != bit {
+                        start = i + 1;
+                    } else {
+                        let len = i + 1 - start;
+                        let (_, best_len) = best;
+                        if len > best_len { best = (start, len); }
+                    }
+                }
+                best
+            };
+            assert_eq!(v.max_run(bit), expected);
+        }
+    }
+
+    #[test]
+    fn test_reverse_bits_in_byte() {
+        assert_eq!(reverse_bits_in_byte(0b10000000), 0b00000001);
+        assert_eq!(reverse_bits_in_byte(0b11010010), 0b01001011);
+        assert_eq!(reverse_bits_in_byte(0x00), 0x00);
+        assert_eq!(reverse_bits_in_byte(0xff), 0xff);
+    }
+
+    #[test]
+    fn test_from_bytes_lsb0() {
+        let lsb = from_bytes_lsb0([0b11010010, 0b00000001]);
+        let msb = from_bytes([reverse_bits_in_byte(0b11010010),
+                              reverse_bits_in_byte(0b00000001)]);
+        assert!(lsb.equal(&msb));
+        assert!(lsb.get(1));
+        assert!(!lsb.get(0));
+        assert!(lsb.get(8));
+    }
+
+    #[test]
+    fn test_reverse_bit_order_in_bytes() {
+        let mut v = from_bytes([0b11010010, 0b10000000]);
+        v.reverse_bit_order_in_bytes();
+        assert!(v.equal(&from_bytes_lsb0([0b11010010, 0b10000000])));
+        v.reverse_bit_order_in_bytes();
+        assert!(v.equal(&from_bytes([0b11010010, 0b10000000])));
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_reverse_bit_order_needs_whole_bytes() {
+        let mut v = Bitv::new(12, false);
+        v.reverse_bit_order_in_bytes();
+    }
+
+    #[test]
+    fn test_raw_roundtrip_big() {
+        let mut v = Bitv::new(200, false);
+        v.set(0, true);
+        v.set(64, true);
+        v.set(199, true);
+        do v.with_raw |p, words| {
+            assert_eq!(words, uint::div_ceil(200, uint::bits));
+            let copied = unsafe { from_raw_parts(p, words, 200) };
+            assert!(copied.equal(&v));
+        }
+    }
+
+    #[test]
+    fn test_raw_roundtrip_small() {
+        let mut v = Bitv::new(10, false);
+        v.set(3, true);
+        v.set(9, true);
+        do v.with_raw |p, words| {
+            assert_eq!(words, 1);
+            let copied = unsafe { from_raw_parts(p, words, 10) };
+            assert!(copied.equal(&v));
+        }
+    }
+
+    #[test]
+    fn test_from_words_single_word_interoperates() {
+        // a one-word input takes the small representation, so the
+        // result compares and combines with a word-sized Bitv::new
+        let v = from_words(~[0b1010]);
+        assert_eq!(v.nbits, uint::bits);
+        let mut w = Bitv::new(uint::bits, false);
+        w.set(1, true);
+        w.set(3, true);
+        assert!(v.equal(&w));
+        assert!(w.equal(&v));
+        assert!(!w.union(&v));
+        assert!(w.intersect(&v) == false);
+        let empty: ~[uint] = ~[];
+        assert!(from_words(empty).equal(&Bitv::new(0, false)));
+    }
+
+    fn high_bits_zero(v: &Bitv) -> bool {
+        let mut ok = true;
+        do v.with_raw |p, words| {
+            let full = unsafe {
+                from_raw_parts(p, words, words * uint::bits)
+            };
+            for full.ones |i| {
+                if i >= v.nbits {
+                    ok = false;
+                }
+            }
+        }
+        ok
+    }
+
+    #[test]
+    fn test_normalized_high_bits() {
+        // every mutating path that can touch the bits above nbits must
+        // leave them zero, observable through with_raw
+        let mut v = Bitv::new(70, true);
+        assert!(high_bits_zero(&v));
+        v.invert();
+        v.invert();
+        assert!(high_bits_zero(&v));
+        v.set_all();
+        assert!(high_bits_zero(&v));
+        let zeros = Bitv::new(70, false);
+        v.nand(&zeros);
+        assert!(high_bits_zero(&v));
+        v.nor(&zeros);
+        assert!(high_bits_zero(&v));
+        v.xnor(&zeros);
+        assert!(high_bits_zero(&v));
+        v.implies(&zeros);
+        assert!(high_bits_zero(&v));
+        v.fill_with_pattern(!0, 3);
+        assert!(high_bits_zero(&v));
+        let mut r = rng();
+        let v = Bitv::random(70, &mut r);
+        assert!(high_bits_zero(&v));
+
+        // the small representation keeps the same invariant
+        let mut s = Bitv::new(10, true);
+        assert!(high_bits_zero(&s));
+        s.invert();
+        s.nand(&Bitv::new(10, false));
+        assert!(high_bits_zero(&s));
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut v = Bitv::new(0, false);
+        for uint::range(0, 2 * uint::bits + 3) |i| {
+            v.push(i % 3 == 0);
+            assert_eq!(v.nbits, i + 1);
+            assert_eq!(v.get(i), i % 3 == 0);
+        }
+        // promotion kept the earlier bits
+        for uint::range(0, 2 * uint::bits + 3) |i| {
+            assert_eq!(v.get(i), i % 3 == 0);
+        }
+        assert!(high_bits_zero(&v));
+        let mut i = 2 * uint::bits + 3;
+        while i > 0 {
+            i -= 1;
+            assert_eq!(v.pop(), i % 3 == 0);
+            assert_eq!(v.nbits, i);
+            assert!(high_bits_zero(&v));
+        }
+    }
+
+    #[test]
+    fn test_popped_vector_interoperates() {
+        // pushing past one word and popping back leaves word storage;
+        // comparisons and binary ops against one-word vectors must not
+        // care
+        let mut v = Bitv::new(uint::bits, false);
+        v.set(3, true);
+        v.push(true);
+        v.pop();
+        let mut u = Bitv::new(uint::bits, false);
+        u.set(3, true);
+        assert!(v.equal(&u));
+        assert!(u.equal(&v));
+        let mut w = Bitv::new(uint::bits, false);
+        w.set(5, true);
+        assert!(!v.equal(&w));
+        assert!(v.union(&w));
+        assert!(v.get(3) && v.get(5));
+        assert!(u.union(&v));
+        assert!(u.get(5));
+        // popping across a word boundary also shrinks against a
+        // freshly built vector of the same length
+        let mut big = Bitv::new(2 * uint::bits + 1, true);
+        big.pop();
+        assert!(big.equal(&Bitv::new(2 * uint::bits, true)));
+        assert!(!big.intersect(&Bitv::new(2 * uint::bits, true)));
+        assert!(high_bits_zero(&v) && high_bits_zero(&big));
+    }
+
+    #[test]
+    fn test_shl_shr_small() {
+        let mut v = from_bytes([0b10010010]);
+        v.shl_assign(2);
+        assert!(v.eq_vec(~[0, 0, 1, 0, 0, 1, 0, 0]));
+        v.shr_assign(5);
+        assert!(v.eq_vec(~[1, 0, 0, 0, 0, 0, 0, 0]));
+        v.shr_assign(1);
+        assert!(v.is_false());
+    }
+
+    #[test]
+    fn test_shl_shr_matches_per_bit() {
+        let v = from_fn(200, |i| i % 11 < 3);
+        // a shift inside a word, exactly a word, and across words
+        for ([1u, 7, uint::bits, uint::bits + 9, 150, 250]).iter()
+                .advance |&n| {
+            let left = v << n;
+            let right = v >> n;
+            for uint::range(0, 200) |i| {
+                let expect_l = i >= n && v[i - n];
+                assert_eq!(left[i], expect_l);
+                let expect_r = i + n < 200 && v[i + n];
+                assert_eq!(right[i], expect_r);
+            }
+            assert!(high_bits_zero(&left));
+            assert!(high_bits_zero(&right));
+        }
+        // in-place and operator forms agree
+        let mut w = from_fn(200, |i| i % 11 < 3);
+        w.shl_assign(13);
+        assert!(w.equal(&(v << 13u)));
+        w.shr_assign(40);
+        assert!(w.equal(&((v << 13u) >> 40u)));
+    }
+
+    #[test]
+    fn test_append() {
+        // small stays small
+        let mut v = from_bytes([0b10110000]);
+        v.truncate(4);
+        let mut w = from_bytes([0b01100000]);
+        w.truncate(3);
+        v.append(&w);
+        assert!(v.eq_vec(~[1, 0, 1, 1, 0, 1, 1]));
+
+        // appending the empty vector is the identity
+        v.append(&Bitv::new(0, false));
+        assert!(v.eq_vec(~[1, 0, 1, 1, 0, 1, 1]));
+
+        // growth across the small/big boundary and across words
+        let mut v = from_fn(uint::bits - 3, |i| i % 2 == 0);
+        let w = from_fn(uint::bits + 9, |i| i % 3 == 0);
+        v.append(&w);
+        assert_eq!(v.nbits, 2 * uint::bits + 6);
+        for uint::range(0, uint::bits - 3) |i| {
+            assert_eq!(v[i], i % 2 == 0);
+        }
+        for uint::range(0, uint::bits + 9) |i| {
+            assert_eq!(v[uint::bits - 3 + i], i % 3 == 0);
+        }
+        assert!(high_bits_zero(&v));
+    }
+
+    #[test]
+    fn test_first_one_first_zero() {
+        let mut v = Bitv::new(200, false);
+        assert_eq!(v.first_one(), None);
+        assert_eq!(v.first_zero(), Some(0));
+        v.set(130, true);
+        assert_eq!(v.first_one(), Some(130));
+        v.set(7, true);
+        assert_eq!(v.first_one(), Some(7));
+        v.invert();
+        assert_eq!(v.first_zero(), Some(7));
+        v.set_all();
+        assert_eq!(v.first_zero(), None);
+        assert_eq!(v.first_one(), Some(0));
+        // the inverted padding of a partial last word is not a zero
+        let s = Bitv::new(10, true);
+        assert_eq!(s.first_zero(), None);
+        assert_eq!(Bitv::new(0, false).first_one(), None);
+        assert_eq!(Bitv::new(0, false).first_zero(), None);
+    }
+
+    #[test]
+    fn test_next_one_next_zero() {
+        let v = from_fn(200, |i| i % 37 == 0);
+        // walking with next_one visits exactly the ones, in order
+        let mut cursor = 0;
+        let mut seen = ~[];
+        loop {
+            match v.next_one(cursor) {
+                Some(i) => {
+                    seen.push(i);
+                    cursor = i + 1;
+                }
+                None => break
+            }
+        }
+        assert_eq!(seen, ~[0u, 37, 74, 111, 148, 185]);
+        // successor queries from arbitrary points
+        assert_eq!(v.next_one(37), Some(37));
+        assert_eq!(v.next_one(38), Some(74));
+        assert_eq!(v.next_one(186), None);
+        assert_eq!(v.next_zero(0), Some(1));
+        assert_eq!(v.next_zero(37), Some(38));
+        let ones = Bitv::new(200, true);
+        assert_eq!(ones.next_zero(50), None);
+        assert_eq!(ones.next_one(199), Some(199));
+        assert_eq!(ones.next_one(200), None);
+    }
+
+    #[test]
+    fn test_leading_trailing_counts() {
+        let v = from_bytes([0b00011000]);
+        assert_eq!(v.trailing_zeros(), 3);
+        assert_eq!(v.trailing_ones(), 0);
+        assert_eq!(v.leading_zeros(), 3);
+        assert_eq!(v.leading_ones(), 0);
+
+        let mut v = Bitv::new(2 * uint::bits + 10, false);
+        v.set(3, true);
+        v.set(2 * uint::bits + 4, true);
+        assert_eq!(v.trailing_zeros(), 3);
+        assert_eq!(v.leading_zeros(), 5);
+        v.set_range(0, 4);
+        assert_eq!(v.trailing_ones(), 4);
+        v.set_range(2 * uint::bits + 4, v.len());
+        assert_eq!(v.leading_ones(), 6);
+
+        // degenerate vectors
+        let zeros = Bitv::new(150, false);
+        assert_eq!(zeros.trailing_zeros(), 150);
+        assert_eq!(zeros.leading_zeros(), 150);
+        assert_eq!(zeros.trailing_ones(), 0);
+        assert_eq!(zeros.leading_ones(), 0);
+        let ones = Bitv::new(150, true);
+        assert_eq!(ones.trailing_ones(), 150);
+        assert_eq!(ones.leading_ones(), 150);
+        assert_eq!(ones.leading_zeros(), 0);
+        let empty = Bitv::new(0, false);
+        assert_eq!(empty.leading_zeros(), 0);
+        assert_eq!(empty.trailing_ones(), 0);
+    }
+
+    #[test]
+    fn test_ones_iter_zeros_iter() {
+        let v = from_fn(3 * uint::bits + 7, |i| i * i % 19 == 4);
+        let mut expected_ones = ~[];
+        let mut expected_zeros = ~[];
+        for uint::range(0, v.len()) |i| {
+            if v[i] {
+                expected_ones.push(i);
+            } else {
+                expected_zeros.push(i);
+            }
+        }
+        let mut it = v.ones_iter();
+        let got: ~[uint] = it.collect();
+        assert_eq!(got, expected_ones);
+        let mut it = v.zeros_iter();
+        let got: ~[uint] = it.collect();
+        assert_eq!(got, expected_zeros);
+
+        let empty = Bitv::new(0, false);
+        let mut it = empty.ones_iter();
+        assert_eq!(it.next(), None);
+        let mut it = empty.zeros_iter();
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn test_bit_iter() {
+        let v = from_bytes([0b10110100]);
+        let mut it = v.iter();
+        let got: ~[bool] = it.collect();
+        assert_eq!(got, v.to_bools());
+
+        // composes with the standard adaptors
+        let mut indices = ~[];
+        for v.iter().enumerate().advance |(i, b)| {
+            if b { indices.push(i); }
+        }
+        assert_eq!(indices, ~[0u, 2, 3, 5]);
+
+        let empty = Bitv::new(0, false);
+        let mut it = empty.iter();
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn test_ones_zeros_closure_forms() {
+        let v = from_bytes([0b10010010]);
+        let mut got = ~[];
+        for v.ones |i| { got.push(i); }
+        assert_eq!(got, ~[0u, 3, 6]);
+        let mut got = ~[];
+        for v.zeros |i| { got.push(i); }
+        assert_eq!(got, ~[1u, 2, 4, 5, 7]);
+        // early exit propagates
+        let mut seen = 0;
+        for v.zeros |_| {
+            seen += 1;
+            if seen == 2 { break; }
+        }
+        assert_eq!(seen, 2);
+    }
+
+    #[test]
+    fn test_copy_bits() {
+        let src = from_bytes([0b11010110]);
+        let mut dst = Bitv::new(16, true);
+        dst.copy_bits(4, &src, 1, 6);
+        for uint::range(0, 16) |i| {
+            let expected = if 4 <= i && i < 10 {
+                src[i - 4 + 1]
+            } else {
+                true
+            };
+            assert_eq!(dst[i], expected);
+        }
+    }
+
+    #[test]
+    fn test_copy_bits_matches_model() {
+        let len = 3 * uint::bits;
+        let src = from_fn(len, |i| i % 3 == 0);
+        // offsets exercising aligned, unaligned, and boundary cases
+        let cases = [(0u, 0u, len), (7, 0, 100), (0, 7, 100),
+                     (uint::bits, 3, uint::bits + 5),
+                     (uint::bits - 1, uint::bits + 1, uint::bits + 2),
+                     (5, 60, 0)];
+        for cases.iter().advance |&(dst_off, src_off, n)| {
+            let mut dst = from_fn(len, |i| i % 5 == 0);
+            dst.copy_bits(dst_off, &src, src_off, n);
+            for uint::range(0, len) |i| {
+                let expected = if dst_off <= i && i < dst_off + n {
+                    src[i - dst_off + src_off]
+                } else {
+                    i % 5 == 0
+                };
+                assert_eq!(dst[i], expected);
+            }
+            assert!(high_bits_zero(&dst));
+        }
+    }
+
+    #[test]
+    fn test_range_ops() {
+        let len = 3 * uint::bits + 10;
+        let mut v = Bitv::new(len, false);
+        v.set_range(5, 2 * uint::bits + 7);
+        for uint::range(0, len) |i| {
+            assert_eq!(v.get(i), 5 <= i && i < 2 * uint::bits + 7);
+        }
+        v.clear_range(uint::bits, uint::bits + 3);
+        for uint::range(uint::bits, uint::bits + 3) |i| {
+            assert!(!v.get(i));
+        }
+        assert!(v.get(uint::bits - 1));
+        assert!(v.get(uint::bits + 3));
+        v.flip_range(0, len);
+        for uint::range(0, len) |i| {
+            let was_set = (5 <= i && i < 2 * uint::bits + 7) &&
+                          !(uint::bits <= i && i < uint::bits + 3);
+            assert_eq!(v.get(i), !was_set);
+        }
+        assert!(high_bits_zero(&v));
+        // empty ranges are no-ops, on either representation
+        v.set_range(7, 7);
+        let mut s = Bitv::new(10, false);
+        s.set_range(2, 9);
+        s.flip_range(0, 10);
+        s.clear_range(0, 2);
+        assert!(s.eq_vec(~[0, 0, 0, 0, 0, 0, 0, 0, 0, 1]));
+    }
 
-        act = Bitv::new(10u, false);
-        act.set(5u, true);
-        act.set(6u, true);
-        act.set(7u, true);
-        act.set(8u, true);
-        act.set(9u, true);
-        assert!((act.eq_vec(~[0u, 0u, 0u, 0u, 0u, 1u, 1u, 1u, 1u, 1u])));
-        // mixed
+    #[test]
+    fn test_flip() {
+        let mut v = Bitv::new(2 * uint::bits, false);
+        assert_eq!(v.flip(3), false);
+        assert!(v.get(3));
+        assert_eq!(v.flip(3), true);
+        assert!(!v.get(3));
+        // in the second storage word
+        assert_eq!(v.flip(uint::bits + 1), false);
+        assert!(v.get(uint::bits + 1));
+        assert_eq!(v.count_ones(), 1);
+        assert!(high_bits_zero(&v));
+        // and on the small representation
+        let mut s = Bitv::new(8, true);
+        assert_eq!(s.flip(7), true);
+        assert!(!s.get(7));
+        assert!(high_bits_zero(&s));
+    }
 
-        act = Bitv::new(10u, false);
-        act.set(0u, true);
-        act.set(3u, true);
-        act.set(6u, true);
-        act.set(9u, true);
-        assert!((act.eq_vec(~[1u, 0u, 0u, 1u, 0u, 0u, 1u, 0u, 0u, 1u])));
+    #[test]
+    fn test_swap_bits() {
+        let mut v = from_bytes([0b10010010]);
+        v.swap(0, 1);
+        assert!(v.eq_vec(~[0, 1, 0, 1, 0, 0, 1, 0]));
+        // swapping equal bits or a bit with itself changes nothing
+        v.swap(2, 4);
+        v.swap(3, 3);
+        assert!(v.eq_vec(~[0, 1, 0, 1, 0, 0, 1, 0]));
+        // across a word boundary
+        let mut v = Bitv::new(2 * uint::bits, false);
+        v.set(3, true);
+        v.swap(3, uint::bits + 5);
+        assert!(!v.get(3));
+        assert!(v.get(uint::bits + 5));
+        assert_eq!(v.count_ones(), 1);
     }
 
     #[test]
-    fn test_31_elements() {
-        let mut act;
-        // all 0
+    fn test_split_off() {
+        // word-aligned split reuses whole words
+        let mut v = from_fn(3 * uint::bits, |i| i % 3 == 0);
+        let tail = v.split_off(uint::bits);
+        assert_eq!(v.nbits, uint::bits);
+        assert_eq!(tail.nbits, 2 * uint::bits);
+        for uint::range(0, uint::bits) |i| {
+            assert_eq!(v[i], i % 3 == 0);
+        }
+        for uint::range(0, 2 * uint::bits) |i| {
+            assert_eq!(tail[i], (i + uint::bits) % 3 == 0);
+        }
 
-        act = Bitv::new(31u, false);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u]));
-        // all 1
+        // unaligned split shifts
+        let mut v = from_fn(200, |i| i % 3 == 0);
+        let tail = v.split_off(77);
+        assert_eq!(v.nbits, 77);
+        assert_eq!(tail.nbits, 123);
+        for uint::range(0, 123) |i| {
+            assert_eq!(tail[i], (i + 77) % 3 == 0);
+        }
+        assert!(high_bits_zero(&v));
+        assert!(high_bits_zero(&tail));
+
+        // degenerate splits
+        let mut v = from_bytes([0b10100000]);
+        let tail = v.split_off(8);
+        assert_eq!(tail.nbits, 0);
+        assert_eq!(v.nbits, 8);
+        let tail = v.split_off(0);
+        assert_eq!(v.nbits, 0);
+        assert!(tail.eq_vec(~[1, 0, 1, 0, 0, 0, 0, 0]));
+    }
 
-        act = Bitv::new(31u, true);
-        assert!(act.eq_vec(
-                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
-                        1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
-                        1u, 1u, 1u, 1u, 1u]));
-        // mixed
+    #[test]
+    fn test_insert_at_remove_at() {
+        let mut v = from_bytes([0b10110100]);
+        v.insert_at(2, true);
+        assert!(v.eq_vec(~[1, 0, 1, 1, 1, 0, 1, 0, 0]));
+        assert_eq!(v.remove_at(2), true);
+        assert!(v.eq_vec(~[1, 0, 1, 1, 0, 1, 0, 0]));
+        // insert at the very end behaves like push
+        v.insert_at(8, true);
+        assert!(v.eq_vec(~[1, 0, 1, 1, 0, 1, 0, 0, 1]));
+        assert_eq!(v.remove_at(0), true);
+        assert!(v.eq_vec(~[0, 1, 1, 0, 1, 0, 0, 1]));
+    }
 
-        act = Bitv::new(31u, false);
-        act.set(0u, true);
-        act.set(1u, true);
-        act.set(2u, true);
-        act.set(3u, true);
-        act.set(4u, true);
-        act.set(5u, true);
-        act.set(6u, true);
-        act.set(7u, true);
-        assert!(act.eq_vec(
-                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u]));
-        // mixed
+    #[test]
+    fn test_insert_at_remove_at_matches_model() {
+        let len = 2 * uint::bits + 7;
+        let mut v = from_fn(len, |i| i % 5 == 0);
+        let mut model = vec::from_fn(len, |i| i % 5 == 0);
+        // poke insertions and removals at word boundaries and interior
+        // positions, checking the whole vector each time
+        let spots = [0u, 1, uint::bits - 1, uint::bits,
+                     uint::bits + 1, 2 * uint::bits, len - 1];
+        for spots.iter().advance |&i| {
+            v.insert_at(i, true);
+            model.insert(i, true);
+            assert_eq!(v.nbits, model.len());
+            for uint::range(0, model.len()) |j| {
+                assert_eq!(v[j], model[j]);
+            }
+            assert!(high_bits_zero(&v));
+        }
+        for spots.iter().advance |&i| {
+            assert_eq!(v.remove_at(i), model.remove(i));
+            assert_eq!(v.nbits, model.len());
+            for uint::range(0, model.len()) |j| {
+                assert_eq!(v[j], model[j]);
+            }
+            assert!(high_bits_zero(&v));
+        }
+    }
 
-        act = Bitv::new(31u, false);
-        act.set(16u, true);
-        act.set(17u, true);
-        act.set(18u, true);
-        act.set(19u, true);
-        act.set(20u, true);
-        act.set(21u, true);
-        act.set(22u, true);
-        act.set(23u, true);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u]));
-        // mixed
+    #[test]
+    fn test_slice_bits() {
+        let v = from_fn(200, |i| i % 7 == 0);
+        // aligned, unaligned, and cross-word ranges
+        let s = v.slice_bits(0, 200);
+        assert!(s.equal(&v));
+        let s = v.slice_bits(3, 3);
+        assert_eq!(s.nbits, 0);
+        let s = v.slice_bits(60, 140);
+        assert_eq!(s.nbits, 80);
+        for uint::range(0, 80) |i| {
+            assert_eq!(s[i], (i + 60) % 7 == 0);
+        }
+        assert!(high_bits_zero(&s));
+        // a small result out of a big vector
+        let s = v.slice_bits(190, 199);
+        for uint::range(0, 9) |i| {
+            assert_eq!(s[i], (i + 190) % 7 == 0);
+        }
+    }
 
-        act = Bitv::new(31u, false);
-        act.set(24u, true);
-        act.set(25u, true);
-        act.set(26u, true);
-        act.set(27u, true);
-        act.set(28u, true);
-        act.set(29u, true);
-        act.set(30u, true);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 1u, 1u,
-                        1u, 1u, 1u, 1u, 1u]));
-        // mixed
+    #[test]
+    #[should_fail]
+    fn test_slice_bits_out_of_range() {
+        let v = Bitv::new(10, false);
+        v.slice_bits(4, 11);
+    }
 
-        act = Bitv::new(31u, false);
-        act.set(3u, true);
-        act.set(17u, true);
-        act.set(30u, true);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 1u]));
+    #[test]
+    fn test_concat() {
+        let a = from_fn(70, |i| i % 2 == 0);
+        let b = from_fn(50, |i| i % 3 == 0);
+        let joined = concat(&a, &b);
+        assert_eq!(joined.nbits, 120);
+        let expected = from_fn(120, |i| {
+            if i < 70 { i % 2 == 0 } else { (i - 70) % 3 == 0 }
+        });
+        assert!(joined.equal(&expected));
+        // the operands are untouched
+        assert_eq!(a.nbits, 70);
+        assert_eq!(b.nbits, 50);
     }
 
     #[test]
-    fn test_32_elements() {
-        let mut act;
-        // all 0
+    fn test_rotate() {
+        let mut v = from_bytes([0b10010010]);
+        v.rotate_left(3);
+        assert!(v.eq_vec(~[0, 1, 0, 1, 0, 0, 1, 0]));
+        v.rotate_right(3);
+        assert!(v.eq_vec(~[1, 0, 0, 1, 0, 0, 1, 0]));
+        // rotation by the length (or zero) is the identity
+        v.rotate_left(8);
+        assert!(v.eq_vec(~[1, 0, 0, 1, 0, 0, 1, 0]));
+        v.rotate_right(0);
+        assert!(v.eq_vec(~[1, 0, 0, 1, 0, 0, 1, 0]));
+    }
 
-        act = Bitv::new(32u, false);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u]));
-        // all 1
+    #[test]
+    fn test_rotate_matches_per_bit() {
+        // a length that leaves a partial last word
+        let len = 2 * uint::bits + 11;
+        let v = from_fn(len, |i| i % 5 == 0 || i % 7 == 3);
+        for ([1u, 10, uint::bits, len - 2, len + 4]).iter().advance |&n| {
+            let mut l = from_fn(len, |i| i % 5 == 0 || i % 7 == 3);
+            l.rotate_left(n);
+            let mut r = from_fn(len, |i| i % 5 == 0 || i % 7 == 3);
+            r.rotate_right(n);
+            for uint::range(0, len) |i| {
+                assert_eq!(l[i], v[(i + len - n % len) % len]);
+                assert_eq!(r[i], v[(i + n) % len]);
+            }
+            assert!(high_bits_zero(&l));
+            assert!(high_bits_zero(&r));
+            // the two directions invert each other
+            l.rotate_right(n);
+            assert!(l.equal(&v));
+        }
+    }
 
-        act = Bitv::new(32u, true);
-        assert!(act.eq_vec(
-                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
-                        1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
-                        1u, 1u, 1u, 1u, 1u, 1u]));
-        // mixed
+    #[test]
+    fn test_count_ones() {
+        assert_eq!(Bitv::new(0, false).count_ones(), 0);
+        assert_eq!(Bitv::new(75, false).count_ones(), 0);
+        assert_eq!(Bitv::new(75, true).count_ones(), 75);
+        let v = from_fn(300, |i| i % 7 == 0);
+        let mut naive = 0;
+        for v.ones |_| { naive += 1; }
+        assert_eq!(v.count_ones(), naive);
+    }
 
-        act = Bitv::new(32u, false);
-        act.set(0u, true);
-        act.set(1u, true);
-        act.set(2u, true);
-        act.set(3u, true);
-        act.set(4u, true);
-        act.set(5u, true);
-        act.set(6u, true);
-        act.set(7u, true);
-        assert!(act.eq_vec(
-                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u]));
-        // mixed
+    #[test]
+    fn test_count_zeros() {
+        assert_eq!(Bitv::new(75, false).count_zeros(), 75);
+        assert_eq!(Bitv::new(75, true).count_zeros(), 0);
+        let v = from_fn(300, |i| i % 7 == 0);
+        assert_eq!(v.count_zeros(), 300 - v.count_ones());
+    }
 
-        act = Bitv::new(32u, false);
-        act.set(16u, true);
-        act.set(17u, true);
-        act.set(18u, true);
-        act.set(19u, true);
-        act.set(20u, true);
-        act.set(21u, true);
-        act.set(22u, true);
-        act.set(23u, true);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u]));
-        // mixed
+    #[test]
+    fn test_with_capacity_and_reserve() {
+        let mut v = Bitv::with_capacity(10 * uint::bits);
+        assert_eq!(v.nbits, 0);
+        let before = v.memory_usage();
+        for uint::range(0, 10 * uint::bits) |i| {
+            v.push(i % 2 == 0);
+        }
+        // the pre-allocated storage absorbed every push
+        assert_eq!(v.memory_usage(), before);
+        for uint::range(0, 10 * uint::bits) |i| {
+            assert_eq!(v.get(i), i % 2 == 0);
+        }
 
-        act = Bitv::new(32u, false);
-        act.set(24u, true);
-        act.set(25u, true);
-        act.set(26u, true);
-        act.set(27u, true);
-        act.set(28u, true);
-        act.set(29u, true);
-        act.set(30u, true);
-        act.set(31u, true);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 1u, 1u,
-                        1u, 1u, 1u, 1u, 1u, 1u]));
-        // mixed
+        // reserving promotes a small vector but keeps its contents
+        let mut s = Bitv::new(10, true);
+        s.reserve(5 * uint::bits);
+        assert_eq!(s.nbits, 10);
+        assert!(s.is_true());
+        assert!(high_bits_zero(&s));
+        // a request that fits in one word leaves a small vector alone
+        let mut small = Bitv::new(4, false);
+        let usage = small.memory_usage();
+        small.reserve(uint::bits);
+        assert_eq!(small.memory_usage(), usage);
+    }
 
-        act = Bitv::new(32u, false);
-        act.set(3u, true);
-        act.set(17u, true);
-        act.set(30u, true);
-        act.set(31u, true);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 1u, 1u]));
+    #[test]
+    fn test_reserved_vector_interoperates() {
+        // a promoted vector still compares and combines with small
+        // vectors of the same length
+        let mut s = Bitv::new(10, true);
+        s.reserve(5 * uint::bits);
+        let o = Bitv::new(10, true);
+        assert!(s.equal(&o));
+        assert!(o.equal(&s));
+        assert!(!s.union(&o));
+        assert!(s.equal(&o));
+        let mut z = Bitv::new(10, false);
+        assert!(z.union(&s));
+        assert!(z.equal(&o));
+        assert!(high_bits_zero(&s) && high_bits_zero(&z));
     }
 
     #[test]
-    fn test_33_elements() {
-        let mut act;
-        // all 0
+    fn test_truncate() {
+        let mut v = Bitv::new(3 * uint::bits, true);
+        v.truncate(uint::bits + 5);
+        assert_eq!(v.nbits, uint::bits + 5);
+        assert!(v.is_true());
+        assert!(high_bits_zero(&v));
+        // whole words past the new length are dropped from storage
+        do v.with_raw |_, words| {
+            assert_eq!(words, 2);
+        }
+        // truncating to a longer length changes nothing
+        v.truncate(1000);
+        assert_eq!(v.nbits, uint::bits + 5);
+        v.truncate(0);
+        assert_eq!(v.nbits, 0);
+        assert!(high_bits_zero(&v));
+        // a small vector just re-masks
+        let mut s = Bitv::new(10, true);
+        s.truncate(4);
+        assert_eq!(s.nbits, 4);
+        assert!(s.is_true());
+        assert!(high_bits_zero(&s));
+    }
 
-        act = Bitv::new(33u, false);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u]));
-        // all 1
+    #[test]
+    fn test_truncated_vector_interoperates() {
+        // truncating word storage below one word must not cut the
+        // result off from small vectors of the same length
+        let mut v = from_words(~[!0, !0]);
+        v.truncate(10);
+        let ones = Bitv::new(10, true);
+        assert!(v.equal(&ones));
+        assert!(ones.equal(&v));
+        assert!(!v.intersect(&ones));
+        let mut z = Bitv::new(10, false);
+        assert!(z.union(&v));
+        assert!(z.equal(&ones));
+        assert!(high_bits_zero(&v) && high_bits_zero(&z));
+    }
 
-        act = Bitv::new(33u, true);
-        assert!(act.eq_vec(
-                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
-                        1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u,
-                        1u, 1u, 1u, 1u, 1u, 1u, 1u]));
-        // mixed
+    #[test]
+    fn test_push_grows_existing_big() {
+        let mut v = from_words(~[!0u, !0u]);
+        v.push(true);
+        assert_eq!(v.nbits, 2 * uint::bits + 1);
+        assert!(v.is_true());
+        assert!(high_bits_zero(&v));
+    }
 
-        act = Bitv::new(33u, false);
-        act.set(0u, true);
-        act.set(1u, true);
-        act.set(2u, true);
-        act.set(3u, true);
-        act.set(4u, true);
-        act.set(5u, true);
-        act.set(6u, true);
-        act.set(7u, true);
-        assert!(act.eq_vec(
-                       ~[1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u]));
-        // mixed
+    #[test]
+    #[should_fail]
+    fn test_pop_empty() {
+        let mut v = Bitv::new(0, false);
+        v.pop();
+    }
 
-        act = Bitv::new(33u, false);
-        act.set(16u, true);
-        act.set(17u, true);
-        act.set(18u, true);
-        act.set(19u, true);
-        act.set(20u, true);
-        act.set(21u, true);
-        act.set(22u, true);
-        act.set(23u, true);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 1u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u]));
-        // mixed
+    #[test]
+    fn test_from_raw_parts_masks_high_bits() {
+        let words = ~[!0u, !0u];
+        do words.as_imm_buf |p, len| {
+            let v = unsafe { from_raw_parts(p, len, 70) };
+            assert!(v.is_true());
+            let mut count = 0;
+            for v.ones |_| { count += 1; }
+            assert_eq!(count, 70);
+            assert!(high_bits_zero(&v));
+        }
+    }
 
-        act = Bitv::new(33u, false);
-        act.set(24u, true);
-        act.set(25u, true);
-        act.set(26u, true);
-        act.set(27u, true);
-        act.set(28u, true);
-        act.set(29u, true);
-        act.set(30u, true);
-        act.set(31u, true);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 1u, 1u,
-                        1u, 1u, 1u, 1u, 1u, 1u, 0u]));
-        // mixed
+    #[test]
+    fn test_normalize() {
+        // a vector whose words were filled in from outside is put back
+        // in canonical form, keeping the defined bits
+        let mut v = Bitv::new(uint::bits + 6, false);
+        do v.with_raw |p, _| {
+            unsafe {
+                *ptr::mut_offset(p as *mut uint, 1) = !0;
+            }
+        }
+        v.normalize();
+        assert!(high_bits_zero(&v));
+        for uint::range(uint::bits, uint::bits + 6) |i| {
+            assert!(v.get(i));
+        }
+        for uint::range(0, uint::bits) |i| {
+            assert!(!v.get(i));
+        }
+    }
 
-        act = Bitv::new(33u, false);
-        act.set(3u, true);
-        act.set(17u, true);
-        act.set(30u, true);
-        act.set(31u, true);
-        act.set(32u, true);
-        assert!(act.eq_vec(
-                       ~[0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u, 0u,
-                        0u, 0u, 0u, 0u, 1u, 1u, 1u]));
+    #[test]
+    fn test_bitv_view_msb_first() {
+        let bytes = ~[0b10100000u8, 0b11000001];
+        let view = BitvView::new(bytes, 16, MsbFirst);
+        assert!(view.equal(&from_bytes(bytes)));
+        assert!(view.get(0));
+        assert!(!view.get(1));
+        assert!(view.get(15));
+        assert_eq!(view.count_ones(), 5);
+        let copied = view.to_bitv();
+        assert!(copied.equal(&from_bytes(bytes)));
     }
 
     #[test]
-    fn test_equal_differing_sizes() {
-        let v0 = Bitv::new(10u, false);
-        let v1 = Bitv::new(11u, false);
-        assert!(!v0.equal(&v1));
+    fn test_bitv_view_lsb_first() {
+        let bytes = ~[0b00000101u8, 0b10000011];
+        let view = BitvView::new(bytes, 16, LsbFirst);
+        assert!(view.get(0));
+        assert!(!view.get(1));
+        assert!(view.get(2));
+        assert!(view.get(8));
+        assert!(view.get(15));
+        assert_eq!(view.count_ones(), 6);
     }
 
     #[test]
-    fn test_equal_greatly_differing_sizes() {
-        let v0 = Bitv::new(10u, false);
-        let v1 = Bitv::new(110u, false);
-        assert!(!v0.equal(&v1));
+    fn test_bitv_view_partial_byte() {
+        let bytes = ~[0b11111111u8, 0b11111111];
+        let msb = BitvView::new(bytes, 11, MsbFirst);
+        assert_eq!(msb.len(), 11);
+        assert_eq!(msb.count_ones(), 11);
+        let lsb = BitvView::new(bytes, 3, LsbFirst);
+        assert_eq!(lsb.count_ones(), 3);
+        // bits past the view's length do not count
+        let mixed = BitvView::new(~[0b00001111u8], 4, MsbFirst);
+        assert_eq!(mixed.count_ones(), 0);
+        assert!(mixed.equal(&Bitv::new(4, false)));
+        assert!(!mixed.equal(&Bitv::new(5, false)));
     }
 
     #[test]
-    fn test_equal_sneaky_small() {
-        let mut a = bitv::Bitv::new(1, false);
-        a.set(0, true);
+    fn test_bitv_set_shift_up_and_down() {
+        let mut s = BitvSet::new();
+        s.insert(0);
+        s.insert(3);
+        s.insert(63);
+        s.insert(100);
+        s.shift_up(70);
+        assert_eq!(s.to_str(), ~"{70, 73, 133, 170}");
+        assert_eq!(s.len(), 4);
+        s.shift_down(70);
+        assert_eq!(s.to_str(), ~"{0, 3, 63, 100}");
+        // members that would go below zero are dropped
+        s.shift_down(64);
+        assert_eq!(s.to_str(), ~"{36}");
+        assert_eq!(s.len(), 1);
+        s.shift_down(1000);
+        assert!(s.is_empty());
+        // shifting an empty set or by zero is a no-op
+        s.shift_up(10);
+        assert!(s.is_empty());
+        let mut t = BitvSet::new();
+        t.insert(7);
+        t.shift_up(0);
+        assert_eq!(t.to_str(), ~"{7}");
+    }
 
-        let mut b = bitv::Bitv::new(1, true);
-        b.set(0, true);
+    #[test]
+    fn test_bitv_set_intersects_range() {
+        let mut s = BitvSet::new();
+        s.insert(5);
+        s.insert(64);
+        s.insert(130);
+        assert!(s.intersects_range(0, 6));
+        assert!(s.intersects_range(5, 6));
+        assert!(!s.intersects_range(0, 5));
+        assert!(!s.intersects_range(6, 64));
+        assert!(s.intersects_range(6, 65));
+        assert!(s.intersects_range(64, 200));
+        assert!(!s.intersects_range(65, 130));
+        assert!(s.intersects_range(65, 131));
+        // ranges past the capacity and empty ranges are handled
+        assert!(!s.intersects_range(131, 1000000));
+        assert!(!s.intersects_range(7, 7));
+        assert!(!BitvSet::new().intersects_range(0, 100));
+        // exhaustive check against the naive definition
+        for uint::range(0, 140) |lo| {
+            let mut any = false;
+            for s.each |&v| {
+                if lo <= v && v < lo + 10 {
+                    any = true;
+                }
+            }
+            assert_eq!(s.intersects_range(lo, lo + 10), any);
+        }
+    }
 
-        assert!(a.equal(&b));
+    #[test]
+    fn test_bitv_set_proper_subset() {
+        let mut a = BitvSet::new();
+        a.insert(1);
+        a.insert(5);
+        let mut b = BitvSet::new();
+        b.insert(1);
+        b.insert(5);
+        assert!(a.is_subset(&b));
+        assert!(!a.is_proper_subset(&b));
+        assert!(!b.is_proper_superset(&a));
+        b.insert(200);
+        assert!(a.is_proper_subset(&b));
+        assert!(b.is_proper_superset(&a));
+        assert!(!b.is_proper_subset(&a));
+        // a smaller but non-contained set is not a proper subset
+        let mut c = BitvSet::new();
+        c.insert(7);
+        assert!(!c.is_proper_subset(&b));
     }
 
     #[test]
-    fn test_equal_sneaky_big() {
-        let mut a = bitv::Bitv::new(100, false);
-        for uint::range(0, 100) |i| {
-            a.set(i, true);
+    fn test_interleave_pair() {
+        let a = from_bools([true, false, true, true]);
+        let b = from_bools([false, true, true, false]);
+        let code = interleave(&a, &b);
+        assert_eq!(code.nbits, 8);
+        assert!(code.eq_vec(~[1u, 0, 0, 1, 1, 1, 1, 0]));
+        let (a2, b2) = deinterleave(&code);
+        assert_eq!(a2.to_bools(), a.to_bools());
+        assert_eq!(b2.to_bools(), b.to_bools());
+    }
+
+    #[test]
+    fn test_interleave_pair_wide() {
+        // channels spanning several storage words
+        let a = from_fn(150, |i| i % 3 == 0);
+        let b = from_fn(150, |i| i % 5 == 0);
+        let code = interleave(&a, &b);
+        assert_eq!(code.nbits, 300);
+        for uint::range(0, 150) |i| {
+            assert_eq!(code.get(2 * i), a.get(i));
+            assert_eq!(code.get(2 * i + 1), b.get(i));
         }
+        let (a2, b2) = deinterleave(&code);
+        assert_eq!(a2.to_bools(), a.to_bools());
+        assert_eq!(b2.to_bools(), b.to_bools());
+    }
 
-        let mut b = bitv::Bitv::new(100, true);
-        for uint::range(0, 100) |i| {
-            b.set(i, true);
+    #[test]
+    fn test_fill_with_pattern() {
+        let mut v = Bitv::new(10, false);
+        v.fill_with_pattern(0b10, 2);
+        assert!(v.eq_vec(~[0u, 1, 0, 1, 0, 1, 0, 1, 0, 1]));
+
+        // a period that does not divide the word size
+        let mut v = Bitv::new(200, true);
+        v.fill_with_pattern(0b011, 3);
+        let expected = from_fn(200, |i| i % 3 != 2);
+        assert!(v.equal(&expected));
+
+        // a whole-word pattern is just a repeated word
+        let mut v = Bitv::new(3 * uint::bits, false);
+        v.fill_with_pattern(!0, uint::bits);
+        assert!(v.is_true());
+
+        let mut v = Bitv::new(64, false);
+        v.fill_with_pattern(0b1, 1);
+        assert!(v.is_true());
+        v.fill_with_pattern(0, 5);
+        assert!(v.is_false());
+    }
+
+    #[test]
+    fn test_random_bitv() {
+        let mut r = rng();
+        let v = Bitv::random(100, &mut r);
+        assert_eq!(v.nbits, 100);
+        // a Rand draw yields a word-sized vector
+        let w: Bitv = r.gen();
+        assert_eq!(w.nbits, uint::bits);
+        // two draws disagreeing somewhere is all randomness we check for
+        let a = Bitv::random(1000, &mut r);
+        let b = Bitv::random(1000, &mut r);
+        assert!(!a.equal(&b));
+    }
+
+    #[test]
+    fn test_random_bitv_set() {
+        let mut r = rng();
+        let empty = BitvSet::random(500, 0.0, &mut r);
+        assert!(empty.is_empty());
+        let full = BitvSet::random(500, 1.0, &mut r);
+        assert_eq!(full.len(), 500);
+        assert!(full.contains(&499));
+        assert!(!full.contains(&500));
+
+        let s = BitvSet::random(500, 0.25, &mut r);
+        // members respect the universe bound and the size is consistent
+        let mut count = 0;
+        for s.each |&v| {
+            assert!(v < 500);
+            count += 1;
         }
+        assert_eq!(count, s.len());
+        // the density should land in a generous band around a quarter
+        assert!(s.len() > 50 && s.len() < 250);
+    }
 
-        assert!(a.equal(&b));
+    #[test]
+    fn test_frozen_bitv_set() {
+        let mut s = BitvSet::new();
+        s.insert(2);
+        s.insert(80);
+        let frozen = s.freeze();
+        assert_eq!(frozen.len(), 2);
+        assert!(frozen.contains(&2));
+        assert!(frozen.contains(&80));
+        assert!(!frozen.contains(&3));
+        assert!(!frozen.contains(&10000));
+        let mut members = ~[];
+        for frozen.each |&v| {
+            members.push(v);
+        }
+        assert_eq!(members, ~[2u, 80]);
+        // thawing restores a set equal to the original
+        let mut thawed = frozen.thaw();
+        assert_eq!(thawed.to_str(), ~"{2, 80}");
+        assert!(thawed.insert(5));
     }
 
     #[test]
-    fn test_from_bytes() {
-        let bitv = from_bytes([0b10110110, 0b00000000, 0b11111111]);
-        let str = ~"10110110" + "00000000" + "11111111";
-        assert_eq!(bitv.to_str(), str);
+    fn test_frozen_bitv_set_in_arc() {
+        use arc;
+
+        let mut s = BitvSet::new();
+        s.insert(42);
+        // compiles only because FrozenBitvSet is Freeze + Send
+        let shared = arc::ARC(s.freeze());
+        let handle = shared.clone();
+        assert!(shared.get().contains(&42));
+        assert!(handle.get().contains(&42));
+        assert!(!handle.get().contains(&41));
     }
 
     #[test]
-    fn test_to_bytes() {
-        let mut bv = Bitv::new(3, true);
-        bv.set(1, false);
-        assert_eq!(bv.to_bytes(), ~[0b10100000]);
+    fn test_bitv_set_ops_with_bitv() {
+        let mut mask = Bitv::new(80, false);
+        mask.set(5, true);
+        mask.set(64, true);
+
+        let mut s = BitvSet::new();
+        s.insert(3);
+        s.insert(5);
+        s.union_with_bitv(&mask);
+        assert_eq!(s.to_str(), ~"{3, 5, 64}");
+        assert_eq!(s.len(), 3);
+
+        s.intersect_with_bitv(&mask);
+        assert_eq!(s.to_str(), ~"{5, 64}");
+        assert_eq!(s.len(), 2);
+
+        // members past the mask's length are dropped by intersection
+        s.insert(200);
+        s.intersect_with_bitv(&mask);
+        assert!(!s.contains(&200));
+        assert_eq!(s.len(), 2);
+
+        // a small-representation mask works too
+        let mut small = Bitv::new(8, true);
+        let mut t = BitvSet::new();
+        t.intersect_with_bitv(&small);
+        assert!(t.is_empty());
+        small.set(7, false);
+        t.union_with_bitv(&small);
+        assert_eq!(t.to_str(), ~"{0, 1, 2, 3, 4, 5, 6}");
+    }
 
-        let mut bv = Bitv::new(9, false);
-        bv.set(2, true);
-        bv.set(8, true);
-        assert_eq!(bv.to_bytes(), ~[0b00100000, 0b10000000]);
+    #[test]
+    fn test_bitv_set_insert_all_remove_all() {
+        let mut s = BitvSet::new();
+        s.insert(1);
+        s.insert_all(&mut (~[1u, 2, 3, 200, 201]).consume_iter());
+        assert_eq!(s.to_str(), ~"{1, 2, 3, 200, 201}");
+        assert_eq!(s.len(), 5);
+        s.remove_all(&mut (~[2u, 200, 999]).consume_iter());
+        assert_eq!(s.to_str(), ~"{1, 3, 201}");
+        assert_eq!(s.len(), 3);
+        // unsorted input still batches correctly
+        s.insert_all(&mut (~[64u, 0, 65, 1]).consume_iter());
+        assert_eq!(s.to_str(), ~"{0, 1, 3, 64, 65, 201}");
+        s.remove_all(&mut (~[]).consume_iter());
+        assert_eq!(s.len(), 6);
     }
 
     #[test]
-    fn test_from_bools() {
-        assert!(from_bools([true, false, true, true]).to_str() ==
-            ~"1011");
+    fn test_bitv_set_ops_with_iter() {
+        let mut s = BitvSet::new();
+        s.insert(1);
+        s.insert(5);
+        s.union_with_iter(&mut (~[5u, 9, 12]).consume_iter());
+        assert_eq!(s.to_str(), ~"{1, 5, 9, 12}");
+        s.difference_with_iter(&mut (~[9u, 40]).consume_iter());
+        assert_eq!(s.to_str(), ~"{1, 5, 12}");
+        s.intersect_with_sorted_iter(&mut (~[0u, 5, 12, 30]).consume_iter());
+        assert_eq!(s.to_str(), ~"{5, 12}");
     }
 
     #[test]
-    fn test_to_bools() {
-        let bools = ~[false, false, true, false, false, true, true, false];
-        assert_eq!(from_bytes([0b00100110]).to_bools(), bools);
+    fn test_bitv_set_ops_with_tree_and_hash_sets() {
+        use std::hashmap::HashSet;
+        use treemap::TreeSet;
+
+        let mut trees = TreeSet::new();
+        trees.insert(2u);
+        trees.insert(60);
+        let mut hashes = HashSet::new();
+        hashes.insert(2u);
+        hashes.insert(7);
+
+        let mut s = BitvSet::new();
+        s.insert(2);
+        s.insert(3);
+        s.union_with_tree_set(&trees);
+        assert_eq!(s.to_str(), ~"{2, 3, 60}");
+        s.intersect_with_hash_set(&hashes);
+        assert_eq!(s.to_str(), ~"{2}");
+        s.insert(60);
+        s.intersect_with_tree_set(&trees);
+        assert_eq!(s.to_str(), ~"{2, 60}");
+        s.union_with_hash_set(&hashes);
+        assert_eq!(s.to_str(), ~"{2, 7, 60}");
     }
 
     #[test]
-    fn test_small_difference() {
-        let mut b1 = Bitv::new(3, false);
-        let mut b2 = Bitv::new(3, false);
-        b1.set(0, true);
-        b1.set(1, true);
-        b2.set(1, true);
-        b2.set(2, true);
-        assert!(b1.difference(&b2));
-        assert!(b1[0]);
-        assert!(!b1[1]);
-        assert!(!b1[2]);
+    fn test_bitv_set_roundtrip_preserves_length() {
+        let mut v = Bitv::new(20, false);
+        v.set(3, true);
+        v.set(19, true);
+        let set = BitvSet::from_bitv_ref(&v);
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&3));
+        assert!(set.contains(&19));
+        // the original is still usable and the round trip is lossless
+        let back = set.to_bitv(v.nbits);
+        assert!(back.equal(&v));
+        // unwrap pads out to the capacity instead
+        assert!(set.unwrap().nbits >= uint::bits);
     }
 
     #[test]
-    fn test_big_difference() {
-        let mut b1 = Bitv::new(100, false);
-        let mut b2 = Bitv::new(100, false);
-        b1.set(0, true);
-        b1.set(40, true);
-        b2.set(40, true);
-        b2.set(80, true);
-        assert!(b1.difference(&b2));
-        assert!(b1[0]);
-        assert!(!b1[40]);
-        assert!(!b1[80]);
+    fn test_bitv_set_to_bitv_truncates() {
+        let mut set = BitvSet::new();
+        set.insert(2);
+        set.insert(100);
+        let v = set.to_bitv(10);
+        assert_eq!(v.nbits, 10);
+        assert!(v.get(2));
+        assert!(v.eq_vec(~[0u, 0, 1, 0, 0, 0, 0, 0, 0, 0]));
     }
 
     #[test]
-    fn test_small_clear() {
-        let mut b = Bitv::new(14, true);
-        b.clear();
-        for b.ones |i| {
-            fail!("found 1 at %?", i);
-        }
+    fn test_bitv_set_from_str() {
+        let s: BitvSet = FromStr::from_str("{1, 5, 9}").unwrap();
+        assert_eq!(s.to_str(), ~"{1, 5, 9}");
+        let bare: BitvSet = FromStr::from_str("3,4, 400").unwrap();
+        assert!(bare.contains(&400));
+        assert_eq!(bare.len(), 3);
+        let empty: BitvSet = FromStr::from_str("{}").unwrap();
+        assert!(empty.is_empty());
+        let unclosed: Option<BitvSet> = FromStr::from_str("{1, 2");
+        assert!(unclosed.is_none());
+        let junk: Option<BitvSet> = FromStr::from_str("{1, x}");
+        assert!(junk.is_none());
+        let unopened: Option<BitvSet> = FromStr::from_str("1, 2}");
+        assert!(unopened.is_none());
     }
 
     #[test]
-    fn test_big_clear() {
-        let mut b = Bitv::new(140, true);
-        b.clear();
-        for b.ones |i| {
-            fail!("found 1 at %?", i);
-        }
+    fn test_bitv_set_to_str() {
+        let mut s = BitvSet::new();
+        assert_eq!(s.to_str(), ~"{}");
+        s.insert(9);
+        s.insert(1);
+        s.insert(5);
+        assert_eq!(s.to_str(), ~"{1, 5, 9}");
     }
 
     #[test]
-    fn test_bitv_set_basic() {
-        let mut b = BitvSet::new();
-        assert!(b.insert(3));
-        assert!(!b.insert(3));
-        assert!(b.contains(&3));
-        assert!(b.insert(400));
-        assert!(!b.insert(400));
-        assert!(b.contains(&400));
-        assert_eq!(b.len(), 2);
+    fn test_serial_roundtrip() {
+        use io_util::BufReader;
+        use std::io;
+
+        let v = from_fn(130, |i| i % 7 == 0);
+        let bytes = do io::with_bytes_writer |wr| {
+            v.write_to(wr);
+        };
+        let rd = @BufReader::new(bytes) as @io::Reader;
+        let w = Bitv::read_from(rd).unwrap();
+        assert_eq!(w.len(), 130);
+        assert!(w.equal(&v));
     }
 
     #[test]
-    fn test_bitv_set_intersection() {
-        let mut a = BitvSet::new();
-        let mut b = BitvSet::new();
+    fn test_serial_rejects_garbage() {
+        use io_util::BufReader;
+        use std::io;
 
-        assert!(a.insert(11));
-        assert!(a.insert(1));
-        assert!(a.insert(3));
-        assert!(a.insert(77));
-        assert!(a.insert(103));
-        assert!(a.insert(5));
+        let v = Bitv::new(10, true);
+        let mut bytes = do io::with_bytes_writer |wr| {
+            v.write_to(wr);
+        };
+        // truncated stream
+        bytes.pop();
+        let rd = @BufReader::new(copy bytes) as @io::Reader;
+        assert!(Bitv::read_from(rd).is_none());
+        // bad magic
+        bytes[0] = 'X' as u8;
+        let rd = @BufReader::new(bytes) as @io::Reader;
+        assert!(Bitv::read_from(rd).is_none());
+    }
 
-        assert!(b.insert(2));
-        assert!(b.insert(11));
-        assert!(b.insert(77));
-        assert!(b.insert(5));
-        assert!(b.insert(3));
+    #[test]
+    fn test_serial_foreign_word_size() {
+        use io_util::BufReader;
+        use std::io;
+
+        // a 12-bit vector of ones written with 16-bit words on a
+        // big-endian build: magic, version 1, word size 16, endian
+        // flag 0, nbits as a big-endian u64, then one word
+        let bytes = ~['B' as u8, 'I' as u8, 'T' as u8, 'V' as u8,
+                      1, 16, 0,
+                      0, 0, 0, 0, 0, 0, 0, 12,
+                      0x0f, 0xff];
+        let rd = @BufReader::new(bytes) as @io::Reader;
+        let v = Bitv::read_from(rd).unwrap();
+        assert_eq!(v.len(), 12);
+        assert!(v.is_true());
+    }
 
-        let mut i = 0;
-        let expected = [3, 5, 11, 77];
-        for a.intersection(&b) |x| {
-            assert_eq!(*x, expected[i]);
-            i += 1
-        }
-        assert_eq!(i, expected.len());
+    #[test]
+    fn test_from_bytes_into() {
+        let mut v = Bitv::new(0, false);
+        // small, big, then back to small, reusing the same vector
+        let small = ~[0b10110110u8];
+        let big = ~[0xffu8, 0, 0x55, 0xaa, 0x0f, 0xf0, 1, 2, 3, 4];
+        let other = ~[0b01u8, 0b11];
+        v.from_bytes_into(small);
+        assert!(v.equal(&from_bytes(small)));
+        v.from_bytes_into(big);
+        assert!(v.equal(&from_bytes(big)));
+        v.from_bytes_into(other);
+        assert!(v.equal(&from_bytes(other)));
+        // refilling clears bits the new contents leave unset
+        v.from_bytes_into([0xff]);
+        v.from_bytes_into([0x00]);
+        assert!(v.is_false());
     }
 
     #[test]
-    fn test_bitv_set_difference() {
-        let mut a = BitvSet::new();
-        let mut b = BitvSet::new();
+    fn test_read_from_into() {
+        use io_util::BufReader;
+        use std::io;
+
+        let mut v = Bitv::new(300, true);
+        let src = from_fn(130, |i| i % 7 == 0);
+        let bytes = do io::with_bytes_writer |wr| {
+            src.write_to(wr);
+        };
+        let rd = @BufReader::new(copy bytes) as @io::Reader;
+        assert!(v.read_from_into(rd));
+        assert_eq!(v.len(), 130);
+        assert!(v.equal(&src));
+
+        // a truncated stream reports failure but leaves the vector
+        // usable for the next attempt
+        let rd = @BufReader::new(bytes.slice(0, bytes.len() - 1).
+                                 to_owned()) as @io::Reader;
+        assert!(!v.read_from_into(rd));
+        let rd = @BufReader::new(bytes) as @io::Reader;
+        assert!(v.read_from_into(rd));
+        assert!(v.equal(&src));
+    }
 
-        assert!(a.insert(1));
-        assert!(a.insert(3));
-        assert!(a.insert(5));
-        assert!(a.insert(200));
-        assert!(a.insert(500));
+    #[test]
+    fn test_serial_bitv_set() {
+        use io_util::BufReader;
+        use std::io;
+
+        let mut s = BitvSet::new();
+        s.insert(1);
+        s.insert(70);
+        s.insert(128);
+        let bytes = do io::with_bytes_writer |wr| {
+            s.write_to(wr);
+        };
+        let rd = @BufReader::new(bytes) as @io::Reader;
+        let t = BitvSet::read_from(rd).unwrap();
+        assert!(s == t);
+    }
 
-        assert!(b.insert(3));
-        assert!(b.insert(200));
+    #[test]
+    fn test_biguint_roundtrip() {
+        use bigint::BigUint;
+
+        let big = (BigUint::from_uint(1) << 100u) +
+                  BigUint::from_uint(0b1011);
+        let bitv = Bitv::from_biguint(&big, 101);
+        assert!(bitv[0] && bitv[1] && !bitv[2] && bitv[3]);
+        assert!(bitv[100]);
+        assert_eq!(bitv.to_biguint(), big);
+    }
 
-        let mut i = 0;
-        let expected = [1, 5, 500];
-        for a.difference(&b) |x| {
-            assert_eq!(*x, expected[i]);
-            i += 1
-        }
-        assert_eq!(i, expected.len());
+    #[test]
+    fn test_biguint_truncation_and_zero() {
+        use bigint::BigUint;
+        use std::num::Zero;
+
+        let big = (BigUint::from_uint(1) << 100u) +
+                  BigUint::from_uint(0b1011);
+        let low = Bitv::from_biguint(&big, 8);
+        assert_eq!(low.to_biguint(), BigUint::from_uint(0b1011));
+        assert!(Bitv::new(500, false).to_biguint().is_zero());
+        assert!(Bitv::from_biguint(&BigUint::from_uint(0), 64).is_false());
     }
 
     #[test]
-    fn test_bitv_set_symmetric_difference() {
-        let mut a = BitvSet::new();
-        let mut b = BitvSet::new();
+    fn test_bitv_container() {
+        let v = Bitv::new(75, true);
+        assert_eq!(v.len(), 75);
+        assert!(!v.is_empty());
+        assert!(Bitv::new(0, false).is_empty());
+    }
 
-        assert!(a.insert(1));
+    #[test]
+    fn test_bitv_mutable_clear() {
+        let mut v = Bitv::new(200, true);
+        v.clear();
+        assert!(v.is_false());
+        assert_eq!(v.len(), 200);
+    }
+
+    #[test]
+    fn test_bitv_as_set() {
+        let mut a = Bitv::new(100, false);
         assert!(a.insert(3));
-        assert!(a.insert(5));
-        assert!(a.insert(9));
-        assert!(a.insert(11));
+        assert!(!a.insert(3));
+        assert!(a.insert(90));
+        assert!(a.contains(&3));
+        assert!(!a.contains(&4));
+        // positions past the length are never present
+        assert!(!a.contains(&1000));
+        assert!(a.remove(&90));
+        assert!(!a.remove(&90));
+        assert!(!a.remove(&1000));
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_bitv_set_insert_out_of_range() {
+        let mut a = Bitv::new(10, false);
+        a.insert(10);
+    }
 
+    #[test]
+    fn test_bitv_set_relations() {
+        // trailing garbage words must not affect the word-wise ops, so
+        // build from all-true vectors and clear back
+        let mut a = Bitv::new(70, true);
+        let mut b = Bitv::new(140, true);
+        b.invert();
+        assert!(!a.is_subset(&b));
+        assert!(!a.is_disjoint(&a));
+        assert!(a.is_disjoint(&b));
         assert!(b.insert(3));
-        assert!(b.insert(9));
-        assert!(b.insert(14));
-        assert!(b.insert(220));
+        assert!(!a.is_disjoint(&b));
+        assert!(b.is_subset(&a));
+        assert!(a.is_superset(&b));
+        assert!(a.remove(&3));
+        assert!(a.is_disjoint(&b));
+    }
 
-        let mut i = 0;
-        let expected = [1, 5, 11, 14, 220];
-        for a.symmetric_difference(&b) |x| {
-            assert_eq!(*x, expected[i]);
-            i += 1
+    // the mutating Bitv methods shadow the same-named Set visitors on
+    // the concrete type, so generic code is the way to reach them
+    fn visit_union<S: Set<uint>>(a: &S, b: &S,
+                                 f: &fn(&uint) -> bool) -> bool {
+        a.union(b, f)
+    }
+
+    fn visit_difference<S: Set<uint>>(a: &S, b: &S,
+                                      f: &fn(&uint) -> bool) -> bool {
+        a.difference(b, f)
+    }
+
+    #[test]
+    fn test_bitv_set_visitors() {
+        let mut a = Bitv::new(10, false);
+        let mut b = Bitv::new(200, false);
+        a.insert(1);
+        a.insert(5);
+        b.insert(5);
+        b.insert(150);
+
+        let mut diff = ~[];
+        for visit_difference(&a, &b) |&v| { diff.push(v); }
+        assert_eq!(diff, ~[1u]);
+
+        let mut sym = ~[];
+        for a.symmetric_difference(&b) |&v| { sym.push(v); }
+        assert_eq!(sym, ~[1u, 150]);
+
+        let mut inter = ~[];
+        for a.intersection(&b) |&v| { inter.push(v); }
+        assert_eq!(inter, ~[5u]);
+
+        let mut un = ~[];
+        for visit_union(&a, &b) |&v| { un.push(v); }
+        assert_eq!(un, ~[1u, 5, 150]);
+    }
+
+    /// Collects any BitCollection's members through the common trait
+    fn members<S: BitCollection>(s: &S) -> ~[uint] {
+        let mut v = ~[];
+        for s.each_bit |b| {
+            v.push(b);
         }
-        assert_eq!(i, expected.len());
+        v
     }
 
     #[test]
-    fn test_bitv_set_union() {
+    fn test_bit_collection_bitv() {
+        let mut a = Bitv::new(8, false);
+        assert!(a.add_bit(1));
+        assert!(a.add_bit(5));
+        assert!(!a.add_bit(5));
+        assert!(a.has_bit(5));
+        assert!(!a.has_bit(2));
+        let mut b = Bitv::new(8, false);
+        b.add_bit(5);
+        b.add_bit(7);
+        assert!(a.union_bits(&b));
+        assert_eq!(members(&a), ~[1u, 5, 7]);
+        assert!(a.intersect_bits(&b));
+        assert_eq!(members(&a), ~[5u, 7]);
+        assert!(a.remove_bit(7));
+        assert!(!a.remove_bit(7));
+    }
+
+    #[test]
+    fn test_bit_collection_bitv_set() {
         let mut a = BitvSet::new();
+        assert!(a.add_bit(3));
+        assert!(a.add_bit(300));
         let mut b = BitvSet::new();
-        assert!(a.insert(1));
-        assert!(a.insert(3));
-        assert!(a.insert(5));
-        assert!(a.insert(9));
-        assert!(a.insert(11));
-        assert!(a.insert(160));
-        assert!(a.insert(19));
-        assert!(a.insert(24));
-
-        assert!(b.insert(1));
-        assert!(b.insert(5));
-        assert!(b.insert(9));
-        assert!(b.insert(13));
-        assert!(b.insert(19));
+        b.add_bit(3);
+        b.add_bit(17);
+        assert!(a.union_bits(&b));
+        assert!(!a.union_bits(&b));
+        assert_eq!(members(&a), ~[3u, 17, 300]);
+        assert!(a.intersect_bits(&b));
+        assert!(!a.intersect_bits(&b));
+        assert_eq!(members(&a), ~[3u, 17]);
+        assert!(a.remove_bit(17));
+        assert!(!a.has_bit(17));
+    }
 
-        let mut i = 0;
-        let expected = [1, 3, 5, 9, 11, 13, 19, 24, 160];
-        for a.union(&b) |x| {
-            assert_eq!(*x, expected[i]);
-            i += 1
-        }
-        assert_eq!(i, expected.len());
+    #[test]
+    fn test_stride_view() {
+        // two interleaved channels: evens set, odds alternating
+        let v = from_fn(16, |i| i % 2 == 0 || i % 4 == 1);
+        let evens = v.stride_view(0, 2);
+        assert_eq!(evens.len(), 8);
+        assert_eq!(evens.count_ones(), 8);
+        let odds = v.stride_view(1, 2);
+        assert_eq!(odds.len(), 8);
+        assert_eq!(odds.count_ones(), 4);
+        assert!(odds.get(0));
+        assert!(!odds.get(1));
+        assert!(odds.to_bitv().eq_vec(~[1u, 0u, 1u, 0u, 1u, 0u, 1u, 0u]));
     }
 
     #[test]
-    fn test_bitv_remove() {
-        let mut a = BitvSet::new();
+    fn test_stride_view_bounds() {
+        let v = Bitv::new(10, true);
+        assert_eq!(v.stride_view(10, 1).len(), 0);
+        assert_eq!(v.stride_view(9, 3).len(), 1);
+        assert_eq!(v.stride_view(0, 3).len(), 4);
+        assert_eq!(v.stride_view(0, 100).len(), 1);
+        assert!(v.stride_view(10, 1).each(|_| fail!()));
+    }
 
-        assert!(a.insert(1));
-        assert!(a.remove(&1));
+    #[test]
+    fn test_sieve_of_eratosthenes() {
+        let primes = Bitv::sieve_of_eratosthenes(30);
+        assert_eq!(primes.nbits, 31);
+        let expected = [2u, 3, 5, 7, 11, 13, 17, 19, 23, 29];
+        for uint::range(0, 31) |i| {
+            assert_eq!(primes[i], expected.contains(&i));
+        }
+    }
 
-        assert!(a.insert(100));
-        assert!(a.remove(&100));
+    #[test]
+    fn test_sieve_small() {
+        assert!(Bitv::sieve_of_eratosthenes(0).is_false());
+        assert!(Bitv::sieve_of_eratosthenes(1).is_false());
+        let two = Bitv::sieve_of_eratosthenes(2);
+        assert!(!two[0] && !two[1] && two[2]);
+    }
 
-        assert!(a.insert(1000));
-        assert!(a.remove(&1000));
-        assert_eq!(a.capacity(), uint::bits);
+    #[test]
+    fn test_sieve_count() {
+        let primes = Bitv::sieve_of_eratosthenes(1000);
+        let mut count = 0;
+        for primes.ones |_| {
+            count += 1;
+        }
+        assert_eq!(count, 168);
     }
 
     fn rng() -> rand::IsaacRng {
@@ -0,0 +1,231 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * Hash-consing for bit vectors. A `BitvInterner` deduplicates
+ * structurally equal `Bitv` values, handing out small integer handles,
+ * and memoizes binary operations on handles, so workloads with
+ * thousands of nearly identical sets (compiler dataflow being the
+ * classic case) store each distinct set once and never compute the same
+ * union twice.
+ */
+
+use bitv;
+use bitv::{Bitv, BitvSet};
+
+use std::hashmap::HashMap;
+
+/// The binary operations the interner can memoize
+enum BitOp {
+    Union,
+    Intersect,
+    Difference
+}
+
+/// The interner. Handles are indices into the interner that created
+/// them and are meaningless elsewhere.
+pub struct BitvInterner {
+    /// Lookup from hashed contents to an existing handle
+    priv map: HashMap<(uint, ~[u8]), uint>,
+    /// Handle to interned value; a handle is an index into this vector
+    priv vals: ~[Bitv],
+    /// Memoized results of binary operations on handles
+    priv memo: HashMap<(uint, uint, uint), uint>
+}
+
+/// The hashable fingerprint of a bit vector: its exact length plus its
+/// packed bytes (the bytes alone would conflate lengths that round up to
+/// the same byte count)
+fn key_of(bits: &Bitv) -> (uint, ~[u8]) {
+    (bits.len(), bits.to_bytes())
+}
+
+impl BitvInterner {
+    /// Create an empty interner
+    pub fn new() -> BitvInterner {
+        BitvInterner{
+            map: HashMap::new(),
+            vals: ~[],
+            memo: HashMap::new()
+        }
+    }
+
+    /// The number of distinct values interned so far
+    pub fn len(&self) -> uint { self.vals.len() }
+
+    /// Intern a value, returning its handle. Structurally equal values
+    /// always receive the same handle.
+    pub fn intern(&mut self, bits: &Bitv) -> uint {
+        let key = key_of(bits);
+        match self.map.find(&key) {
+            Some(&handle) => return handle,
+            None => ()
+        }
+        let handle = self.vals.len();
+        self.vals.push(bits.clone());
+        self.map.insert(key, handle);
+        handle
+    }
+
+    /// Intern the contents of a BitvSet. Two sets with the same members
+    /// and the same capacity receive the same handle.
+    pub fn intern_set(&mut self, set: &BitvSet) -> uint {
+        let mut words = ~[];
+        for set.each_word |_, w| {
+            words.push(w);
+        }
+        self.intern(&bitv::from_words(words))
+    }
+
+    /// Borrow the value behind a handle
+    pub fn get<'a>(&'a self, handle: uint) -> &'a Bitv {
+        &self.vals[handle]
+    }
+
+    /// Apply `op` to two handles, interning and memoizing the result
+    fn apply(&mut self, op: BitOp, lhs: uint, rhs: uint) -> uint {
+        let memo_key = (op as uint, lhs, rhs);
+        match self.memo.find(&memo_key) {
+            Some(&handle) => return handle,
+            None => ()
+        }
+        let mut result = self.vals[lhs].clone();
+        match op {
+            Union => { result.union(&self.vals[rhs]); }
+            Intersect => { result.intersect(&self.vals[rhs]); }
+            Difference => { result.difference(&self.vals[rhs]); }
+        }
+        let handle = self.intern(&result);
+        self.memo.insert(memo_key, handle);
+        handle
+    }
+
+    /// The handle for the union of two handles
+    pub fn union(&mut self, lhs: uint, rhs: uint) -> uint {
+        self.apply(Union, lhs, rhs)
+    }
+
+    /// The handle for the intersection of two handles
+    pub fn intersect(&mut self, lhs: uint, rhs: uint) -> uint {
+        self.apply(Intersect, lhs, rhs)
+    }
+
+    /// The handle for the difference of two handles
+    pub fn difference(&mut self, lhs: uint, rhs: uint) -> uint {
+        self.apply(Difference, lhs, rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bitv;
+
+    #[test]
+    fn test_dedups_equal_values() {
+        let mut interner = BitvInterner::new();
+        let a = bitv::from_bools([true, false, true]);
+        let b = bitv::from_bools([true, false, true]);
+        let c = bitv::from_bools([false, false, true]);
+        let ha = interner.intern(&a);
+        let hb = interner.intern(&b);
+        let hc = interner.intern(&c);
+        assert_eq!(ha, hb);
+        assert!(ha != hc);
+        assert_eq!(interner.len(), 2);
+        assert!(interner.get(ha).eq_vec(~[1u, 0u, 1u]));
+    }
+
+    #[test]
+    fn test_length_part_of_identity() {
+        // same packed bytes, different lengths
+        let mut interner = BitvInterner::new();
+        let short = bitv::from_bools([true, true, true]);
+        let long = bitv::from_bools(
+            [true, true, true, false, false, false, false, false]);
+        let hs = interner.intern(&short);
+        let hl = interner.intern(&long);
+        assert!(hs != hl);
+    }
+
+    #[test]
+    fn test_ops_are_interned() {
+        let mut interner = BitvInterner::new();
+        let a = interner.intern(&bitv::from_bools([true, true, false, false]));
+        let b = interner.intern(&bitv::from_bools([false, true, true, false]));
+
+        let u = interner.union(a, b);
+        assert!(interner.get(u).eq_vec(~[1u, 1u, 1u, 0u]));
+
+        let i = interner.intersect(a, b);
+        assert!(interner.get(i).eq_vec(~[0u, 1u, 0u, 0u]));
+
+        let d = interner.difference(a, b);
+        assert!(interner.get(d).eq_vec(~[1u, 0u, 0u, 0u]));
+
+        // an op result equal to an existing value reuses its handle
+        let everything = interner.intern(
+            &bitv::from_bools([true, true, true, false]));
+        assert_eq!(u, everything);
+    }
+
+    #[test]
+    fn test_intern_set() {
+        use bitv::BitvSet;
+
+        let mut interner = BitvInterner::new();
+        let mut s1 = BitvSet::new();
+        let mut s2 = BitvSet::new();
+        assert!(s1.insert(3));
+        assert!(s1.insert(40));
+        assert!(s2.insert(40));
+        assert!(s2.insert(3));
+        let h1 = interner.intern_set(&s1);
+        let h2 = interner.intern_set(&s2);
+        assert_eq!(h1, h2);
+        assert!(interner.get(h1)[40]);
+    }
+
+    #[test]
+    fn test_intern_multiword() {
+        use bitv::BitvSet;
+        use std::uint;
+
+        // word-multiple lengths are what intern_set builds for any set
+        // touching an element past the first word
+        let mut interner = BitvInterner::new();
+        let a = interner.intern(&bitv::from_words(~[0b1000, 0b1]));
+        let b = interner.intern(&bitv::from_words(~[0, 0b10000000]));
+        assert!(a != b);
+        let u = interner.union(a, b);
+        let got = interner.get(u);
+        assert_eq!(got.len(), 2 * uint::bits);
+        assert!(got[3] && got[uint::bits] && got[uint::bits + 7]);
+
+        let mut s = BitvSet::new();
+        assert!(s.insert(3));
+        assert!(s.insert(uint::bits));
+        let h = interner.intern_set(&s);
+        assert!(interner.get(h)[3] && interner.get(h)[uint::bits]);
+    }
+
+    #[test]
+    fn test_ops_are_memoized() {
+        let mut interner = BitvInterner::new();
+        let a = interner.intern(&bitv::from_bools([true, false]));
+        let b = interner.intern(&bitv::from_bools([false, true]));
+        let u1 = interner.union(a, b);
+        let before = interner.len();
+        let u2 = interner.union(a, b);
+        assert_eq!(u1, u2);
+        assert_eq!(interner.len(), before);
+    }
+}
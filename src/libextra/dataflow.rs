@@ -0,0 +1,284 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * Scaffolding for bit-vector dataflow analyses: a `DataflowSets` type
+ * holding one equal-length bit set per graph node in a single
+ * contiguous allocation, word-level gen/kill transfer application, and
+ * a worklist driver that iterates transfer functions to a fixed point,
+ * requeueing successors only when a node's set actually changed.
+ */
+
+use bitv;
+use bitv::Bitv;
+
+use std::uint;
+use std::vec;
+
+/// A family of equal-length bit sets, one per node, stored contiguously
+pub struct DataflowSets {
+    /// The number of sets
+    priv nsets: uint,
+    /// The length of each set in bits
+    priv nbits: uint,
+    /// The number of words each set occupies
+    priv words_per_set: uint,
+    /// All sets, packed back to back
+    priv storage: ~[uint]
+}
+
+impl DataflowSets {
+    /// Create `nsets` empty sets of `nbits` bits each
+    pub fn new(nsets: uint, nbits: uint) -> DataflowSets {
+        let words_per_set = uint::div_ceil(nbits, uint::bits);
+        DataflowSets{
+            nsets: nsets,
+            nbits: nbits,
+            words_per_set: words_per_set,
+            storage: vec::from_elem(nsets * words_per_set, 0)
+        }
+    }
+
+    /// The number of sets
+    pub fn nsets(&self) -> uint { self.nsets }
+
+    /// The length of each set in bits
+    pub fn nbits(&self) -> uint { self.nbits }
+
+    /// The storage index of word `i` of set `set`
+    #[inline]
+    fn word(&self, set: uint, i: uint) -> uint {
+        set * self.words_per_set + i
+    }
+
+    /// Read bit `bit` of set `set`
+    pub fn get(&self, set: uint, bit: uint) -> bool {
+        assert!(set < self.nsets && bit < self.nbits);
+        let w = self.storage[self.word(set, bit / uint::bits)];
+        w & (1 << (bit % uint::bits)) != 0
+    }
+
+    /// Set bit `bit` of set `set`
+    pub fn set_bit(&mut self, set: uint, bit: uint) {
+        assert!(set < self.nsets && bit < self.nbits);
+        let w = self.word(set, bit / uint::bits);
+        self.storage[w] |= 1 << (bit % uint::bits);
+    }
+
+    /// Clear bit `bit` of set `set`
+    pub fn clear_bit(&mut self, set: uint, bit: uint) {
+        assert!(set < self.nsets && bit < self.nbits);
+        let w = self.word(set, bit / uint::bits);
+        self.storage[w] &= !(1 << (bit % uint::bits));
+    }
+
+    /// Clear every bit of set `set`
+    pub fn clear_set(&mut self, set: uint) {
+        assert!(set < self.nsets);
+        for uint::range(0, self.words_per_set) |i| {
+            let w = self.word(set, i);
+            self.storage[w] = 0;
+        }
+    }
+
+    /// Make set `dst` a copy of set `src`. Return true if `dst` changed.
+    pub fn copy_set(&mut self, dst: uint, src: uint) -> bool {
+        assert!(dst < self.nsets && src < self.nsets);
+        let mut changed = false;
+        for uint::range(0, self.words_per_set) |i| {
+            let v = self.storage[self.word(src, i)];
+            let d = self.word(dst, i);
+            if self.storage[d] != v {
+                self.storage[d] = v;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Union set `src` into set `dst`. Return true if `dst` gained any
+    /// bits.
+    pub fn union_into(&mut self, dst: uint, src: uint) -> bool {
+        assert!(dst < self.nsets && src < self.nsets);
+        let mut changed = false;
+        for uint::range(0, self.words_per_set) |i| {
+            let v = self.storage[self.word(src, i)];
+            let d = self.word(dst, i);
+            let merged = self.storage[d] | v;
+            if self.storage[d] != merged {
+                self.storage[d] = merged;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Apply the classic transfer function for node `node`: replace set
+    /// `node` with `gens[node] | (self[src] & !kills[node])`. The gen and
+    /// kill families must have the same geometry as `self`. Return true
+    /// if set `node` changed.
+    pub fn apply_gen_kill(&mut self, node: uint, src: uint,
+                          gens: &DataflowSets,
+                          kills: &DataflowSets) -> bool {
+        assert!(node < self.nsets && src < self.nsets);
+        assert!(gens.words_per_set == self.words_per_set);
+        assert!(kills.words_per_set == self.words_per_set);
+        let mut changed = false;
+        for uint::range(0, self.words_per_set) |i| {
+            let input = self.storage[self.word(src, i)];
+            let gen = gens.storage[gens.word(node, i)];
+            let kill = kills.storage[kills.word(node, i)];
+            let out = gen | (input & !kill);
+            let d = self.word(node, i);
+            if self.storage[d] != out {
+                self.storage[d] = out;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Visit each set bit of set `set` in order
+    pub fn each_bit(&self, set: uint, f: &fn(uint) -> bool) -> bool {
+        assert!(set < self.nsets);
+        for uint::range(0, self.words_per_set) |i| {
+            let w = self.storage[self.word(set, i)];
+            if !bitv::iterate_bits(i * uint::bits, w, f) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Copy set `set` out into a standalone Bitv
+    pub fn to_bitv(&self, set: uint) -> Bitv {
+        let mut bits = Bitv::new(self.nbits, false);
+        for self.each_bit(set) |b| {
+            bits.set(b, true);
+        }
+        bits
+    }
+}
+
+/**
+ * Run a worklist algorithm to a fixed point. Every node in
+ * `[0, nnodes)` is evaluated at least once; whenever `transfer(n)`
+ * reports that node n's state changed, the successors of n (as
+ * enumerated by `each_succ`) are queued for re-evaluation. Returns the
+ * total number of transfer function evaluations.
+ */
+pub fn fixpoint(nnodes: uint,
+                each_succ: &fn(uint, &fn(uint) -> bool) -> bool,
+                transfer: &fn(uint) -> bool) -> uint {
+    let mut queue = vec::from_fn(nnodes, |n| n);
+    let mut queued = Bitv::new(nnodes, true);
+    let mut head = 0;
+    let mut evals = 0;
+    while head < queue.len() {
+        let n = queue[head];
+        head += 1;
+        queued.set(n, false);
+        evals += 1;
+        if transfer(n) {
+            for each_succ(n) |s| {
+                if !queued[s] {
+                    queued.set(s, true);
+                    queue.push(s);
+                }
+            }
+        }
+    }
+    evals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::uint;
+
+    #[test]
+    fn test_basic_bits() {
+        let mut sets = DataflowSets::new(3, 100);
+        assert_eq!(sets.nsets(), 3);
+        assert_eq!(sets.nbits(), 100);
+        sets.set_bit(1, 99);
+        sets.set_bit(1, 0);
+        assert!(sets.get(1, 99));
+        assert!(!sets.get(0, 99));
+        sets.clear_bit(1, 99);
+        assert!(!sets.get(1, 99));
+        assert!(sets.to_bitv(1)[0]);
+    }
+
+    #[test]
+    fn test_union_and_copy_report_changes() {
+        let mut sets = DataflowSets::new(2, 64);
+        sets.set_bit(0, 5);
+        assert!(sets.union_into(1, 0));
+        assert!(!sets.union_into(1, 0));
+        sets.set_bit(0, 40);
+        assert!(sets.copy_set(1, 0));
+        assert!(!sets.copy_set(1, 0));
+        assert!(sets.get(1, 40));
+    }
+
+    #[test]
+    fn test_apply_gen_kill() {
+        let mut sets = DataflowSets::new(2, 8);
+        let mut gens = DataflowSets::new(2, 8);
+        let mut kills = DataflowSets::new(2, 8);
+        sets.set_bit(0, 1);
+        sets.set_bit(0, 2);
+        gens.set_bit(1, 3);
+        kills.set_bit(1, 2);
+
+        // out[1] = gen[1] | (out[0] - kill[1]) = {3} | {1} = {1, 3}
+        assert!(sets.apply_gen_kill(1, 0, &gens, &kills));
+        assert!(sets.get(1, 1));
+        assert!(!sets.get(1, 2));
+        assert!(sets.get(1, 3));
+        assert!(!sets.apply_gen_kill(1, 0, &gens, &kills));
+    }
+
+    #[test]
+    fn test_fixpoint_reachability() {
+        // a cycle 0 -> 1 -> 2 -> 0 with a side exit 1 -> 3; every node
+        // generates its own bit, nothing is killed, so each node's set
+        // converges to the definitions reaching it
+        static NNODES: uint = 4;
+        let succs = ~[~[1u], ~[2u, 3u], ~[0u], ~[]];
+        let preds = ~[~[2u], ~[0u], ~[1u], ~[1u]];
+
+        let mut sets = DataflowSets::new(NNODES + 1, NNODES);
+        let mut gens = DataflowSets::new(NNODES + 1, NNODES);
+        let kills = DataflowSets::new(NNODES + 1, NNODES);
+        for uint::range(0, NNODES) |n| {
+            gens.set_bit(n, n);
+        }
+
+        // the last set is scratch space for the merged input
+        let evals = do fixpoint(NNODES,
+                                |n, f| succs[n].iter().advance(|&s| f(s)))
+                |n| {
+            sets.clear_set(NNODES);
+            for preds[n].iter().advance |&p| {
+                sets.union_into(NNODES, p);
+            }
+            sets.apply_gen_kill(n, NNODES, &gens, &kills)
+        };
+        assert!(evals >= NNODES);
+
+        for uint::range(0, 3) |n| {
+            assert!(sets.to_bitv(n).eq_vec(~[1u, 1u, 1u, 0u]));
+        }
+        assert!(sets.to_bitv(3).eq_vec(~[1u, 1u, 1u, 1u]));
+    }
+}
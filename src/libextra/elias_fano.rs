@@ -0,0 +1,277 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * An Elias-Fano encoded set of strictly increasing uints. Each value
+ * is split into a low half stored verbatim in a packed array and a
+ * high half stored in unary in a rank/select bitvector, giving close
+ * to the information-theoretic minimum of space for large sorted ID
+ * lists — the regime where a BitvSet over the universe is too big and
+ * a plain ~[uint] too fat. The structure is static once built, with
+ * O(1) `select` and near-O(1) `rank` and membership.
+ */
+
+use bitv::Bitv;
+use rs_bitv::RsBitv;
+
+use std::uint;
+use std::vec;
+
+/// The Elias-Fano set type
+pub struct EliasFanoSet {
+    /// The number of values stored
+    priv size: uint,
+    /// The number of low bits stored verbatim per value
+    priv low_bits: uint,
+    /// The low halves, packed at `low_bits` bits each
+    priv lows: ~[uint],
+    /// The high halves in unary: value `i` sets bit `high(i) + i`
+    priv highs: RsBitv
+}
+
+/// Read element `i` of an array packed at `width` bits per element
+fn get_packed(words: &[uint], width: uint, i: uint) -> uint {
+    let bit = i * width;
+    let w = bit / uint::bits;
+    let o = bit % uint::bits;
+    let mask = (1 << width) - 1;
+    if o + width <= uint::bits {
+        (words[w] >> o) & mask
+    } else {
+        ((words[w] >> o) | (words[w + 1] << (uint::bits - o))) & mask
+    }
+}
+
+/// Write element `i` of an array packed at `width` bits per element
+fn set_packed(words: &mut [uint], width: uint, i: uint, value: uint) {
+    let bit = i * width;
+    let w = bit / uint::bits;
+    let o = bit % uint::bits;
+    if o + width <= uint::bits {
+        let mask = ((1 << width) - 1) << o;
+        words[w] = (words[w] & !mask) | (value << o);
+    } else {
+        let lo = uint::bits - o;
+        let lo_mask = ((1 << lo) - 1) << o;
+        words[w] = (words[w] & !lo_mask) | (value << o);
+        let hi_mask = (1 << (width - lo)) - 1;
+        words[w + 1] = (words[w + 1] & !hi_mask) | (value >> lo);
+    }
+}
+
+impl Container for EliasFanoSet {
+    /// Return the number of values in the set
+    fn len(&self) -> uint { self.size }
+
+    /// Return true if the set is empty
+    fn is_empty(&self) -> bool { self.size == 0 }
+}
+
+impl EliasFanoSet {
+    /// Encode a strictly increasing sequence of values
+    pub fn from_sorted(values: &[uint]) -> EliasFanoSet {
+        let n = values.len();
+        if n == 0 {
+            return EliasFanoSet{
+                size: 0,
+                low_bits: 0,
+                lows: ~[],
+                highs: RsBitv::from_bitv(&Bitv::new(1, false))
+            };
+        }
+        for uint::range(1, n) |i| {
+            assert!(values[i - 1] < values[i]);
+        }
+        let universe = values[n - 1] + 1;
+
+        // the space-optimal split stores floor(log2(u / n)) low bits
+        let mut low_bits = 0;
+        while (universe >> (low_bits + 1)) >= n {
+            low_bits += 1;
+        }
+
+        let mut lows = if low_bits == 0 {
+            ~[]
+        } else {
+            vec::from_elem(uint::div_ceil(n * low_bits, uint::bits) + 1, 0)
+        };
+        let max_high = (universe - 1) >> low_bits;
+        let mut high = Bitv::new(n + max_high + 1, false);
+        for values.iter().enumerate().advance |(i, &v)| {
+            if low_bits > 0 {
+                set_packed(lows, low_bits, i, v & ((1 << low_bits) - 1));
+            }
+            high.set((v >> low_bits) + i, true);
+        }
+        EliasFanoSet{
+            size: n,
+            low_bits: low_bits,
+            lows: lows,
+            highs: RsBitv::from_bitv(&high)
+        }
+    }
+
+    /// The low half of the `k`th value
+    fn low(&self, k: uint) -> uint {
+        if self.low_bits == 0 {
+            0
+        } else {
+            get_packed(self.lows, self.low_bits, k)
+        }
+    }
+
+    /// The index range of values whose high half is `h`
+    fn bucket(&self, h: uint) -> (uint, uint) {
+        let start = if h == 0 {
+            0
+        } else {
+            match self.highs.select0(h - 1) {
+                Some(pos) => pos - (h - 1),
+                None => return (self.size, self.size)
+            }
+        };
+        let end = match self.highs.select0(h) {
+            Some(pos) => pos - h,
+            None => self.size
+        };
+        (start, end)
+    }
+
+    /// The `k`th smallest value in the set
+    pub fn select(&self, k: uint) -> uint {
+        assert!(k < self.size);
+        let pos = self.highs.select(k).unwrap();
+        ((pos - k) << self.low_bits) | self.low(k)
+    }
+
+    /// The number of values strictly less than `x`
+    pub fn rank(&self, x: uint) -> uint {
+        let h = x >> self.low_bits;
+        let lo = x & ((1 << self.low_bits) - 1);
+        let (start, end) = self.bucket(h);
+        let mut k = start;
+        while k < end && self.low(k) < lo {
+            k += 1;
+        }
+        k
+    }
+
+    /// Return true if `x` is in the set
+    pub fn contains(&self, x: uint) -> bool {
+        let h = x >> self.low_bits;
+        let lo = x & ((1 << self.low_bits) - 1);
+        let (start, end) = self.bucket(h);
+        let mut k = start;
+        while k < end {
+            if self.low(k) == lo {
+                return true;
+            }
+            k += 1;
+        }
+        false
+    }
+
+    /// Visit the values in increasing order
+    pub fn each(&self, f: &fn(uint) -> bool) -> bool {
+        for uint::range(0, self.size) |k| {
+            if !f(self.select(k)) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// The number of words of storage behind the encoding
+    pub fn storage_words(&self) -> uint {
+        self.lows.len() + uint::div_ceil(self.highs.len(), uint::bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::uint;
+    use std::vec;
+
+    #[test]
+    fn test_select_roundtrip() {
+        let values = ~[2u, 3, 5, 7, 11, 13, 24, 1000, 1001, 900000];
+        let ef = EliasFanoSet::from_sorted(values);
+        assert_eq!(ef.len(), values.len());
+        for values.iter().enumerate().advance |(k, &v)| {
+            assert_eq!(ef.select(k), v);
+        }
+    }
+
+    #[test]
+    fn test_rank_and_contains() {
+        let values = ~[0u, 17, 18, 40, 63, 64, 65, 5000];
+        let ef = EliasFanoSet::from_sorted(values);
+        for uint::range(0, 100) |x| {
+            let mut expected = 0;
+            for values.iter().advance |&v| {
+                if v < x { expected += 1; }
+            }
+            assert_eq!(ef.rank(x), expected);
+            assert_eq!(ef.contains(x), values.contains(&x));
+        }
+        assert_eq!(ef.rank(5000), 7);
+        assert_eq!(ef.rank(5001), 8);
+        assert!(ef.contains(5000));
+        assert!(!ef.contains(4999));
+    }
+
+    #[test]
+    fn test_each_in_order() {
+        let values = ~[1u, 4, 9, 16, 25];
+        let ef = EliasFanoSet::from_sorted(values);
+        let mut observed = ~[];
+        for ef.each |v| {
+            observed.push(v);
+        }
+        assert_eq!(observed, values);
+    }
+
+    #[test]
+    fn test_empty() {
+        let ef = EliasFanoSet::from_sorted([]);
+        assert!(ef.is_empty());
+        assert_eq!(ef.rank(100), 0);
+        assert!(!ef.contains(0));
+        assert!(ef.each(|_| fail!()));
+    }
+
+    #[test]
+    fn test_dense_run() {
+        // dense values force low_bits to zero
+        let mut values = ~[];
+        for uint::range(10, 200) |i| {
+            values.push(i);
+        }
+        let ef = EliasFanoSet::from_sorted(values);
+        assert_eq!(ef.select(0), 10);
+        assert_eq!(ef.select(189), 199);
+        assert_eq!(ef.rank(10), 0);
+        assert_eq!(ef.rank(200), 190);
+        assert!(ef.contains(57));
+        assert!(!ef.contains(5));
+    }
+
+    #[test]
+    fn test_space_is_compact() {
+        // a million-wide universe with 100 values should take far
+        // fewer words than a bitmap over the universe
+        let values = vec::from_fn(100, |i| i * 9973);
+        let ef = EliasFanoSet::from_sorted(values);
+        assert!(ef.storage_words() <
+                1_000_000 / uint::bits / 10);
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A fixed bit set backed by a single machine word, indexed by the values
+ * of a C-like enum. Each enum value occupies one bit, so membership tests
+ * and whole-set operations are single instructions, in the style of C
+ * bit-flag words but with the element type checked statically.
+ */
+
+use std::iterator::Iterator;
+use std::uint;
+
+/// An interface for casting C-like enum values to uints and back
+pub trait CLike {
+    /// Converts a C-like enum to a uint; the result must be smaller than
+    /// `uint::bits`
+    pub fn to_uint(&self) -> uint;
+    /// Converts a uint produced by `to_uint` back into a C-like enum
+    pub fn from_uint(uint) -> Self;
+}
+
+/// Implements `CLike` for a C-like enum whose discriminants start at zero.
+/// (Macros are not currently exported across crates, so this is only
+/// usable from within libextra; external users write the impl by hand.)
+macro_rules! c_like_enum {
+    ($T:ty) => {
+        impl CLike for $T {
+            pub fn to_uint(&self) -> uint { *self as uint }
+            pub fn from_uint(v: uint) -> $T {
+                unsafe { ::std::cast::transmute(v as int) }
+            }
+        }
+    }
+}
+
+/// A set of C-like enum values, stored as bits in a single uint
+#[deriving(Clone, Eq)]
+pub struct EnumSet<E> {
+    /// The bitwise OR of `bit(e)` over the members of the set
+    priv bits: uint
+}
+
+#[inline]
+fn bit<E: CLike>(e: E) -> uint {
+    let i = e.to_uint();
+    assert!(i < uint::bits);
+    1 << i
+}
+
+impl<E: CLike> EnumSet<E> {
+    /// Create an empty EnumSet
+    pub fn empty() -> EnumSet<E> {
+        EnumSet{bits: 0}
+    }
+
+    /// Return true if the set contains no values
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /// Return the number of values in the set
+    pub fn len(&self) -> uint {
+        let mut w = self.bits;
+        let mut count = 0;
+        while w != 0 {
+            w &= w - 1;
+            count += 1;
+        }
+        count
+    }
+
+    /// Return true if the two sets have any values in common
+    pub fn intersects(&self, e: EnumSet<E>) -> bool {
+        self.bits & e.bits != 0
+    }
+
+    /// Return the intersection of the two sets
+    pub fn intersection(&self, e: EnumSet<E>) -> EnumSet<E> {
+        EnumSet{bits: self.bits & e.bits}
+    }
+
+    /// Return true if the set contains every value of `e`
+    pub fn contains(&self, e: EnumSet<E>) -> bool {
+        self.bits & e.bits == e.bits
+    }
+
+    /// Return the union of the two sets
+    pub fn union(&self, e: EnumSet<E>) -> EnumSet<E> {
+        EnumSet{bits: self.bits | e.bits}
+    }
+
+    /// Add a value to the set. Return true if the value was not already
+    /// present in the set.
+    pub fn add(&mut self, e: E) -> bool {
+        let flag = bit(e);
+        let present = self.bits & flag != 0;
+        self.bits |= flag;
+        !present
+    }
+
+    /// Remove a value from the set. Return true if the value was
+    /// present in the set.
+    pub fn remove(&mut self, e: E) -> bool {
+        let flag = bit(e);
+        let present = self.bits & flag != 0;
+        self.bits &= !flag;
+        present
+    }
+
+    /// Return true if the set contains `e`
+    pub fn contains_elem(&self, e: E) -> bool {
+        self.bits & bit(e) != 0
+    }
+
+    /// Visit each value in the set, in increasing discriminant order
+    pub fn each(&self, f: &fn(E) -> bool) -> bool {
+        let mut bits = self.bits;
+        let mut index = 0;
+        while bits != 0 {
+            if bits & 1 != 0 {
+                let e: E = CLike::from_uint(index);
+                if !f(e) {
+                    return false;
+                }
+            }
+            index += 1;
+            bits >>= 1;
+        }
+        return true;
+    }
+
+    /// An external iterator over the values in the set
+    pub fn iter(&self) -> EnumSetIterator<E> {
+        EnumSetIterator{index: 0, bits: self.bits}
+    }
+}
+
+impl<E: CLike> Sub<EnumSet<E>, EnumSet<E>> for EnumSet<E> {
+    fn sub(&self, e: &EnumSet<E>) -> EnumSet<E> {
+        EnumSet{bits: self.bits & !e.bits}
+    }
+}
+
+impl<E: CLike> BitOr<EnumSet<E>, EnumSet<E>> for EnumSet<E> {
+    fn bitor(&self, e: &EnumSet<E>) -> EnumSet<E> {
+        EnumSet{bits: self.bits | e.bits}
+    }
+}
+
+impl<E: CLike> BitAnd<EnumSet<E>, EnumSet<E>> for EnumSet<E> {
+    fn bitand(&self, e: &EnumSet<E>) -> EnumSet<E> {
+        EnumSet{bits: self.bits & e.bits}
+    }
+}
+
+/// An iterator over an EnumSet
+pub struct EnumSetIterator<E> {
+    priv index: uint,
+    priv bits: uint
+}
+
+impl<E: CLike> Iterator<E> for EnumSetIterator<E> {
+    fn next(&mut self) -> Option<E> {
+        if self.bits == 0 {
+            return None;
+        }
+        while self.bits & 1 == 0 {
+            self.index += 1;
+            self.bits >>= 1;
+        }
+        let elem = CLike::from_uint(self.index);
+        self.index += 1;
+        self.bits >>= 1;
+        Some(elem)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[deriving(Eq)]
+    enum Foo {
+        A, B, C
+    }
+
+    c_like_enum!(Foo)
+
+    #[test]
+    fn test_empty() {
+        let e: EnumSet<Foo> = EnumSet::empty();
+        assert!(e.is_empty());
+        assert_eq!(e.len(), 0);
+        assert!(!e.contains_elem(A));
+    }
+
+    #[test]
+    fn test_add_remove() {
+        let mut e: EnumSet<Foo> = EnumSet::empty();
+        assert!(e.add(A));
+        assert!(!e.add(A));
+        assert!(e.add(C));
+        assert_eq!(e.len(), 2);
+        assert!(e.contains_elem(A));
+        assert!(!e.contains_elem(B));
+        assert!(e.contains_elem(C));
+        assert!(e.remove(A));
+        assert!(!e.remove(A));
+        assert_eq!(e.len(), 1);
+    }
+
+    #[test]
+    fn test_operators() {
+        let mut ab: EnumSet<Foo> = EnumSet::empty();
+        ab.add(A);
+        ab.add(B);
+        let mut bc: EnumSet<Foo> = EnumSet::empty();
+        bc.add(B);
+        bc.add(C);
+
+        let union = ab | bc;
+        assert_eq!(union.len(), 3);
+
+        let inter = ab & bc;
+        assert_eq!(inter.len(), 1);
+        assert!(inter.contains_elem(B));
+
+        let diff = ab - bc;
+        assert_eq!(diff.len(), 1);
+        assert!(diff.contains_elem(A));
+
+        assert!(ab.intersects(bc));
+        assert!(union.contains(ab));
+        assert!(!ab.contains(union));
+    }
+
+    #[test]
+    fn test_each() {
+        let mut e: EnumSet<Foo> = EnumSet::empty();
+        e.add(C);
+        e.add(A);
+        let mut observed = ~[];
+        for e.each |v| {
+            observed.push(v);
+        }
+        assert_eq!(observed, ~[A, C]);
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut e: EnumSet<Foo> = EnumSet::empty();
+        e.add(B);
+        e.add(C);
+        let mut it = e.iter();
+        assert_eq!(it.next(), Some(B));
+        assert_eq!(it.next(), Some(C));
+        assert_eq!(it.next(), None);
+    }
+}
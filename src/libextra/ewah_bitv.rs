@@ -0,0 +1,337 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A word-aligned hybrid compressed bitmap in the style of WAH/EWAH. The
+ * bitmap is an append-only sequence of machine words, stored either as
+ * literal words or as fills (runs of all-zero or all-one words). Boolean
+ * operations stream over the compressed operands directly, merging fills
+ * without expanding them, which is the standard evaluation strategy for
+ * bitmap indexes.
+ */
+
+use bitv;
+use bitv::Bitv;
+
+use std::uint;
+use std::vec;
+
+/// A single compressed container word
+enum EwahWord {
+    /// A run of consecutive all-zero or all-one uncompressed words
+    Fill(bool, uint),
+    /// A single uncompressed word stored verbatim
+    Literal(uint)
+}
+
+/// The compressed bitmap type. Bits are appended a word at a time and the
+/// logical length is always a multiple of `uint::bits`.
+pub struct EwahBitv {
+    /// The compressed container words
+    priv words: ~[EwahWord],
+    /// The number of uncompressed words represented
+    priv nwords: uint
+}
+
+impl EwahBitv {
+    /// Create an empty bitmap
+    pub fn new() -> EwahBitv {
+        EwahBitv{words: ~[], nwords: 0}
+    }
+
+    /// The number of bits in the bitmap
+    pub fn len(&self) -> uint { self.nwords * uint::bits }
+
+    /// The number of uncompressed words represented
+    pub fn word_len(&self) -> uint { self.nwords }
+
+    /// The number of container words actually stored
+    pub fn compressed_len(&self) -> uint { self.words.len() }
+
+    /// Append one uncompressed word of bits. All-zero and all-one words
+    /// are absorbed into fills; anything else is stored as a literal.
+    pub fn push_word(&mut self, w: uint) {
+        self.nwords += 1;
+        if w == 0 || w == !0 {
+            self.push_fill(w != 0, 1);
+        } else {
+            self.words.push(Literal(w));
+        }
+    }
+
+    /// Append a fill of `count` words, each all-zero or all-one
+    fn push_fill(&mut self, value: bool, count: uint) {
+        if count == 0 { return; }
+        let n = self.words.len();
+        if n > 0 {
+            let merged = match self.words[n - 1] {
+                Fill(v, c) if v == value => Some(c + count),
+                _ => None
+            };
+            match merged {
+                Some(c) => {
+                    self.words[n - 1] = Fill(value, c);
+                    return;
+                }
+                None => {}
+            }
+        }
+        self.words.push(Fill(value, count));
+    }
+
+    /// Compress an existing bit vector. If the vector's length is not a
+    /// multiple of the word size, the final word is padded with zeros.
+    pub fn from_bitv(bitv: &Bitv) -> EwahBitv {
+        let mut ewah = EwahBitv::new();
+        let mut w = 0;
+        let mut filled = 0;
+        for bitv.each |b| {
+            if b { w |= 1 << filled; }
+            filled += 1;
+            if filled == uint::bits {
+                ewah.push_word(w);
+                w = 0;
+                filled = 0;
+            }
+        }
+        if filled > 0 {
+            ewah.push_word(w);
+        }
+        ewah
+    }
+
+    /// Expand into an uncompressed bit vector
+    pub fn to_bitv(&self) -> Bitv {
+        let mut words = vec::with_capacity(self.nwords);
+        for self.each_word |w| {
+            words.push(w);
+        }
+        bitv::from_words(words)
+    }
+
+    /// Visit each uncompressed word in order
+    pub fn each_word(&self, f: &fn(uint) -> bool) -> bool {
+        for self.words.iter().advance |cw| {
+            match *cw {
+                Literal(w) => if !f(w) { return false; },
+                Fill(value, count) => {
+                    let w = if value {!0} else {0};
+                    for count.times {
+                        if !f(w) { return false; }
+                    }
+                }
+            }
+        }
+        return true;
+    }
+
+    /// Retrieve the value at bit index `i`
+    pub fn get(&self, i: uint) -> bool {
+        assert!(i < self.len());
+        let target = i / uint::bits;
+        let mut word = 0;
+        for self.words.iter().advance |cw| {
+            match *cw {
+                Literal(w) => {
+                    if word == target {
+                        return w & (1 << (i % uint::bits)) != 0;
+                    }
+                    word += 1;
+                }
+                Fill(value, count) => {
+                    if target < word + count {
+                        return value;
+                    }
+                    word += count;
+                }
+            }
+        }
+        fail!("container words inconsistent with word count");
+    }
+
+    /// Stream a word-wise binary operation over two compressed operands,
+    /// producing a compressed result. Aligned fills are combined a whole
+    /// run at a time. A shorter operand is treated as zero-extended.
+    fn binop(&self, other: &EwahBitv, op: &fn(uint, uint) -> uint)
+             -> EwahBitv {
+        let total = uint::max(self.nwords, other.nwords);
+        let mut result = EwahBitv::new();
+        let mut i = 0;
+        let mut j = 0;
+        let mut rem1 = 0;
+        let mut rem2 = 0;
+        let mut w1 = 0;
+        let mut w2 = 0;
+        let mut fill1 = false;
+        let mut fill2 = false;
+        let mut done = 0;
+        while done < total {
+            if rem1 == 0 {
+                if i < self.words.len() {
+                    match self.words[i] {
+                        Literal(w) => { w1 = w; rem1 = 1; fill1 = false; }
+                        Fill(v, c) => {
+                            w1 = if v {!0} else {0};
+                            rem1 = c;
+                            fill1 = true;
+                        }
+                    }
+                    i += 1;
+                } else {
+                    w1 = 0;
+                    rem1 = total - done;
+                    fill1 = true;
+                }
+            }
+            if rem2 == 0 {
+                if j < other.words.len() {
+                    match other.words[j] {
+                        Literal(w) => { w2 = w; rem2 = 1; fill2 = false; }
+                        Fill(v, c) => {
+                            w2 = if v {!0} else {0};
+                            rem2 = c;
+                            fill2 = true;
+                        }
+                    }
+                    j += 1;
+                } else {
+                    w2 = 0;
+                    rem2 = total - done;
+                    fill2 = true;
+                }
+            }
+            if fill1 && fill2 {
+                let chunk = uint::min(rem1, rem2);
+                result.push_fill(op(w1, w2) != 0, chunk);
+                result.nwords += chunk;
+                rem1 -= chunk;
+                rem2 -= chunk;
+                done += chunk;
+            } else {
+                result.push_word(op(w1, w2));
+                rem1 -= 1;
+                rem2 -= 1;
+                done += 1;
+            }
+        }
+        result
+    }
+
+    /// The bitwise OR of two compressed bitmaps
+    pub fn union(&self, other: &EwahBitv) -> EwahBitv {
+        self.binop(other, |a, b| a | b)
+    }
+
+    /// The bitwise AND of two compressed bitmaps
+    pub fn intersect(&self, other: &EwahBitv) -> EwahBitv {
+        self.binop(other, |a, b| a & b)
+    }
+
+    /// The bitwise XOR of two compressed bitmaps
+    pub fn xor(&self, other: &EwahBitv) -> EwahBitv {
+        self.binop(other, |a, b| a ^ b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::uint;
+
+    #[test]
+    fn test_push_word_compression() {
+        let mut ewah = EwahBitv::new();
+        for 100.times {
+            ewah.push_word(0);
+        }
+        for 100.times {
+            ewah.push_word(!0);
+        }
+        ewah.push_word(0b1010);
+        assert_eq!(ewah.word_len(), 201);
+        assert_eq!(ewah.compressed_len(), 3);
+        assert_eq!(ewah.len(), 201 * uint::bits);
+    }
+
+    #[test]
+    fn test_get() {
+        let mut ewah = EwahBitv::new();
+        ewah.push_word(0);
+        ewah.push_word(0b100);
+        ewah.push_word(!0);
+        assert!(!ewah.get(0));
+        assert!(!ewah.get(uint::bits - 1));
+        assert!(ewah.get(uint::bits + 2));
+        assert!(!ewah.get(uint::bits + 3));
+        assert!(ewah.get(2 * uint::bits));
+        assert!(ewah.get(3 * uint::bits - 1));
+    }
+
+    #[test]
+    fn test_bitv_round_trip() {
+        let mut ewah = EwahBitv::new();
+        ewah.push_word(0b1100);
+        ewah.push_word(0);
+        ewah.push_word(!0);
+        let bitv = ewah.to_bitv();
+        assert_eq!(bitv.to_vec().len(), 3 * uint::bits);
+        let again = EwahBitv::from_bitv(&bitv);
+        assert!(again.to_bitv().equal(&bitv));
+        assert_eq!(again.compressed_len(), 3);
+    }
+
+    #[test]
+    fn test_ops() {
+        let mut a = EwahBitv::new();
+        let mut b = EwahBitv::new();
+        for 10.times { a.push_word(0); }
+        a.push_word(0b1111);
+        for 10.times { b.push_word(!0); }
+        b.push_word(0b1010);
+
+        let or = a.union(&b);
+        assert_eq!(or.word_len(), 11);
+        assert!(or.get(0));
+        assert!(or.get(10 * uint::bits));
+        assert!(or.get(10 * uint::bits + 2));
+
+        let and = a.intersect(&b);
+        assert!(!and.get(0));
+        assert!(!and.get(10 * uint::bits));
+        assert!(and.get(10 * uint::bits + 1));
+        assert!(and.get(10 * uint::bits + 3));
+
+        let xor = a.xor(&b);
+        assert!(xor.get(0));
+        assert!(xor.get(10 * uint::bits + 0));
+        assert!(!xor.get(10 * uint::bits + 1));
+        assert!(xor.get(10 * uint::bits + 2));
+    }
+
+    #[test]
+    fn test_ops_unequal_lengths() {
+        let mut a = EwahBitv::new();
+        let mut b = EwahBitv::new();
+        a.push_word(0b11);
+        for 5.times { b.push_word(!0); }
+
+        let or = a.union(&b);
+        assert_eq!(or.word_len(), 5);
+        assert!(or.get(4 * uint::bits));
+
+        let and = a.intersect(&b);
+        assert_eq!(and.word_len(), 5);
+        assert!(and.get(0));
+        assert!(and.get(1));
+        assert!(!and.get(2));
+        assert!(!and.get(uint::bits));
+    }
+}
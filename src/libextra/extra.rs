@@ -70,6 +70,32 @@ pub mod flatpipes;
 // Collections
 
 pub mod bitv;
+pub mod rle_bitv;
+pub mod ewah_bitv;
+pub mod bit_matrix;
+pub mod rs_bitv;
+pub mod wavelet_tree;
+pub mod sparse_bitv;
+pub mod atomic_bitv;
+pub mod enum_set;
+pub mod bit_io;
+pub mod veb_set;
+pub mod bit_deque;
+pub mod bitboard;
+pub mod bitv_intern;
+pub mod dataflow;
+pub mod persistent_bitv;
+pub mod quotient_filter;
+pub mod generational_map;
+pub mod id_allocator;
+pub mod bit_prio_queue;
+pub mod trie_int_map;
+pub mod histogram;
+pub mod morton;
+pub mod bit_sliced_index;
+pub mod bit_window;
+pub mod elias_fano;
+pub mod lru_cache;
 pub mod deque;
 pub mod fun_treemap;
 pub mod list;
@@ -0,0 +1,248 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A map whose keys are handed out by the map itself and carry a
+ * generation counter alongside the slot index. Freeing a slot bumps its
+ * generation, so a stale handle kept across a free/reuse cycle is
+ * detected and reads as absent instead of silently aliasing whatever
+ * value reused the index. Entity-component systems and resource pools
+ * otherwise layer this bookkeeping over `SmallIntMap` by hand.
+ */
+
+use std::uint;
+use std::util::replace;
+
+/// A handle into a GenerationalIntMap: a slot index plus the slot's
+/// generation at the time of insertion
+#[deriving(Clone, Eq)]
+pub struct GenKey {
+    priv index: uint,
+    priv generation: uint
+}
+
+impl GenKey {
+    /// The raw slot index; only meaningful to code that tracks
+    /// generations itself
+    pub fn index(&self) -> uint { self.index }
+}
+
+struct Slot<V> {
+    /// Incremented every time the slot is freed
+    generation: uint,
+    value: Option<V>
+}
+
+/// The generational map type
+pub struct GenerationalIntMap<V> {
+    priv slots: ~[Slot<V>],
+    /// Indices of slots whose values have been freed
+    priv free: ~[uint],
+    priv size: uint
+}
+
+impl<V> Container for GenerationalIntMap<V> {
+    /// Return the number of live values in the map
+    fn len(&self) -> uint { self.size }
+
+    /// Return true if the map contains no live values
+    fn is_empty(&self) -> bool { self.size == 0 }
+}
+
+impl<V> Mutable for GenerationalIntMap<V> {
+    /// Remove every value. All outstanding keys are invalidated.
+    fn clear(&mut self) {
+        self.free.clear();
+        for uint::range(0, self.slots.len()) |i| {
+            if self.slots[i].value.is_some() {
+                self.slots[i].value = None;
+                self.slots[i].generation += 1;
+            }
+            self.free.push(i);
+        }
+        self.size = 0;
+    }
+}
+
+impl<V> GenerationalIntMap<V> {
+    /// Create an empty GenerationalIntMap
+    pub fn new() -> GenerationalIntMap<V> {
+        GenerationalIntMap{slots: ~[], free: ~[], size: 0}
+    }
+
+    /// Store a value, returning the key that retrieves it
+    pub fn insert(&mut self, value: V) -> GenKey {
+        self.size += 1;
+        if self.free.is_empty() {
+            self.slots.push(Slot{generation: 0, value: Some(value)});
+            GenKey{index: self.slots.len() - 1, generation: 0}
+        } else {
+            let index = self.free.pop();
+            self.slots[index].value = Some(value);
+            GenKey{
+                index: index,
+                generation: self.slots[index].generation
+            }
+        }
+    }
+
+    /// Return true if `key` is still live
+    pub fn contains_key(&self, key: &GenKey) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// Return a reference to the value behind `key`, or None if the key
+    /// is stale or was never issued by this map
+    pub fn find<'a>(&'a self, key: &GenKey) -> Option<&'a V> {
+        if key.index >= self.slots.len() {
+            return None;
+        }
+        let slot = &self.slots[key.index];
+        if slot.generation != key.generation {
+            return None;
+        }
+        match slot.value {
+            Some(ref value) => Some(value),
+            None => None
+        }
+    }
+
+    /// Return a mutable reference to the value behind `key`
+    pub fn find_mut<'a>(&'a mut self, key: &GenKey) -> Option<&'a mut V> {
+        if key.index >= self.slots.len() {
+            return None;
+        }
+        let slot = &mut self.slots[key.index];
+        if slot.generation != key.generation {
+            return None;
+        }
+        match slot.value {
+            Some(ref mut value) => Some(value),
+            None => None
+        }
+    }
+
+    /// Free the slot behind `key`, returning its value. The slot's
+    /// generation is bumped, so `key` and any copies of it go stale.
+    pub fn pop(&mut self, key: &GenKey) -> Option<V> {
+        if !self.contains_key(key) {
+            return None;
+        }
+        let slot = &mut self.slots[key.index];
+        let value = replace(&mut slot.value, None);
+        slot.generation += 1;
+        self.free.push(key.index);
+        self.size -= 1;
+        value
+    }
+
+    /// Free the slot behind `key`. Return true if the key was live.
+    pub fn remove(&mut self, key: &GenKey) -> bool {
+        self.pop(key).is_some()
+    }
+
+    /// Visit every live key-value pair, in slot order
+    pub fn each<'a>(&'a self, it: &fn(GenKey, &'a V) -> bool) -> bool {
+        for self.slots.iter().enumerate().advance |(i, slot)| {
+            match slot.value {
+                Some(ref value) => {
+                    let key = GenKey{
+                        index: i,
+                        generation: slot.generation
+                    };
+                    if !it(key, value) {
+                        return false;
+                    }
+                }
+                None => ()
+            }
+        }
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_find() {
+        let mut m = GenerationalIntMap::new();
+        assert!(m.is_empty());
+        let a = m.insert(~"a");
+        let b = m.insert(~"b");
+        assert_eq!(m.len(), 2);
+        assert_eq!(m.find(&a), Some(&~"a"));
+        assert_eq!(m.find(&b), Some(&~"b"));
+        assert!(m.contains_key(&a));
+    }
+
+    #[test]
+    fn test_stale_keys_read_as_absent() {
+        let mut m = GenerationalIntMap::new();
+        let a = m.insert(10);
+        assert_eq!(m.pop(&a), Some(10));
+        assert_eq!(m.find(&a), None);
+
+        // the freed slot is reused, but the old handle stays dead
+        let b = m.insert(20);
+        assert_eq!(b.index(), a.index());
+        assert!(a != b);
+        assert_eq!(m.find(&a), None);
+        assert_eq!(m.find(&b), Some(&20));
+        assert!(!m.remove(&a));
+        assert_eq!(m.len(), 1);
+    }
+
+    #[test]
+    fn test_find_mut() {
+        let mut m = GenerationalIntMap::new();
+        let k = m.insert(1);
+        match m.find_mut(&k) {
+            Some(v) => *v = 2,
+            None => fail!()
+        }
+        assert_eq!(m.find(&k), Some(&2));
+        m.remove(&k);
+        assert!(m.find_mut(&k).is_none());
+    }
+
+    #[test]
+    fn test_each() {
+        let mut m = GenerationalIntMap::new();
+        let a = m.insert(1);
+        let _b = m.insert(2);
+        let c = m.insert(3);
+        assert!(m.remove(&a));
+        let mut values = ~[];
+        let mut keys_live = true;
+        for m.each |k, &v| {
+            values.push(v);
+            keys_live = keys_live && m.contains_key(&k);
+        }
+        assert_eq!(values, ~[2, 3]);
+        assert!(keys_live);
+        assert_eq!(m.find(&c), Some(&3));
+    }
+
+    #[test]
+    fn test_clear_invalidates_everything() {
+        let mut m = GenerationalIntMap::new();
+        let a = m.insert(1);
+        let b = m.insert(2);
+        m.clear();
+        assert!(m.is_empty());
+        assert_eq!(m.find(&a), None);
+        assert_eq!(m.find(&b), None);
+        let c = m.insert(3);
+        assert_eq!(m.find(&c), Some(&3));
+        assert_eq!(m.len(), 1);
+    }
+}
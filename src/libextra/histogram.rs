@@ -0,0 +1,235 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A bucketing histogram over uint samples, for benchmark and telemetry
+ * code that wants percentiles without retaining every sample. Buckets
+ * are delimited by a sorted vector of exclusive upper bounds, either
+ * fixed-width or caller-supplied; samples at or above the last bound
+ * land in an implicit overflow bucket. Two histograms with the same
+ * boundaries can be merged by summing their counts.
+ */
+
+use std::uint;
+use std::vec;
+
+/// The histogram type
+pub struct Histogram {
+    /// Exclusive upper bounds of the buckets, strictly increasing
+    priv bounds: ~[uint],
+    /// One count per bound, plus a final overflow count
+    priv counts: ~[uint],
+    /// The number of recorded samples
+    priv total: uint
+}
+
+impl Container for Histogram {
+    /// Return the number of recorded samples
+    fn len(&self) -> uint { self.total }
+
+    /// Return true if no samples have been recorded
+    fn is_empty(&self) -> bool { self.total == 0 }
+}
+
+impl Mutable for Histogram {
+    /// Discard all recorded samples, keeping the bucket boundaries
+    fn clear(&mut self) {
+        for self.counts.mut_iter().advance |c| { *c = 0; }
+        self.total = 0;
+    }
+}
+
+impl Histogram {
+    /// Create a histogram with `nbuckets` buckets of `width` values
+    /// each, covering `[0, width * nbuckets)`
+    pub fn with_width(width: uint, nbuckets: uint) -> Histogram {
+        assert!(width > 0 && nbuckets > 0);
+        Histogram::with_bounds(vec::from_fn(nbuckets, |i| width * (i + 1)))
+    }
+
+    /// Create a histogram from a strictly increasing vector of
+    /// exclusive bucket upper bounds
+    pub fn with_bounds(bounds: ~[uint]) -> Histogram {
+        assert!(!bounds.is_empty());
+        for uint::range(1, bounds.len()) |i| {
+            assert!(bounds[i - 1] < bounds[i]);
+        }
+        let nbuckets = bounds.len() + 1;
+        Histogram{
+            bounds: bounds,
+            counts: vec::from_elem(nbuckets, 0),
+            total: 0
+        }
+    }
+
+    /// The number of buckets, counting the overflow bucket
+    pub fn bucket_count(&self) -> uint { self.counts.len() }
+
+    /// The index of the bucket a value falls in
+    fn bucket_of(&self, value: uint) -> uint {
+        let mut lo = 0;
+        let mut hi = self.bounds.len();
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if value < self.bounds[mid] {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        lo
+    }
+
+    /// Record one sample
+    pub fn record(&mut self, value: uint) {
+        let bucket = self.bucket_of(value);
+        self.counts[bucket] += 1;
+        self.total += 1;
+    }
+
+    /// The number of samples recorded in the bucket holding `value`
+    pub fn count_at(&self, value: uint) -> uint {
+        self.counts[self.bucket_of(value)]
+    }
+
+    /// The smallest bucket upper bound such that at least `p` percent
+    /// of the samples fall at or below it, or None if the histogram is
+    /// empty. Samples in the overflow bucket report `uint::max_value`.
+    pub fn percentile(&self, p: uint) -> Option<uint> {
+        assert!(p <= 100);
+        if self.total == 0 {
+            return None;
+        }
+        let rank = uint::max(1, (p * self.total + 99) / 100);
+        let mut seen = 0;
+        for self.counts.iter().enumerate().advance |(i, &c)| {
+            seen += c;
+            if seen >= rank {
+                if i < self.bounds.len() {
+                    return Some(self.bounds[i] - 1);
+                } else {
+                    return Some(uint::max_value);
+                }
+            }
+        }
+        fail!("histogram counts disagree with total");
+    }
+
+    /// Add every sample of `other`, which must have the same bucket
+    /// boundaries
+    pub fn merge(&mut self, other: &Histogram) {
+        assert_eq!(self.bounds, other.bounds);
+        for uint::range(0, self.counts.len()) |i| {
+            self.counts[i] += other.counts[i];
+        }
+        self.total += other.total;
+    }
+
+    /// Visit each bucket as `(lower, limit, count)`, where the bucket
+    /// holds values in `[lower, limit)`; the overflow bucket reports a
+    /// limit of `uint::max_value`
+    pub fn each_bucket(&self, f: &fn(uint, uint, uint) -> bool) -> bool {
+        let mut lower = 0;
+        for self.counts.iter().enumerate().advance |(i, &c)| {
+            let limit = if i < self.bounds.len() {
+                self.bounds[i]
+            } else {
+                uint::max_value
+            };
+            if !f(lower, limit, c) {
+                return false;
+            }
+            lower = limit;
+        }
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::uint;
+
+    #[test]
+    fn test_fixed_width_buckets() {
+        let mut h = Histogram::with_width(10, 4);
+        assert!(h.is_empty());
+        h.record(0);
+        h.record(9);
+        h.record(10);
+        h.record(35);
+        h.record(40); // overflow
+        assert_eq!(h.len(), 5);
+        assert_eq!(h.count_at(5), 2);
+        assert_eq!(h.count_at(10), 1);
+        assert_eq!(h.count_at(20), 0);
+        assert_eq!(h.count_at(1000), 1);
+    }
+
+    #[test]
+    fn test_percentile() {
+        let mut h = Histogram::with_width(10, 10);
+        for uint::range(0, 100) |i| {
+            h.record(i);
+        }
+        assert_eq!(h.percentile(1), Some(9));
+        assert_eq!(h.percentile(50), Some(49));
+        assert_eq!(h.percentile(99), Some(99));
+        assert_eq!(h.percentile(100), Some(99));
+        h.record(5000);
+        assert_eq!(h.percentile(100), Some(uint::max_value));
+    }
+
+    #[test]
+    fn test_percentile_empty() {
+        let h = Histogram::with_width(1, 1);
+        assert_eq!(h.percentile(50), None);
+    }
+
+    #[test]
+    fn test_custom_bounds_and_each_bucket() {
+        let mut h = Histogram::with_bounds(~[1, 10, 100]);
+        assert_eq!(h.bucket_count(), 4);
+        h.record(0);
+        h.record(3);
+        h.record(99);
+        h.record(100);
+        let mut observed = ~[];
+        for h.each_bucket |lower, limit, count| {
+            observed.push((lower, limit, count));
+        }
+        assert_eq!(observed,
+                   ~[(0u, 1u, 1u), (1u, 10u, 1u), (10u, 100u, 1u),
+                     (100u, uint::max_value, 1u)]);
+    }
+
+    #[test]
+    fn test_merge_and_clear() {
+        let mut a = Histogram::with_width(10, 2);
+        let mut b = Histogram::with_width(10, 2);
+        a.record(1);
+        b.record(1);
+        b.record(15);
+        a.merge(&b);
+        assert_eq!(a.len(), 3);
+        assert_eq!(a.count_at(1), 2);
+        assert_eq!(a.count_at(15), 1);
+        a.clear();
+        assert!(a.is_empty());
+        assert_eq!(a.count_at(1), 0);
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_unsorted_bounds_fail() {
+        let _h = Histogram::with_bounds(~[10, 10, 20]);
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * An allocator of small integer IDs that always hands out the lowest
+ * free one, in the manner of a file descriptor table or slot map. One
+ * bit per ID tracks allocation; a word-level cursor remembers the lowest
+ * possibly-free word, so allocation skips over the densely allocated
+ * prefix instead of rescanning it bit by bit.
+ */
+
+use bitv::iterate_bits;
+
+use std::uint;
+
+/// The ID allocator type
+pub struct IdAllocator {
+    /// One bit per ID; set means allocated
+    priv storage: ~[uint],
+    /// Index of the first word that might contain a clear bit; every
+    /// word before it is known to be full
+    priv cursor: uint,
+    /// The number of allocated IDs
+    priv size: uint
+}
+
+/// The index of the lowest clear bit of a word with at least one
+fn first_zero(w: uint) -> uint {
+    let mut i = 0;
+    while w & (1 << i) != 0 { i += 1; }
+    i
+}
+
+impl IdAllocator {
+    /// Create an allocator with no IDs allocated
+    pub fn new() -> IdAllocator {
+        IdAllocator{storage: ~[0], cursor: 0, size: 0}
+    }
+
+    /// The number of currently allocated IDs
+    pub fn len(&self) -> uint { self.size }
+
+    /// Return true if no IDs are allocated
+    pub fn is_empty(&self) -> bool { self.size == 0 }
+
+    /// Allocate and return the lowest free ID
+    pub fn alloc(&mut self) -> uint {
+        let mut w = self.cursor;
+        while w < self.storage.len() && self.storage[w] == !0 {
+            w += 1;
+        }
+        if w == self.storage.len() {
+            self.storage.push(0);
+        }
+        let bit = first_zero(self.storage[w]);
+        self.storage[w] |= 1 << bit;
+        self.cursor = w;
+        self.size += 1;
+        w * uint::bits + bit
+    }
+
+    /// Return true if `id` is currently allocated
+    pub fn is_allocated(&self, id: uint) -> bool {
+        let w = id / uint::bits;
+        w < self.storage.len() &&
+            self.storage[w] & (1 << (id % uint::bits)) != 0
+    }
+
+    /// Release an ID so it can be handed out again. Return true if the
+    /// ID was allocated.
+    pub fn free(&mut self, id: uint) -> bool {
+        if !self.is_allocated(id) {
+            return false;
+        }
+        let w = id / uint::bits;
+        self.storage[w] &= !(1 << (id % uint::bits));
+        if w < self.cursor {
+            self.cursor = w;
+        }
+        self.size -= 1;
+        true
+    }
+
+    /// Visit every allocated ID in increasing order
+    pub fn each_allocated(&self, f: &fn(uint) -> bool) -> bool {
+        for self.storage.iter().enumerate().advance |(i, &w)| {
+            if !iterate_bits(i * uint::bits, w, f) {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::uint;
+
+    #[test]
+    fn test_allocates_lowest_free() {
+        let mut ids = IdAllocator::new();
+        assert_eq!(ids.alloc(), 0);
+        assert_eq!(ids.alloc(), 1);
+        assert_eq!(ids.alloc(), 2);
+        assert_eq!(ids.len(), 3);
+        assert!(ids.free(1));
+        assert_eq!(ids.alloc(), 1);
+        assert_eq!(ids.alloc(), 3);
+    }
+
+    #[test]
+    fn test_free_and_reuse_across_words() {
+        let mut ids = IdAllocator::new();
+        for uint::range(0, 3 * uint::bits) |i| {
+            assert_eq!(ids.alloc(), i);
+        }
+        // poke a hole in the first word, well behind the cursor
+        assert!(ids.free(5));
+        assert!(!ids.is_allocated(5));
+        assert_eq!(ids.alloc(), 5);
+        // with the hole plugged, allocation resumes at the frontier
+        assert_eq!(ids.alloc(), 3 * uint::bits);
+    }
+
+    #[test]
+    fn test_free_unallocated() {
+        let mut ids = IdAllocator::new();
+        assert!(!ids.free(10));
+        assert!(!ids.free(10_000));
+        let id = ids.alloc();
+        assert!(ids.free(id));
+        assert!(!ids.free(id));
+        assert!(ids.is_empty());
+    }
+
+    #[test]
+    fn test_each_allocated() {
+        let mut ids = IdAllocator::new();
+        for 5u.times {
+            ids.alloc();
+        }
+        assert!(ids.free(2));
+        let mut observed = ~[];
+        for ids.each_allocated |id| {
+            observed.push(id);
+        }
+        assert_eq!(observed, ~[0u, 1, 3, 4]);
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * An LRU cache keyed by small integers. A `SmallIntMap` gives O(1)
+ * slot lookup while an intrusive recency list threaded through the
+ * entries orders them from most to least recently used; `get` and
+ * `insert` refresh an entry's recency and inserting past capacity
+ * evicts the least recently used entry, optionally handing it to an
+ * eviction callback.
+ */
+
+use smallintmap::SmallIntMap;
+
+/// A cache entry, threaded on the recency list by key
+struct Entry<V> {
+    value: V,
+    /// The next more recently used key
+    prev: Option<uint>,
+    /// The next less recently used key
+    next: Option<uint>
+}
+
+/// The LRU cache type
+pub struct SmallIntLruCache<V> {
+    priv map: SmallIntMap<Entry<V>>,
+    /// The most entries kept before eviction
+    priv cap: uint,
+    /// The most recently used key
+    priv head: Option<uint>,
+    /// The least recently used key
+    priv tail: Option<uint>,
+    /// Called with each entry evicted by a capacity overflow
+    priv on_evict: Option<@fn(uint, V)>
+}
+
+impl<V> Container for SmallIntLruCache<V> {
+    /// Return the number of cached entries
+    fn len(&self) -> uint { self.map.len() }
+
+    /// Return true if the cache holds no entries
+    fn is_empty(&self) -> bool { self.map.is_empty() }
+}
+
+impl<V> Mutable for SmallIntLruCache<V> {
+    /// Drop every entry. The eviction callback is not run.
+    fn clear(&mut self) {
+        self.map.clear();
+        self.head = None;
+        self.tail = None;
+    }
+}
+
+impl<V> SmallIntLruCache<V> {
+    /// Create a cache holding at most `capacity` entries
+    pub fn new(capacity: uint) -> SmallIntLruCache<V> {
+        assert!(capacity > 0);
+        SmallIntLruCache{
+            map: SmallIntMap::new(),
+            cap: capacity,
+            head: None,
+            tail: None,
+            on_evict: None
+        }
+    }
+
+    /// Create a cache that hands entries evicted by capacity overflow
+    /// to `callback`
+    pub fn with_eviction_callback(capacity: uint,
+                                  callback: @fn(uint, V))
+                                  -> SmallIntLruCache<V> {
+        let mut cache = SmallIntLruCache::new(capacity);
+        cache.on_evict = Some(callback);
+        cache
+    }
+
+    /// The most entries the cache keeps
+    pub fn capacity(&self) -> uint { self.cap }
+
+    /// Take a key out of the recency list
+    fn unlink(&mut self, key: uint) {
+        let (prev, next) = {
+            let e = self.map.find(&key).unwrap();
+            (e.prev, e.next)
+        };
+        match prev {
+            Some(p) => self.map.find_mut(&p).unwrap().next = next,
+            None => self.head = next
+        }
+        match next {
+            Some(n) => self.map.find_mut(&n).unwrap().prev = prev,
+            None => self.tail = prev
+        }
+    }
+
+    /// Put a key at the most recently used end of the recency list
+    fn link_front(&mut self, key: uint) {
+        let old_head = self.head;
+        match old_head {
+            Some(h) => self.map.find_mut(&h).unwrap().prev = Some(key),
+            None => self.tail = Some(key)
+        }
+        {
+            let e = self.map.find_mut(&key).unwrap();
+            e.prev = None;
+            e.next = old_head;
+        }
+        self.head = Some(key);
+    }
+
+    /// Insert a value, refreshing the key's recency and evicting the
+    /// least recently used entry if the cache is over capacity. Return
+    /// true if the key was not already cached.
+    pub fn insert(&mut self, key: uint, value: V) -> bool {
+        if self.map.contains_key(&key) {
+            self.map.find_mut(&key).unwrap().value = value;
+            self.unlink(key);
+            self.link_front(key);
+            return false;
+        }
+        self.map.insert(key, Entry{value: value, prev: None, next: None});
+        self.link_front(key);
+        if self.map.len() > self.cap {
+            let lru = self.tail.unwrap();
+            self.unlink(lru);
+            let entry = self.map.pop(&lru).unwrap();
+            match self.on_evict {
+                Some(cb) => cb(lru, entry.value),
+                None => ()
+            }
+        }
+        true
+    }
+
+    /// Look a key up and mark it most recently used
+    pub fn get<'a>(&'a mut self, key: uint) -> Option<&'a V> {
+        if !self.map.contains_key(&key) {
+            return None;
+        }
+        self.unlink(key);
+        self.link_front(key);
+        match self.map.find(&key) {
+            Some(e) => Some(&e.value),
+            None => None
+        }
+    }
+
+    /// Look a key up without refreshing its recency
+    pub fn peek<'a>(&'a self, key: uint) -> Option<&'a V> {
+        match self.map.find(&key) {
+            Some(e) => Some(&e.value),
+            None => None
+        }
+    }
+
+    /// Remove an entry, bypassing the eviction callback
+    pub fn pop(&mut self, key: &uint) -> Option<V> {
+        if !self.map.contains_key(key) {
+            return None;
+        }
+        self.unlink(*key);
+        match self.map.pop(key) {
+            Some(entry) => Some(entry.value),
+            None => None
+        }
+    }
+
+    /// Visit the entries from most to least recently used
+    pub fn each_recent<'a>(&'a self,
+                           f: &fn(uint, &'a V) -> bool) -> bool {
+        let mut cursor = self.head;
+        loop {
+            let key = match cursor {
+                None => return true,
+                Some(key) => key
+            };
+            let e = self.map.find(&key).unwrap();
+            if !f(key, &e.value) {
+                return false;
+            }
+            cursor = e.next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = SmallIntLruCache::new(2);
+        assert!(cache.insert(1, ~"one"));
+        assert!(cache.insert(2, ~"two"));
+        assert!(cache.insert(3, ~"three"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.peek(1).is_none());
+        assert_eq!(cache.peek(2), Some(&~"two"));
+        assert_eq!(cache.peek(3), Some(&~"three"));
+    }
+
+    #[test]
+    fn test_get_refreshes_recency() {
+        let mut cache = SmallIntLruCache::new(2);
+        cache.insert(1, 'a');
+        cache.insert(2, 'b');
+        assert_eq!(cache.get(1), Some(&'a'));
+        // 2 is now least recently used
+        cache.insert(3, 'c');
+        assert!(cache.peek(2).is_none());
+        assert_eq!(cache.peek(1), Some(&'a'));
+    }
+
+    #[test]
+    fn test_reinsert_refreshes_and_replaces() {
+        let mut cache = SmallIntLruCache::new(2);
+        cache.insert(1, 10);
+        cache.insert(2, 20);
+        assert!(!cache.insert(1, 11));
+        cache.insert(3, 30);
+        assert!(cache.peek(2).is_none());
+        assert_eq!(cache.peek(1), Some(&11));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_eviction_callback() {
+        let log = @mut ~[];
+        let mut cache = do SmallIntLruCache::with_eviction_callback(2)
+                |key, value| {
+            log.push((key, value));
+        };
+        cache.insert(1, ~"a");
+        cache.insert(2, ~"b");
+        cache.insert(3, ~"c");
+        cache.insert(4, ~"d");
+        assert_eq!(*log, ~[(1u, ~"a"), (2u, ~"b")]);
+        // pop bypasses the callback
+        assert_eq!(cache.pop(&3), Some(~"c"));
+        assert_eq!(log.len(), 2);
+    }
+
+    #[test]
+    fn test_each_recent() {
+        let mut cache = SmallIntLruCache::new(3);
+        cache.insert(1, 'a');
+        cache.insert(2, 'b');
+        cache.insert(3, 'c');
+        cache.get(1);
+        let mut order = ~[];
+        for cache.each_recent |key, _| {
+            order.push(key);
+        }
+        assert_eq!(order, ~[1u, 3, 2]);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut cache = SmallIntLruCache::new(2);
+        cache.insert(1, 1);
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(cache.peek(1).is_none());
+        cache.insert(2, 2);
+        assert_eq!(cache.peek(2), Some(&2));
+    }
+}
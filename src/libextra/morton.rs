@@ -0,0 +1,215 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * Morton (Z-order) encoding: interleaving the bits of two or three
+ * coordinates into one key so that nearby points land near each other
+ * in key order, as spatial indexes want. Word-sized codes use the
+ * parallel scatter/gather shift sequences; coordinates too wide to
+ * share a uint can be interleaved into a `Bitv` instead.
+ */
+
+use bitv::Bitv;
+
+use std::uint;
+
+/// Spread the low 32 bits of a word across the even bit positions
+pub fn scatter_2(x: u64) -> u64 {
+    let mut x = x & 0x00000000ffffffff;
+    x = (x | (x << 16)) & 0x0000ffff0000ffff;
+    x = (x | (x << 8))  & 0x00ff00ff00ff00ff;
+    x = (x | (x << 4))  & 0x0f0f0f0f0f0f0f0f;
+    x = (x | (x << 2))  & 0x3333333333333333;
+    x = (x | (x << 1))  & 0x5555555555555555;
+    x
+}
+
+/// Collect the even bit positions of a word into its low 32 bits
+pub fn gather_2(x: u64) -> u64 {
+    let mut x = x & 0x5555555555555555;
+    x = (x | (x >> 1))  & 0x3333333333333333;
+    x = (x | (x >> 2))  & 0x0f0f0f0f0f0f0f0f;
+    x = (x | (x >> 4))  & 0x00ff00ff00ff00ff;
+    x = (x | (x >> 8))  & 0x0000ffff0000ffff;
+    x = (x | (x >> 16)) & 0x00000000ffffffff;
+    x
+}
+
+/// Spread the low 21 bits of a word across every third bit position
+fn scatter_3(x: u64) -> u64 {
+    let mut x = x & 0x00000000001fffff;
+    x = (x | (x << 32)) & 0x001f00000000ffff;
+    x = (x | (x << 16)) & 0x001f0000ff0000ff;
+    x = (x | (x << 8))  & 0x100f00f00f00f00f;
+    x = (x | (x << 4))  & 0x10c30c30c30c30c3;
+    x = (x | (x << 2))  & 0x1249249249249249;
+    x
+}
+
+/// Collect every third bit position of a word into its low 21 bits
+fn gather_3(x: u64) -> u64 {
+    let mut x = x & 0x1249249249249249;
+    x = (x | (x >> 2))  & 0x10c30c30c30c30c3;
+    x = (x | (x >> 4))  & 0x100f00f00f00f00f;
+    x = (x | (x >> 8))  & 0x001f0000ff0000ff;
+    x = (x | (x >> 16)) & 0x001f00000000ffff;
+    x = (x | (x >> 32)) & 0x00000000001fffff;
+    x
+}
+
+/// Interleave two coordinates into one Morton code, x in the even bit
+/// positions and y in the odd ones. Each coordinate must fit in half a
+/// word.
+pub fn encode_2d(x: uint, y: uint) -> uint {
+    assert!(x < (1 << (uint::bits / 2)));
+    assert!(y < (1 << (uint::bits / 2)));
+    (scatter_2(x as u64) | (scatter_2(y as u64) << 1)) as uint
+}
+
+/// Recover the coordinates interleaved by `encode_2d`
+pub fn decode_2d(code: uint) -> (uint, uint) {
+    (gather_2(code as u64) as uint,
+     gather_2((code as u64) >> 1) as uint)
+}
+
+/// Interleave three coordinates into one Morton code. Each coordinate
+/// must fit in a third of a word.
+pub fn encode_3d(x: uint, y: uint, z: uint) -> uint {
+    assert!(x < (1 << (uint::bits / 3)));
+    assert!(y < (1 << (uint::bits / 3)));
+    assert!(z < (1 << (uint::bits / 3)));
+    (scatter_3(x as u64)
+     | (scatter_3(y as u64) << 1)
+     | (scatter_3(z as u64) << 2)) as uint
+}
+
+/// Recover the coordinates interleaved by `encode_3d`
+pub fn decode_3d(code: uint) -> (uint, uint, uint) {
+    (gather_3(code as u64) as uint,
+     gather_3((code as u64) >> 1) as uint,
+     gather_3((code as u64) >> 2) as uint)
+}
+
+/// Interleave equal-length coordinate bit vectors into one Bitv, for
+/// keys too wide to share a uint. Bit `i` of coordinate `c` lands at
+/// position `i * coords.len() + c` of the result.
+pub fn interleave_bitv(coords: &[Bitv]) -> Bitv {
+    assert!(!coords.is_empty());
+    let k = coords.len();
+    let nbits = coords[0].len();
+    for coords.iter().advance |c| {
+        assert_eq!(c.len(), nbits);
+    }
+    let mut code = Bitv::new(nbits * k, false);
+    for uint::range(0, nbits) |i| {
+        for uint::range(0, k) |c| {
+            code.set(i * k + c, coords[c][i]);
+        }
+    }
+    code
+}
+
+/// Split a Morton code produced by `interleave_bitv` back into its `k`
+/// coordinate bit vectors
+pub fn deinterleave_bitv(code: &Bitv, k: uint) -> ~[Bitv] {
+    assert!(k > 0);
+    assert_eq!(code.len() % k, 0);
+    let nbits = code.len() / k;
+    let mut coords = ~[];
+    for uint::range(0, k) |c| {
+        let mut coord = Bitv::new(nbits, false);
+        for uint::range(0, nbits) |i| {
+            coord.set(i, code[i * k + c]);
+        }
+        coords.push(coord);
+    }
+    coords
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitv;
+    use bitv::Bitv;
+
+    use std::uint;
+
+    #[test]
+    fn test_encode_2d() {
+        assert_eq!(encode_2d(0, 0), 0);
+        assert_eq!(encode_2d(1, 0), 0b01);
+        assert_eq!(encode_2d(0, 1), 0b10);
+        assert_eq!(encode_2d(0b11, 0b00), 0b0101);
+        assert_eq!(encode_2d(0b10, 0b11), 0b1110);
+        assert_eq!(encode_2d(5, 9), 0b10010011);
+    }
+
+    #[test]
+    fn test_2d_roundtrip() {
+        let limit = 1 << (uint::bits / 2);
+        let coords = [0, 1, 2, 3, 1000, limit - 1];
+        for coords.iter().advance |&x| {
+            for coords.iter().advance |&y| {
+                assert_eq!(decode_2d(encode_2d(x, y)), (x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_3d() {
+        assert_eq!(encode_3d(1, 0, 0), 0b001);
+        assert_eq!(encode_3d(0, 1, 0), 0b010);
+        assert_eq!(encode_3d(0, 0, 1), 0b100);
+        assert_eq!(encode_3d(0b11, 0b01, 0b10), 0b101011);
+    }
+
+    #[test]
+    fn test_3d_roundtrip() {
+        let limit = 1 << (uint::bits / 3);
+        let coords = [0, 1, 7, 100, limit - 1];
+        for coords.iter().advance |&x| {
+            for coords.iter().advance |&y| {
+                for coords.iter().advance |&z| {
+                    assert_eq!(decode_3d(encode_3d(x, y, z)), (x, y, z));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_interleave_bitv() {
+        let x = bitv::from_bools([true, false, true]);
+        let y = bitv::from_bools([false, true, true]);
+        let code = interleave_bitv([x.clone(), y.clone()]);
+        assert!(code.eq_vec(~[1u, 0u, 0u, 1u, 1u, 1u]));
+        let coords = deinterleave_bitv(&code, 2);
+        assert_eq!(coords.len(), 2);
+        assert_eq!(coords[0].to_bools(), x.to_bools());
+        assert_eq!(coords[1].to_bools(), y.to_bools());
+    }
+
+    #[test]
+    fn test_interleave_wide() {
+        // three words per coordinate: too wide for a uint code
+        let mut x = Bitv::new(3 * uint::bits, false);
+        x.set(0, true);
+        x.set(2 * uint::bits + 7, true);
+        let y = Bitv::new(3 * uint::bits, true);
+        let coords = deinterleave_bitv(&interleave_bitv([x.clone(), y]), 2);
+        assert_eq!(coords[0].to_bools(), x.to_bools());
+        assert_eq!(coords[1].to_bools(), Bitv::new(3 * uint::bits, true).to_bools());
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_interleave_length_mismatch() {
+        let _ = interleave_bitv([Bitv::new(4, false), Bitv::new(5, false)]);
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A persistent bitset. Insert and remove leave the receiver untouched
+ * and return a new version; the two versions share every word block the
+ * operation did not touch, via a 32-ary trie of managed nodes in the
+ * style of `fun_treemap`. Snapshot-heavy algorithms (backtracking
+ * search, versioned analyses) can keep thousands of versions alive at a
+ * cost of one O(log n) path copy per step instead of a full bitmap copy.
+ */
+
+use bitv::iterate_bits;
+
+use std::uint;
+use std::vec;
+
+/// The trie branching factor
+static FANOUT: uint = 32;
+
+/// The number of index bits consumed per trie level
+static FANOUT_BITS: uint = 5;
+
+enum Node {
+    /// An entirely empty subtree, of whatever depth its context implies;
+    /// sharing one nullary variant is what keeps empty regions free
+    EmptyNode,
+    /// One word of bits; only ever found at depth zero
+    LeafNode(uint),
+    /// An interior node with FANOUT children
+    BranchNode(~[@Node])
+}
+
+/// The persistent bitset type. Cloning a version is a pointer copy.
+#[deriving(Clone)]
+pub struct PersistentBitvSet {
+    priv root: @Node,
+    /// The number of branch levels above the leaves
+    priv depth: uint,
+    /// The number of elements in this version
+    priv size: uint
+}
+
+/// The number of bits a subtree rooted at `depth` spans
+fn span(depth: uint) -> uint {
+    uint::bits << (FANOUT_BITS * depth)
+}
+
+fn node_contains(node: @Node, depth: uint, bit: uint) -> bool {
+    match *node {
+        EmptyNode => false,
+        LeafNode(w) => w & (1 << bit) != 0,
+        BranchNode(ref children) => {
+            let child_span = span(depth - 1);
+            node_contains(children[bit / child_span], depth - 1,
+                          bit % child_span)
+        }
+    }
+}
+
+/// The heart of the structure: rebuild the path from the root down to
+/// one bit, sharing every node off the path with the original
+fn with_bit(node: @Node, depth: uint, bit: uint, on: bool) -> @Node {
+    match *node {
+        EmptyNode => {
+            if !on {
+                return node;
+            }
+            if depth == 0 {
+                @LeafNode(1 << bit)
+            } else {
+                let child_span = span(depth - 1);
+                let mut children = vec::from_elem(FANOUT, @EmptyNode);
+                children[bit / child_span] =
+                    with_bit(@EmptyNode, depth - 1, bit % child_span, on);
+                @BranchNode(children)
+            }
+        }
+        LeafNode(w) => {
+            let flag = 1 << bit;
+            let new = if on { w | flag } else { w & !flag };
+            if new == 0 { @EmptyNode } else { @LeafNode(new) }
+        }
+        BranchNode(ref children) => {
+            let child_span = span(depth - 1);
+            let i = bit / child_span;
+            let mut new_children = children.to_owned();
+            new_children[i] = with_bit(children[i], depth - 1,
+                                       bit % child_span, on);
+            if !on {
+                let mut all_empty = true;
+                for new_children.iter().advance |c| {
+                    match **c {
+                        EmptyNode => (),
+                        _ => { all_empty = false; }
+                    }
+                }
+                if all_empty {
+                    return @EmptyNode;
+                }
+            }
+            @BranchNode(new_children)
+        }
+    }
+}
+
+fn node_each(node: @Node, depth: uint, base: uint,
+             f: &fn(&uint) -> bool) -> bool {
+    match *node {
+        EmptyNode => true,
+        LeafNode(w) => iterate_bits(base, w, |b| f(&b)),
+        BranchNode(ref children) => {
+            let child_span = span(depth - 1);
+            for children.iter().enumerate().advance |(i, &c)| {
+                if !node_each(c, depth - 1, base + i * child_span, f) {
+                    return false;
+                }
+            }
+            true
+        }
+    }
+}
+
+impl PersistentBitvSet {
+    /// Create an empty version
+    pub fn new() -> PersistentBitvSet {
+        PersistentBitvSet{root: @EmptyNode, depth: 0, size: 0}
+    }
+
+    /// Return the number of elements in this version
+    pub fn len(&self) -> uint { self.size }
+
+    /// Return true if this version contains no elements
+    pub fn is_empty(&self) -> bool { self.size == 0 }
+
+    /// Return true if this version contains a value
+    pub fn contains(&self, value: &uint) -> bool {
+        if *value >= span(self.depth) {
+            false
+        } else {
+            node_contains(self.root, self.depth, *value)
+        }
+    }
+
+    /// Return a new version that also contains `value`, sharing all
+    /// untouched structure with `self`
+    pub fn insert(&self, value: uint) -> PersistentBitvSet {
+        let mut root = self.root;
+        let mut depth = self.depth;
+        while value >= span(depth) {
+            // deepen the trie; the old root becomes child zero
+            root = match *root {
+                EmptyNode => @EmptyNode,
+                _ => {
+                    let mut children = vec::from_elem(FANOUT, @EmptyNode);
+                    children[0] = root;
+                    @BranchNode(children)
+                }
+            };
+            depth += 1;
+        }
+        if node_contains(root, depth, value) {
+            return self.clone();
+        }
+        PersistentBitvSet{
+            root: with_bit(root, depth, value, true),
+            depth: depth,
+            size: self.size + 1
+        }
+    }
+
+    /// Return a new version without `value`, sharing all untouched
+    /// structure with `self`
+    pub fn remove(&self, value: &uint) -> PersistentBitvSet {
+        if !self.contains(value) {
+            return self.clone();
+        }
+        PersistentBitvSet{
+            root: with_bit(self.root, self.depth, *value, false),
+            depth: self.depth,
+            size: self.size - 1
+        }
+    }
+
+    /// Visit all values in this version, in order
+    pub fn each(&self, f: &fn(&uint) -> bool) -> bool {
+        node_each(self.root, self.depth, 0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_are_independent() {
+        let v0 = PersistentBitvSet::new();
+        let v1 = v0.insert(10);
+        let v2 = v1.insert(2000);
+        let v3 = v2.remove(&10);
+
+        assert!(v0.is_empty());
+        assert!(!v0.contains(&10));
+
+        assert_eq!(v1.len(), 1);
+        assert!(v1.contains(&10));
+        assert!(!v1.contains(&2000));
+
+        assert_eq!(v2.len(), 2);
+        assert!(v2.contains(&10));
+        assert!(v2.contains(&2000));
+
+        assert_eq!(v3.len(), 1);
+        assert!(!v3.contains(&10));
+        assert!(v3.contains(&2000));
+    }
+
+    #[test]
+    fn test_insert_existing_is_identity() {
+        let v1 = PersistentBitvSet::new().insert(7);
+        let v2 = v1.insert(7);
+        assert_eq!(v2.len(), 1);
+        let v3 = v1.remove(&100);
+        assert_eq!(v3.len(), 1);
+        assert!(v3.contains(&7));
+    }
+
+    #[test]
+    fn test_large_sparse_values() {
+        let v = PersistentBitvSet::new()
+            .insert(0)
+            .insert(1_000_000)
+            .insert(33_000_000);
+        assert_eq!(v.len(), 3);
+        assert!(v.contains(&0));
+        assert!(v.contains(&1_000_000));
+        assert!(v.contains(&33_000_000));
+        assert!(!v.contains(&999_999));
+        assert!(!v.contains(&1_000_000_000));
+    }
+
+    #[test]
+    fn test_each_in_order() {
+        let v = PersistentBitvSet::new()
+            .insert(500_000)
+            .insert(3)
+            .insert(64)
+            .insert(65);
+        let mut observed = ~[];
+        for v.each |&b| {
+            observed.push(b);
+        }
+        assert_eq!(observed, ~[3u, 64, 65, 500_000]);
+    }
+
+    #[test]
+    fn test_remove_collapses_to_empty() {
+        let v1 = PersistentBitvSet::new().insert(100_000);
+        let v2 = v1.remove(&100_000);
+        assert!(v2.is_empty());
+        let mut visited = 0;
+        for v2.each |_| {
+            visited += 1;
+        }
+        assert_eq!(visited, 0);
+    }
+}
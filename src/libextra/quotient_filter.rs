@@ -0,0 +1,557 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A quotient filter: an approximate-membership structure in the spirit
+ * of a Bloom filter, but storing each element's fingerprint in a single
+ * hash-table-like slot array. A fingerprint of q+r bits is split into a
+ * q-bit quotient (the canonical slot) and an r-bit remainder (stored in
+ * the slot, packed at r bits each); collisions are resolved with linear
+ * probing plus three metadata bits per slot that let the original slot
+ * of every remainder be reconstructed. Unlike a Bloom filter it
+ * supports deletion, resizing to a larger table, merging, and its
+ * probes touch one contiguous cluster, which is kinder to caches and
+ * disks.
+ */
+
+use std::uint;
+use std::vec;
+
+/// The quotient filter type
+pub struct QuotientFilter {
+    /// Number of quotient bits; the table has 2^qbits slots
+    priv qbits: uint,
+    /// Number of remainder bits stored per slot
+    priv rbits: uint,
+    /// Whether each canonical slot has any fingerprints in the table
+    priv occupied: ~[uint],
+    /// Whether each slot continues the run started in an earlier slot
+    priv continuation: ~[uint],
+    /// Whether the remainder in each slot is displaced from its
+    /// canonical slot
+    priv shifted: ~[uint],
+    /// The remainders, packed at `rbits` bits each
+    priv remainders: ~[uint],
+    /// The number of fingerprints stored
+    priv size: uint
+}
+
+/// Scramble a key into a fingerprint-sized hash (the 64-bit finalizer
+/// from MurmurHash3)
+fn hash_key(key: uint) -> uint {
+    let mut k = key as u64;
+    k = k ^ (k >> 33);
+    k = k * 0xff51afd7ed558ccd;
+    k = k ^ (k >> 33);
+    k = k * 0xc4ceb9fe1a85ec53;
+    k = k ^ (k >> 33);
+    k as uint
+}
+
+fn get_bit(words: &[uint], i: uint) -> bool {
+    words[i / uint::bits] & (1 << (i % uint::bits)) != 0
+}
+
+fn set_bit(words: &mut [uint], i: uint, value: bool) {
+    let flag = 1 << (i % uint::bits);
+    if value {
+        words[i / uint::bits] |= flag;
+    } else {
+        words[i / uint::bits] &= !flag;
+    }
+}
+
+/// Read element `i` of an array packed at `width` bits per element
+fn get_packed(words: &[uint], width: uint, i: uint) -> uint {
+    let bit = i * width;
+    let w = bit / uint::bits;
+    let o = bit % uint::bits;
+    let mask = (1 << width) - 1;
+    if o + width <= uint::bits {
+        (words[w] >> o) & mask
+    } else {
+        ((words[w] >> o) | (words[w + 1] << (uint::bits - o))) & mask
+    }
+}
+
+/// Write element `i` of an array packed at `width` bits per element
+fn set_packed(words: &mut [uint], width: uint, i: uint, value: uint) {
+    let bit = i * width;
+    let w = bit / uint::bits;
+    let o = bit % uint::bits;
+    if o + width <= uint::bits {
+        let mask = ((1 << width) - 1) << o;
+        words[w] = (words[w] & !mask) | (value << o);
+    } else {
+        let lo = uint::bits - o;
+        let lo_mask = ((1 << lo) - 1) << o;
+        words[w] = (words[w] & !lo_mask) | (value << o);
+        let hi_mask = (1 << (width - lo)) - 1;
+        words[w + 1] = (words[w + 1] & !hi_mask) | (value >> lo);
+    }
+}
+
+impl QuotientFilter {
+    /// Create an empty filter with 2^qbits slots holding rbits-bit
+    /// remainders. The false positive rate is roughly size / 2^(q+r).
+    pub fn new(qbits: uint, rbits: uint) -> QuotientFilter {
+        assert!(qbits >= 1 && rbits >= 1);
+        assert!(qbits + rbits <= uint::bits);
+        let nslots = 1 << qbits;
+        let meta_words = uint::div_ceil(nslots, uint::bits);
+        let rem_words = uint::div_ceil(nslots * rbits, uint::bits) + 1;
+        QuotientFilter{
+            qbits: qbits,
+            rbits: rbits,
+            occupied: vec::from_elem(meta_words, 0),
+            continuation: vec::from_elem(meta_words, 0),
+            shifted: vec::from_elem(meta_words, 0),
+            remainders: vec::from_elem(rem_words, 0),
+            size: 0
+        }
+    }
+
+    /// The number of fingerprints stored
+    pub fn len(&self) -> uint { self.size }
+
+    /// Return true if the filter stores no fingerprints
+    pub fn is_empty(&self) -> bool { self.size == 0 }
+
+    /// The number of slots in the table
+    pub fn capacity(&self) -> uint { 1 << self.qbits }
+
+    /// The number of bits in a fingerprint
+    pub fn fingerprint_bits(&self) -> uint { self.qbits + self.rbits }
+
+    #[inline]
+    fn next_slot(&self, i: uint) -> uint {
+        (i + 1) & (self.capacity() - 1)
+    }
+
+    #[inline]
+    fn prev_slot(&self, i: uint) -> uint {
+        (i + self.capacity() - 1) & (self.capacity() - 1)
+    }
+
+    #[inline]
+    fn is_occupied(&self, i: uint) -> bool { get_bit(self.occupied, i) }
+
+    #[inline]
+    fn is_continuation(&self, i: uint) -> bool {
+        get_bit(self.continuation, i)
+    }
+
+    #[inline]
+    fn is_shifted(&self, i: uint) -> bool { get_bit(self.shifted, i) }
+
+    /// A slot holds no remainder exactly when all three metadata bits
+    /// are clear
+    #[inline]
+    fn slot_empty(&self, i: uint) -> bool {
+        !self.is_occupied(i) && !self.is_continuation(i) &&
+            !self.is_shifted(i)
+    }
+
+    #[inline]
+    fn get_rem(&self, i: uint) -> uint {
+        get_packed(self.remainders, self.rbits, i)
+    }
+
+    #[inline]
+    fn set_rem(&mut self, i: uint, value: uint) {
+        set_packed(self.remainders, self.rbits, i, value);
+    }
+
+    /// Split a fingerprint into its quotient and remainder
+    fn split(&self, fp: uint) -> (uint, uint) {
+        let fp = fp & ((1 << (self.qbits + self.rbits)) - 1);
+        (fp >> self.rbits, fp & ((1 << self.rbits) - 1))
+    }
+
+    /// Find the slot where the run for canonical slot `q` starts (or
+    /// would start). `q` must lie inside a cluster.
+    fn run_start(&self, q: uint) -> uint {
+        // walk left to the start of the cluster
+        let mut b = q;
+        while self.is_shifted(b) {
+            b = self.prev_slot(b);
+        }
+        // skip one run for every occupied canonical slot before q
+        let mut s = b;
+        while b != q {
+            loop {
+                s = self.next_slot(s);
+                if !self.is_continuation(s) { break; }
+            }
+            loop {
+                b = self.next_slot(b);
+                if self.is_occupied(b) { break; }
+            }
+        }
+        s
+    }
+
+    /// Return true if the filter may contain a fingerprint
+    pub fn contains_fingerprint(&self, fp: uint) -> bool {
+        let (fq, fr) = self.split(fp);
+        if !self.is_occupied(fq) {
+            return false;
+        }
+        let mut s = self.run_start(fq);
+        loop {
+            if self.get_rem(s) == fr {
+                return true;
+            }
+            s = self.next_slot(s);
+            if !self.is_continuation(s) {
+                return false;
+            }
+        }
+    }
+
+    /// Insert a fingerprint. Return true if it was not already present.
+    /// Fails if the table is full; keep the load factor below one.
+    pub fn insert_fingerprint(&mut self, fp: uint) -> bool {
+        if self.size == self.capacity() {
+            fail!("quotient filter is full");
+        }
+        let (fq, fr) = self.split(fp);
+        if self.slot_empty(fq) {
+            // the canonical slot is free: the common, fast case
+            self.set_rem(fq, fr);
+            set_bit(self.occupied, fq, true);
+            self.size += 1;
+            return true;
+        }
+        let extend = self.is_occupied(fq);
+        set_bit(self.occupied, fq, true);
+        let start = self.run_start(fq);
+        let mut s = start;
+        let mut new_is_cont = false;
+        if extend {
+            // find the sorted position of fr within the existing run
+            loop {
+                let rem = self.get_rem(s);
+                if rem == fr {
+                    return false;
+                }
+                if rem > fr {
+                    break;
+                }
+                s = self.next_slot(s);
+                if !self.is_continuation(s) {
+                    break;
+                }
+            }
+            new_is_cont = s != start;
+        }
+        // place the new entry at s and push everything after it one
+        // slot to the right
+        let mut push_rem = fr;
+        let mut push_cont = new_is_cont;
+        let mut displacing_head = extend && s == start;
+        let mut first = true;
+        let mut i = s;
+        loop {
+            let empty = self.slot_empty(i);
+            let old_rem = self.get_rem(i);
+            let old_cont = self.is_continuation(i);
+            self.set_rem(i, push_rem);
+            set_bit(self.continuation, i, push_cont);
+            set_bit(self.shifted, i, if first { i != fq } else { true });
+            if empty {
+                break;
+            }
+            push_rem = old_rem;
+            // a run head displaced by a new head becomes a continuation
+            push_cont = old_cont || displacing_head;
+            displacing_head = false;
+            first = false;
+            i = self.next_slot(i);
+        }
+        self.size += 1;
+        true
+    }
+
+    /// Remove a fingerprint. Return true if it was present.
+    pub fn remove_fingerprint(&mut self, fp: uint) -> bool {
+        let (fq, fr) = self.split(fp);
+        if !self.is_occupied(fq) {
+            return false;
+        }
+        let start = self.run_start(fq);
+        let mut s = start;
+        loop {
+            let rem = self.get_rem(s);
+            if rem == fr {
+                break;
+            }
+            if rem > fr {
+                return false;
+            }
+            s = self.next_slot(s);
+            if !self.is_continuation(s) {
+                return false;
+            }
+        }
+        let was_head = s == start;
+        let run_continues = self.is_continuation(self.next_slot(s));
+        if was_head && !run_continues {
+            set_bit(self.occupied, fq, false);
+        }
+        // slide the rest of the cluster left over the hole
+        let mut dst = s;
+        let mut src = self.next_slot(s);
+        let mut curr_q = fq;
+        let mut first = true;
+        loop {
+            if self.slot_empty(src) || !self.is_shifted(src) {
+                // end of the cluster; clear the final hole
+                self.set_rem(dst, 0);
+                set_bit(self.continuation, dst, false);
+                set_bit(self.shifted, dst, false);
+                break;
+            }
+            let mut cont = self.is_continuation(src);
+            if !cont {
+                // src heads the run of the next occupied quotient
+                loop {
+                    curr_q = self.next_slot(curr_q);
+                    if self.is_occupied(curr_q) { break; }
+                }
+            } else if first && was_head && run_continues {
+                // the element after a deleted run head becomes the head
+                cont = false;
+            }
+            let rem = self.get_rem(src);
+            self.set_rem(dst, rem);
+            set_bit(self.continuation, dst, cont);
+            set_bit(self.shifted, dst, cont || dst != curr_q);
+            first = false;
+            dst = src;
+            src = self.next_slot(src);
+        }
+        self.size -= 1;
+        true
+    }
+
+    /// Return true if the filter may contain `key`; false means the key
+    /// was definitely never inserted
+    pub fn contains(&self, key: &uint) -> bool {
+        self.contains_fingerprint(hash_key(*key))
+    }
+
+    /// Insert a key. Return true if its fingerprint was not already
+    /// present.
+    pub fn insert(&mut self, key: uint) -> bool {
+        self.insert_fingerprint(hash_key(key))
+    }
+
+    /// Remove a key previously inserted. Return true if its fingerprint
+    /// was present.
+    pub fn remove(&mut self, key: &uint) -> bool {
+        self.remove_fingerprint(hash_key(*key))
+    }
+
+    /// Visit every stored fingerprint, in table order
+    pub fn each_fingerprint(&self, f: &fn(uint) -> bool) -> bool {
+        if self.size == 0 {
+            return true;
+        }
+        // start scanning from an empty slot so run bookkeeping begins at
+        // a cluster boundary; one always exists while size < capacity
+        let mut start = 0;
+        while !self.slot_empty(start) {
+            start += 1;
+        }
+        // queue of canonical slots whose runs have not started yet
+        let mut pending = ~[];
+        let mut head = 0;
+        let mut curr_q = 0;
+        let mut i = start;
+        for self.capacity().times {
+            if self.is_occupied(i) {
+                pending.push(i);
+            }
+            if !self.slot_empty(i) {
+                if !self.is_continuation(i) {
+                    curr_q = pending[head];
+                    head += 1;
+                }
+                if !f((curr_q << self.rbits) | self.get_rem(i)) {
+                    return false;
+                }
+            }
+            i = self.next_slot(i);
+        }
+        return true;
+    }
+
+    /// Copy the contents into a filter with twice as many slots. One
+    /// bit moves from each remainder to its quotient, so fingerprints
+    /// (and thus the false positive rate) are preserved.
+    pub fn doubled(&self) -> QuotientFilter {
+        assert!(self.rbits > 1);
+        let mut bigger = QuotientFilter::new(self.qbits + 1,
+                                             self.rbits - 1);
+        for self.each_fingerprint |fp| {
+            bigger.insert_fingerprint(fp);
+        }
+        bigger
+    }
+
+    /// Insert every fingerprint of `other` into this filter. The two
+    /// filters must have the same fingerprint size.
+    pub fn merge(&mut self, other: &QuotientFilter) {
+        assert_eq!(self.fingerprint_bits(), other.fingerprint_bits());
+        for other.each_fingerprint |fp| {
+            self.insert_fingerprint(fp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::uint;
+
+    #[test]
+    fn test_no_false_negatives() {
+        let mut filter = QuotientFilter::new(10, 6);
+        for uint::range(0, 500) |k| {
+            filter.insert(k * 7 + 1);
+        }
+        assert_eq!(filter.len(), 500);
+        for uint::range(0, 500) |k| {
+            assert!(filter.contains(&(k * 7 + 1)));
+        }
+    }
+
+    #[test]
+    fn test_few_false_positives() {
+        let mut filter = QuotientFilter::new(10, 8);
+        for uint::range(0, 300) |k| {
+            filter.insert(k);
+        }
+        let mut false_positives = 0;
+        for uint::range(10_000, 12_000) |k| {
+            if filter.contains(&k) {
+                false_positives += 1;
+            }
+        }
+        // expected rate is about 300/2^18; leave a wide margin
+        assert!(false_positives < 40);
+    }
+
+    #[test]
+    fn test_runs_share_a_canonical_slot() {
+        // fingerprints chosen to collide on quotients and force runs to
+        // shift: three share quotient 2, one sits at quotient 3
+        let mut filter = QuotientFilter::new(3, 4);
+        let fps = [0x25u, 0x22, 0x29, 0x37];
+        for fps.iter().advance |&fp| {
+            assert!(filter.insert_fingerprint(fp));
+        }
+        for fps.iter().advance |&fp| {
+            assert!(filter.contains_fingerprint(fp));
+        }
+        assert!(!filter.contains_fingerprint(0x21));
+        assert!(!filter.contains_fingerprint(0x36));
+
+        // duplicates are detected, not stored twice
+        assert!(!filter.insert_fingerprint(0x22));
+        assert_eq!(filter.len(), 4);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut filter = QuotientFilter::new(3, 4);
+        let fps = [0x25u, 0x22, 0x29, 0x37, 0x11];
+        for fps.iter().advance |&fp| {
+            assert!(filter.insert_fingerprint(fp));
+        }
+        // delete a run head with continuations, a middle element, and a
+        // member of a shifted run
+        assert!(filter.remove_fingerprint(0x22));
+        assert!(!filter.contains_fingerprint(0x22));
+        assert!(filter.contains_fingerprint(0x25));
+        assert!(filter.contains_fingerprint(0x29));
+        assert!(filter.contains_fingerprint(0x37));
+
+        assert!(filter.remove_fingerprint(0x37));
+        assert!(!filter.contains_fingerprint(0x37));
+        assert!(filter.contains_fingerprint(0x29));
+
+        assert!(!filter.remove_fingerprint(0x22));
+        assert_eq!(filter.len(), 3);
+
+        // reinsertion works after deletion
+        assert!(filter.insert_fingerprint(0x22));
+        assert!(filter.contains_fingerprint(0x22));
+    }
+
+    #[test]
+    fn test_each_fingerprint_round_trips() {
+        let mut filter = QuotientFilter::new(4, 4);
+        let fps = [0x25u, 0x22, 0x29, 0x37, 0xf1, 0x03];
+        for fps.iter().advance |&fp| {
+            assert!(filter.insert_fingerprint(fp));
+        }
+        let mut seen = ~[];
+        for filter.each_fingerprint |fp| {
+            seen.push(fp);
+        }
+        assert_eq!(seen.len(), fps.len());
+        for fps.iter().advance |&fp| {
+            assert!(seen.contains(&fp));
+        }
+    }
+
+    #[test]
+    fn test_doubled_preserves_membership() {
+        let mut filter = QuotientFilter::new(4, 8);
+        for uint::range(0, 12) |k| {
+            filter.insert(k);
+        }
+        let bigger = filter.doubled();
+        assert_eq!(bigger.capacity(), 2 * filter.capacity());
+        assert_eq!(bigger.len(), filter.len());
+        for uint::range(0, 12) |k| {
+            assert!(bigger.contains(&k));
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut a = QuotientFilter::new(6, 6);
+        let mut b = QuotientFilter::new(6, 6);
+        for uint::range(0, 20) |k| {
+            a.insert(k);
+            b.insert(100 + k);
+        }
+        a.merge(&b);
+        for uint::range(0, 20) |k| {
+            assert!(a.contains(&k));
+            assert!(a.contains(&(100 + k)));
+        }
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_overfull_fails() {
+        let mut filter = QuotientFilter::new(2, 4);
+        let mut fp = 0;
+        loop {
+            filter.insert_fingerprint(fp);
+            fp += 3;
+        }
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A run-length encoded bit vector. Instead of storing the bits themselves,
+ * an `RleBitv` stores maximal runs of equal bits, so vectors with long
+ * homogeneous stretches (file allocation maps, interval masks) use space
+ * proportional to the number of runs rather than the number of bits.
+ */
+
+use bitv::Bitv;
+
+use std::uint;
+
+/// A maximal run of equal bits
+#[deriving(Clone, Eq)]
+struct Run {
+    /// The value of every bit in the run
+    value: bool,
+    /// The number of bits in the run; never zero
+    len: uint
+}
+
+/// The run-length encoded bit vector type
+pub struct RleBitv {
+    /// Maximal runs in order; adjacent runs always differ in value
+    priv runs: ~[Run],
+    /// The total number of bits represented
+    priv nbits: uint
+}
+
+impl RleBitv {
+    /// Create an RleBitv of the given length with all bits set to `init`
+    pub fn new(nbits: uint, init: bool) -> RleBitv {
+        let runs = if nbits == 0 {
+            ~[]
+        } else {
+            ~[Run{value: init, len: nbits}]
+        };
+        RleBitv{runs: runs, nbits: nbits}
+    }
+
+    /// Compress an existing bit vector
+    pub fn from_bitv(bitv: &Bitv) -> RleBitv {
+        let mut rle = RleBitv::new(0, false);
+        for bitv.each |b| {
+            rle.push_bit(b);
+        }
+        rle
+    }
+
+    /// Expand back into an uncompressed bit vector of the same length
+    pub fn to_bitv(&self) -> Bitv {
+        let mut bitv = Bitv::new(self.nbits, false);
+        let mut idx = 0;
+        for self.each_run |value, len| {
+            if value {
+                for uint::range(idx, idx + len) |i| {
+                    bitv.set(i, true);
+                }
+            }
+            idx += len;
+        }
+        bitv
+    }
+
+    /// The number of bits in the vector
+    pub fn len(&self) -> uint { self.nbits }
+
+    /// The number of maximal runs used to represent the vector
+    pub fn run_count(&self) -> uint { self.runs.len() }
+
+    /// Append a single bit to the end of the vector
+    pub fn push_bit(&mut self, value: bool) {
+        self.nbits += 1;
+        let n = self.runs.len();
+        if n > 0 && self.runs[n - 1].value == value {
+            self.runs[n - 1].len += 1;
+        } else {
+            self.runs.push(Run{value: value, len: 1});
+        }
+    }
+
+    /// Retrieve the value at index `i`
+    pub fn get(&self, i: uint) -> bool {
+        assert!(i < self.nbits);
+        let mut idx = i;
+        for self.runs.iter().advance |run| {
+            if idx < run.len {
+                return run.value;
+            }
+            idx -= run.len;
+        }
+        fail!("run lengths inconsistent with nbits");
+    }
+
+    /// Return the number of 1 bits strictly before index `i`
+    pub fn rank(&self, i: uint) -> uint {
+        assert!(i <= self.nbits);
+        let mut remaining = i;
+        let mut ones = 0;
+        for self.runs.iter().advance |run| {
+            if remaining <= run.len {
+                if run.value { ones += remaining; }
+                return ones;
+            }
+            if run.value { ones += run.len; }
+            remaining -= run.len;
+        }
+        ones
+    }
+
+    /// Visit each bit in order
+    pub fn each(&self, f: &fn(bool) -> bool) -> bool {
+        for self.runs.iter().advance |run| {
+            for run.len.times {
+                if !f(run.value) { return false; }
+            }
+        }
+        return true;
+    }
+
+    /// Visit each maximal run in order, as (value, length) pairs
+    pub fn each_run(&self, f: &fn(bool, uint) -> bool) -> bool {
+        self.runs.iter().advance(|run| f(run.value, run.len))
+    }
+
+    /// Visit the indices of all 1 bits in order
+    pub fn ones(&self, f: &fn(uint) -> bool) -> bool {
+        let mut idx = 0;
+        for self.runs.iter().advance |run| {
+            if run.value {
+                for uint::range(idx, idx + run.len) |i| {
+                    if !f(i) { return false; }
+                }
+            }
+            idx += run.len;
+        }
+        return true;
+    }
+
+    /// Merge the runs of two vectors, combining overlapping stretches with
+    /// `op`. Both vectors must be the same length. Returns true if `self`
+    /// changed.
+    #[inline]
+    fn do_op(&mut self, other: &RleBitv, op: &fn(bool, bool) -> bool) -> bool {
+        if self.nbits != other.nbits {
+            fail!("Tried to do operation on run-length encoded bit vectors \
+                   with different sizes");
+        }
+        let mut merged: ~[Run] = ~[];
+        let mut i = 0;
+        let mut j = 0;
+        let mut rem1 = 0;
+        let mut rem2 = 0;
+        let mut v1 = false;
+        let mut v2 = false;
+        let mut left = self.nbits;
+        while left > 0 {
+            if rem1 == 0 {
+                v1 = self.runs[i].value;
+                rem1 = self.runs[i].len;
+                i += 1;
+            }
+            if rem2 == 0 {
+                v2 = other.runs[j].value;
+                rem2 = other.runs[j].len;
+                j += 1;
+            }
+            let chunk = uint::min(rem1, rem2);
+            let value = op(v1, v2);
+            let n = merged.len();
+            if n > 0 && merged[n - 1].value == value {
+                merged[n - 1].len += chunk;
+            } else {
+                merged.push(Run{value: value, len: chunk});
+            }
+            rem1 -= chunk;
+            rem2 -= chunk;
+            left -= chunk;
+        }
+        if merged == self.runs {
+            false
+        } else {
+            self.runs = merged;
+            true
+        }
+    }
+
+    /// Set `self` to the union of `self` and `other`. Both vectors must be
+    /// the same length. Returns true if `self` changed.
+    pub fn union(&mut self, other: &RleBitv) -> bool {
+        self.do_op(other, |a, b| a || b)
+    }
+
+    /// Set `self` to the intersection of `self` and `other`. Both vectors
+    /// must be the same length. Returns true if `self` changed.
+    pub fn intersect(&mut self, other: &RleBitv) -> bool {
+        self.do_op(other, |a, b| a && b)
+    }
+
+    /// Set `self` to the difference of `self` and `other`. Both vectors
+    /// must be the same length. Returns true if `self` changed.
+    pub fn difference(&mut self, other: &RleBitv) -> bool {
+        self.do_op(other, |a, b| a && !b)
+    }
+
+    /// Invert every bit, preserving the run structure
+    pub fn invert(&mut self) {
+        for self.runs.mut_iter().advance |run| {
+            run.value = !run.value;
+        }
+    }
+}
+
+impl Clone for RleBitv {
+    fn clone(&self) -> RleBitv {
+        RleBitv{runs: self.runs.clone(), nbits: self.nbits}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bitv;
+    use bitv::Bitv;
+
+    #[test]
+    fn test_basic() {
+        let mut rle = RleBitv::new(1000, false);
+        assert_eq!(rle.len(), 1000);
+        assert_eq!(rle.run_count(), 1);
+        assert!(!rle.get(0));
+        assert!(!rle.get(999));
+
+        rle = RleBitv::new(1000, true);
+        assert_eq!(rle.run_count(), 1);
+        assert!(rle.get(500));
+    }
+
+    #[test]
+    fn test_from_to_bitv() {
+        let bitv = bitv::from_bytes([0b11110000, 0b00001111]);
+        let rle = RleBitv::from_bitv(&bitv);
+        assert_eq!(rle.len(), 16);
+        assert_eq!(rle.run_count(), 3);
+        assert!(rle.to_bitv().equal(&bitv));
+    }
+
+    #[test]
+    fn test_rank() {
+        let bitv = bitv::from_bytes([0b11110000]);
+        let rle = RleBitv::from_bitv(&bitv);
+        assert_eq!(rle.rank(0), 0);
+        assert_eq!(rle.rank(2), 2);
+        assert_eq!(rle.rank(4), 4);
+        assert_eq!(rle.rank(8), 4);
+    }
+
+    #[test]
+    fn test_ones() {
+        let bitv = bitv::from_bytes([0b01100001]);
+        let rle = RleBitv::from_bitv(&bitv);
+        let mut observed = ~[];
+        for rle.ones |i| {
+            observed.push(i);
+        }
+        assert_eq!(observed, ~[1u, 2, 7]);
+    }
+
+    #[test]
+    fn test_union() {
+        let mut a = RleBitv::from_bitv(&bitv::from_bytes([0b11110000]));
+        let b = RleBitv::from_bitv(&bitv::from_bytes([0b00001111]));
+        assert!(a.union(&b));
+        assert_eq!(a.run_count(), 1);
+        assert!(a.to_bitv().equal(&Bitv::new(8, true)));
+        // unioning again changes nothing
+        assert!(!a.union(&b));
+    }
+
+    #[test]
+    fn test_intersect() {
+        let mut a = RleBitv::from_bitv(&bitv::from_bytes([0b11110000]));
+        let b = RleBitv::from_bitv(&bitv::from_bytes([0b00111100]));
+        assert!(a.intersect(&b));
+        assert!(a.to_bitv().equal(&bitv::from_bytes([0b00110000])));
+    }
+
+    #[test]
+    fn test_invert() {
+        let mut a = RleBitv::from_bitv(&bitv::from_bytes([0b11110000]));
+        a.invert();
+        assert!(a.to_bitv().equal(&bitv::from_bytes([0b00001111])));
+        assert_eq!(a.run_count(), 2);
+    }
+}
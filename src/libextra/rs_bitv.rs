@@ -0,0 +1,264 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A static rank/select structure over a frozen bit vector. Building an
+ * `RsBitv` precomputes cumulative population counts at superblock and
+ * block (word) granularity, so `rank` runs in constant time and `select`
+ * in logarithmic time over the superblock directory. The structure is
+ * immutable once built; rebuild it if the underlying bits change.
+ */
+
+use bitv::Bitv;
+use bitv::population_count;
+
+use std::uint;
+use std::vec;
+
+/// Number of words per superblock
+static SUPERBLOCK_WORDS: uint = 8;
+
+/// Count the 1 bits in a word
+fn count_ones_in_word(w: uint) -> uint {
+    population_count(w)
+}
+
+/// The frozen rank/select bit vector type
+pub struct RsBitv {
+    /// The packed bits
+    priv words: ~[uint],
+    /// The number of valid bits
+    priv nbits: uint,
+    /// Cumulative ones before each superblock
+    priv superblocks: ~[uint],
+    /// Cumulative ones before each word, relative to its superblock
+    priv blocks: ~[u16],
+    /// The total number of ones
+    priv nones: uint
+}
+
+impl RsBitv {
+    /// Freeze a bit vector, building the rank and select directories
+    pub fn from_bitv(bitv: &Bitv) -> RsBitv {
+        let mut words = ~[];
+        let mut w = 0;
+        let mut filled = 0;
+        let mut nbits = 0;
+        for bitv.each |b| {
+            if b { w |= 1 << filled; }
+            filled += 1;
+            nbits += 1;
+            if filled == uint::bits {
+                words.push(w);
+                w = 0;
+                filled = 0;
+            }
+        }
+        if filled > 0 {
+            words.push(w);
+        }
+
+        let nwords = words.len();
+        let nsuper = uint::div_ceil(nwords, SUPERBLOCK_WORDS) + 1;
+        let mut superblocks = vec::with_capacity(nsuper);
+        let mut blocks = vec::with_capacity(nwords);
+        let mut total = 0;
+        let mut within = 0;
+        for uint::range(0, nwords) |i| {
+            if i % SUPERBLOCK_WORDS == 0 {
+                superblocks.push(total);
+                within = 0;
+            }
+            blocks.push(within as u16);
+            let ones = count_ones_in_word(words[i]);
+            total += ones;
+            within += ones;
+        }
+        superblocks.push(total);
+
+        RsBitv{
+            words: words,
+            nbits: nbits,
+            superblocks: superblocks,
+            blocks: blocks,
+            nones: total
+        }
+    }
+
+    /// The number of bits in the vector
+    pub fn len(&self) -> uint { self.nbits }
+
+    /// The total number of 1 bits in the vector
+    pub fn count_ones(&self) -> uint { self.nones }
+
+    /// Retrieve the value at index `i`
+    pub fn get(&self, i: uint) -> bool {
+        assert!(i < self.nbits);
+        self.words[i / uint::bits] & (1 << (i % uint::bits)) != 0
+    }
+
+    /// The number of 1 bits strictly before index `i`, in O(1) time
+    pub fn rank(&self, i: uint) -> uint {
+        assert!(i <= self.nbits);
+        let word = i / uint::bits;
+        if word >= self.words.len() {
+            return self.nones;
+        }
+        let base = self.superblocks[word / SUPERBLOCK_WORDS] +
+                   self.blocks[word] as uint;
+        let mask = (1 << (i % uint::bits)) - 1;
+        base + count_ones_in_word(self.words[word] & mask)
+    }
+
+    /// The number of 0 bits strictly before index `i`
+    pub fn rank0(&self, i: uint) -> uint {
+        i - self.rank(i)
+    }
+
+    /// The index of the `k`-th 1 bit (zero-based), or None if fewer than
+    /// `k + 1` bits are set. Binary-searches the superblock directory,
+    /// then scans at most one superblock of words.
+    pub fn select(&self, k: uint) -> Option<uint> {
+        if k >= self.nones {
+            return None;
+        }
+
+        // find the superblock containing the k-th one
+        let mut lo = 0;
+        let mut hi = self.superblocks.len() - 1;
+        while lo + 1 < hi {
+            let mid = (lo + hi) / 2;
+            if self.superblocks[mid] <= k {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        // scan the words of that superblock
+        let mut remaining = k - self.superblocks[lo];
+        let mut word = lo * SUPERBLOCK_WORDS;
+        loop {
+            let ones = count_ones_in_word(self.words[word]);
+            if remaining < ones {
+                break;
+            }
+            remaining -= ones;
+            word += 1;
+        }
+
+        // scan the bits of that word
+        let mut w = self.words[word];
+        let mut bit = 0;
+        loop {
+            if w & 1 != 0 {
+                if remaining == 0 {
+                    return Some(word * uint::bits + bit);
+                }
+                remaining -= 1;
+            }
+            w >>= 1;
+            bit += 1;
+        }
+    }
+    /// The index of the `k`-th 0 bit (zero-based), or None if fewer than
+    /// `k + 1` bits are clear. Implemented by binary search over `rank0`.
+    pub fn select0(&self, k: uint) -> Option<uint> {
+        if k >= self.nbits - self.nones {
+            return None;
+        }
+        let mut lo = 0;
+        let mut hi = self.nbits + 1;
+        while lo + 1 < hi {
+            let mid = (lo + hi) / 2;
+            if self.rank0(mid) <= k {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        Some(lo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bitv;
+    use bitv::Bitv;
+
+    use std::uint;
+
+    #[test]
+    fn test_empty() {
+        let rs = RsBitv::from_bitv(&Bitv::new(0, false));
+        assert_eq!(rs.len(), 0);
+        assert_eq!(rs.count_ones(), 0);
+        assert_eq!(rs.rank(0), 0);
+        assert_eq!(rs.select(0), None);
+    }
+
+    #[test]
+    fn test_rank_small() {
+        let rs = RsBitv::from_bitv(&bitv::from_bytes([0b10110110]));
+        assert_eq!(rs.count_ones(), 5);
+        assert_eq!(rs.rank(0), 0);
+        assert_eq!(rs.rank(1), 1);
+        assert_eq!(rs.rank(3), 2);
+        assert_eq!(rs.rank(8), 5);
+        assert_eq!(rs.rank0(8), 3);
+    }
+
+    #[test]
+    fn test_rank_matches_naive() {
+        // every 7th bit set, crossing word and superblock boundaries
+        let bitv = bitv::from_fn(20 * uint::bits, |i| i % 7 == 0);
+        let rs = RsBitv::from_bitv(&bitv);
+        let mut naive = 0;
+        for uint::range(0, 20 * uint::bits) |i| {
+            assert_eq!(rs.rank(i), naive);
+            if bitv[i] { naive += 1; }
+        }
+        assert_eq!(rs.rank(20 * uint::bits), naive);
+    }
+
+    #[test]
+    fn test_select() {
+        let bitv = bitv::from_fn(20 * uint::bits, |i| i % 7 == 0);
+        let rs = RsBitv::from_bitv(&bitv);
+        for uint::range(0, rs.count_ones()) |k| {
+            assert_eq!(rs.select(k), Some(k * 7));
+        }
+        assert_eq!(rs.select(rs.count_ones()), None);
+    }
+
+    #[test]
+    fn test_select0() {
+        let bitv = bitv::from_fn(1000, |i| i % 3 != 1);
+        let rs = RsBitv::from_bitv(&bitv);
+        let zeros = rs.len() - rs.count_ones();
+        for uint::range(0, zeros) |k| {
+            assert_eq!(rs.select0(k), Some(k * 3 + 1));
+        }
+        assert_eq!(rs.select0(zeros), None);
+    }
+
+    #[test]
+    fn test_select_inverts_rank() {
+        let bitv = bitv::from_fn(1000, |i| i % 3 == 1);
+        let rs = RsBitv::from_bitv(&bitv);
+        for uint::range(0, rs.count_ones()) |k| {
+            let pos = rs.select(k).unwrap();
+            assert!(rs.get(pos));
+            assert_eq!(rs.rank(pos), k);
+        }
+    }
+}
@@ -16,9 +16,14 @@
 #[allow(missing_doc)];
 
 
-use std::cmp;
+use bitv;
+use bitv::{BigBitv, BitCollection, BitvSet, iterate_bits};
+use serialize;
+
 use std::container::{Container, Mutable, Map, Set};
-use std::iterator::{Iterator, EnumerateIterator};
+use std::hashmap::HashMap;
+use std::iterator::{Iterator, FromIterator, EnumerateIterator};
+use std::sys;
 use std::uint;
 use std::util::replace;
 use std::vec;
@@ -50,6 +55,13 @@ pub struct SmallIntMapMutRevIterator<'self, V> {
     priv len: uint
 }
 
+#[allow(missing_doc)]
+pub struct SmallIntMapMissingKeysIterator<'self, V> {
+    priv v: &'self ~[Option<V>],
+    priv idx: uint,
+    priv upto: uint
+}
+
 impl<V> Container for SmallIntMap<V> {
     /// Return the number of elements in the map
     fn len(&self) -> uint {
@@ -178,6 +190,14 @@ impl<V> SmallIntMap<V> {
         SmallIntMapMutRevIterator{iter: self.v.mut_rev_iter().enumerate(), len: len - 1}
     }
 
+    /// External iterator over the keys in `[0, upto)` with no entry, in
+    /// order, useful for finding free slots and validating dense
+    /// numbering
+    pub fn missing_keys_iter<'a>(&'a self, upto: uint)
+                                 -> SmallIntMapMissingKeysIterator<'a, V> {
+        SmallIntMapMissingKeysIterator{v: &self.v, idx: 0, upto: upto}
+    }
+
     /// Visit all keys in order
     pub fn each_key(&self, blk: &fn(key: &uint) -> bool) -> bool {
         self.each(|k, _| blk(k))
@@ -213,6 +233,106 @@ impl<V> SmallIntMap<V> {
     pub fn get<'a>(&'a self, key: &uint) -> &'a V {
         self.find(key).expect("key not present")
     }
+
+    /// The `k`-th smallest present key, or None if fewer than `k + 1`
+    /// keys are present
+    pub fn nth_key(&self, k: uint) -> Option<uint> {
+        let mut remaining = k;
+        for uint::range(0, self.v.len()) |i| {
+            if self.v[i].is_some() {
+                if remaining == 0 {
+                    return Some(i);
+                }
+                remaining -= 1;
+            }
+        }
+        None
+    }
+
+    /// The number of present keys strictly below `key`
+    pub fn key_rank(&self, key: uint) -> uint {
+        let mut rank = 0;
+        for uint::range(0, uint::min(key, self.v.len())) |i| {
+            if self.v[i].is_some() {
+                rank += 1;
+            }
+        }
+        rank
+    }
+
+    /// Apply `f` to every present entry with a key in `[lo, hi)`,
+    /// touching only that slice of the backing vector rather than
+    /// scanning the whole map
+    pub fn mutate_range(&mut self, lo: uint, hi: uint,
+                        f: &fn(uint, &mut V)) {
+        assert!(lo <= hi);
+        let top = uint::min(hi, self.v.len());
+        let mut i = lo;
+        while i < top {
+            match self.v[i] {
+                Some(ref mut elt) => f(i, elt),
+                None => ()
+            }
+            i += 1;
+        }
+    }
+
+    /// Exchange the entries at two keys in place, including their
+    /// presence or absence, so permutation passes need not pop both
+    /// values and handle the missing-key cases by hand
+    pub fn swap_keys(&mut self, a: uint, b: uint) {
+        if a == b {
+            return;
+        }
+        let len = self.v.len();
+        let a_present = a < len && self.v[a].is_some();
+        let b_present = b < len && self.v[b].is_some();
+        if !a_present && !b_present {
+            return;
+        }
+        let needed = uint::max(a, b) + 1;
+        if len < needed {
+            self.v.grow_fn(needed - len, |_| None);
+        }
+        self.v.swap(a, b);
+    }
+
+    /// Remove every entry with key `k` or above, shrinking the backing
+    /// vector accordingly, and return how many entries were dropped —
+    /// for windowed keyspaces that would otherwise loop `remove` over
+    /// an unknown upper range
+    pub fn truncate_keys_above(&mut self, k: uint) -> uint {
+        let mut dropped = 0;
+        for uint::range(k, self.v.len()) |i| {
+            if self.v[i].is_some() {
+                dropped += 1;
+            }
+        }
+        if k < self.v.len() {
+            self.v.truncate(k);
+        }
+        dropped
+    }
+
+    /// Returns the number of bytes of heap memory owned by this map, not
+    /// counting any heap memory the values themselves may own
+    pub fn memory_usage(&self) -> uint {
+        self.v.capacity() * sys::size_of::<Option<V>>()
+    }
+}
+
+impl<V: Eq + Hash + Clone> SmallIntMap<V> {
+    /// Build the reverse index of the map: each distinct value mapped
+    /// to the set of keys storing it, as when a renaming table needs to
+    /// be consulted in the other direction
+    pub fn invert(&self) -> HashMap<V, BitvSet> {
+        let mut index = HashMap::new();
+        for self.each |&k, v| {
+            index.find_or_insert_with(v.clone(),
+                                      |_| BitvSet::new()).insert(k);
+        }
+        index
+    }
 }
 
 impl<V:Copy> SmallIntMap<V> {
@@ -287,49 +407,473 @@ impl<'self, V> Iterator<(uint, &'self mut V)> for SmallIntMapMutRevIterator<'sel
     }
 }
 
-/// A set implemented on top of the SmallIntMap type. This set is always a set
-/// of integers, and the space requirements are on the order of the highest
-/// valued integer in the set.
+/// Implementation of the missing-keys external iterator
+impl<'self, V> Iterator<uint> for SmallIntMapMissingKeysIterator<'self, V> {
+    #[inline]
+    fn next(&mut self) -> Option<uint> {
+        while self.idx < self.upto {
+            let key = self.idx;
+            self.idx += 1;
+            if key >= self.v.len() || self.v[key].is_none() {
+                return Some(key);
+            }
+        }
+        None
+    }
+}
+
+/// A map from small integer keys to any number of values per key. Values
+/// are stored densely per key in insertion order, so appending is cheap
+/// and a key's group can be handed out as a slice.
+pub struct SmallIntMultiMap<V> {
+    priv v: ~[~[V]],
+    priv size: uint,
+    /// Stays empty forever; handed out by `get` for keys that have no
+    /// allocated group, so that `get` can always return a borrowed slice
+    priv empty: ~[V]
+}
+
+impl<V> Container for SmallIntMultiMap<V> {
+    /// Return the total number of values in the map, over all keys
+    fn len(&self) -> uint { self.size }
+
+    /// Return true if the map contains no values
+    fn is_empty(&self) -> bool { self.size == 0 }
+}
+
+impl<V> Mutable for SmallIntMultiMap<V> {
+    /// Clear the map, removing all key-value pairs
+    fn clear(&mut self) {
+        self.v.clear();
+        self.size = 0;
+    }
+}
+
+impl<V> SmallIntMultiMap<V> {
+    /// Create an empty SmallIntMultiMap
+    pub fn new() -> SmallIntMultiMap<V> {
+        SmallIntMultiMap{v: ~[], size: 0, empty: ~[]}
+    }
+
+    /// Return the number of keys that have at least one value
+    pub fn key_len(&self) -> uint {
+        let mut keys = 0;
+        for self.v.iter().advance |group| {
+            if !group.is_empty() {
+                keys += 1;
+            }
+        }
+        keys
+    }
+
+    /// Append a value to the group of values stored under `key`
+    pub fn insert(&mut self, key: uint, value: V) {
+        if key >= self.v.len() {
+            self.v.grow_fn(key - self.v.len() + 1, |_| ~[]);
+        }
+        self.v[key].push(value);
+        self.size += 1;
+    }
+
+    /// Return the values stored under `key`, in insertion order; the
+    /// slice is empty if the key has no values
+    pub fn get<'a>(&'a self, key: &uint) -> &'a [V] {
+        if *key < self.v.len() {
+            let group: &'a [V] = self.v[*key];
+            group
+        } else {
+            let group: &'a [V] = self.empty;
+            group
+        }
+    }
+
+    /// Remove all values stored under `key`, returning them. Returns
+    /// None if the key had no values.
+    pub fn pop_key(&mut self, key: &uint) -> Option<~[V]> {
+        if *key >= self.v.len() || self.v[*key].is_empty() {
+            return None;
+        }
+        let group = replace(&mut self.v[*key], ~[]);
+        self.size -= group.len();
+        Some(group)
+    }
+
+    /// Visit each key and its group of values, in key order, skipping
+    /// keys with no values
+    pub fn each_group<'a>(&'a self,
+                          it: &fn(&uint, &'a [V]) -> bool) -> bool {
+        for uint::range(0, self.v.len()) |i| {
+            if !self.v[i].is_empty() {
+                let group: &'a [V] = self.v[i];
+                if !it(&i, group) {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+
+    /// Visit every key-value pair, in key order and then insertion order
+    /// within a key
+    pub fn each<'a>(&'a self, it: &fn(&uint, &'a V) -> bool) -> bool {
+        for self.each_group |k, group| {
+            for group.iter().advance |v| {
+                if !it(k, v) {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+}
+
+impl<V: Eq> SmallIntMultiMap<V> {
+    /// Remove the first value equal to `value` from the group stored
+    /// under `key`. Return true if a value was removed.
+    pub fn remove_value(&mut self, key: &uint, value: &V) -> bool {
+        if *key >= self.v.len() {
+            return false;
+        }
+        let group = &mut self.v[*key];
+        for uint::range(0, group.len()) |i| {
+            if group[i] == *value {
+                group.remove(i);
+                self.size -= 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Return true if `value` is stored under `key`
+    pub fn contains_value(&self, key: &uint, value: &V) -> bool {
+        self.get(key).iter().any_(|v| *v == *value)
+    }
+}
+
+/// A map keyed by a pair of small integers, with each row stored densely
+/// so that iterating one row touches contiguous memory. Suited to
+/// grid-shaped tables such as per-(block, register) facts, which would
+/// otherwise be maps of maps with poor locality.
+pub struct SmallIntMap2<V> {
+    priv rows: ~[~[Option<V>]],
+    priv size: uint
+}
+
+impl<V> Container for SmallIntMap2<V> {
+    /// Return the number of elements in the map
+    fn len(&self) -> uint { self.size }
+
+    /// Return true if the map contains no elements
+    fn is_empty(&self) -> bool { self.size == 0 }
+}
+
+impl<V> Mutable for SmallIntMap2<V> {
+    /// Clear the map, removing all key-value pairs
+    fn clear(&mut self) {
+        self.rows.clear();
+        self.size = 0;
+    }
+}
+
+impl<V> SmallIntMap2<V> {
+    /// Create an empty SmallIntMap2
+    pub fn new() -> SmallIntMap2<V> {
+        SmallIntMap2{rows: ~[], size: 0}
+    }
+
+    /// Return a reference to the value keyed by `(row, col)`
+    pub fn find<'a>(&'a self, row: uint, col: uint) -> Option<&'a V> {
+        if row < self.rows.len() && col < self.rows[row].len() {
+            match self.rows[row][col] {
+                Some(ref value) => Some(value),
+                None => None
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Return a mutable reference to the value keyed by `(row, col)`
+    pub fn find_mut<'a>(&'a mut self, row: uint,
+                        col: uint) -> Option<&'a mut V> {
+        if row < self.rows.len() && col < self.rows[row].len() {
+            match self.rows[row][col] {
+                Some(ref mut value) => Some(value),
+                None => None
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Return true if the map contains a value for `(row, col)`
+    pub fn contains_key(&self, row: uint, col: uint) -> bool {
+        self.find(row, col).is_some()
+    }
+
+    /// Insert a key-value pair into the map. An existing value for the
+    /// key is replaced by the new value. Return true if the key did not
+    /// already exist in the map.
+    pub fn insert(&mut self, row: uint, col: uint, value: V) -> bool {
+        if row >= self.rows.len() {
+            self.rows.grow_fn(row - self.rows.len() + 1, |_| ~[]);
+        }
+        if col >= self.rows[row].len() {
+            let n = col - self.rows[row].len() + 1;
+            self.rows[row].grow_fn(n, |_| None);
+        }
+        let exists = self.rows[row][col].is_some();
+        self.rows[row][col] = Some(value);
+        if !exists {
+            self.size += 1;
+        }
+        !exists
+    }
+
+    /// Remove a key from the map, returning the value at the key if the
+    /// key was previously in the map
+    pub fn pop(&mut self, row: uint, col: uint) -> Option<V> {
+        if row >= self.rows.len() || col >= self.rows[row].len() {
+            return None;
+        }
+        let value = replace(&mut self.rows[row][col], None);
+        if value.is_some() {
+            self.size -= 1;
+        }
+        value
+    }
+
+    /// Visit all key-value pairs in row-major order
+    pub fn each<'a>(&'a self,
+                    it: &fn(uint, uint, &'a V) -> bool) -> bool {
+        for uint::range(0, self.rows.len()) |r| {
+            if !self.each_in_row(r, |c, v| it(r, c, v)) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Visit the values in row `row`, in column order
+    pub fn each_in_row<'a>(&'a self, row: uint,
+                           it: &fn(uint, &'a V) -> bool) -> bool {
+        if row >= self.rows.len() {
+            return true;
+        }
+        let cells: &'a [Option<V>] = self.rows[row];
+        for cells.iter().enumerate().advance |(c, cell)| {
+            match *cell {
+                Some(ref value) => {
+                    if !it(c, value) {
+                        return false;
+                    }
+                }
+                None => ()
+            }
+        }
+        return true;
+    }
+
+    /// Visit the values in column `col`, in row order. Unlike row
+    /// iteration this strides across rows.
+    pub fn each_in_col<'a>(&'a self, col: uint,
+                           it: &fn(uint, &'a V) -> bool) -> bool {
+        for uint::range(0, self.rows.len()) |r| {
+            if col < self.rows[r].len() {
+                match self.rows[r][col] {
+                    Some(ref value) => {
+                        if !it(r, value) {
+                            return false;
+                        }
+                    }
+                    None => ()
+                }
+            }
+        }
+        return true;
+    }
+}
+
+/// A disjoint-set (union-find) structure over the dense elements
+/// `[0, nelems)`, with path compression and union by rank, so a
+/// sequence of operations runs in effectively linear time. `find` and
+/// its relatives take `&mut self` because compression rewrites parent
+/// links.
+pub struct UnionFind {
+    priv parent: ~[uint],
+    priv rank: ~[uint],
+    /// The number of distinct sets remaining
+    priv nsets: uint
+}
+
+impl UnionFind {
+    /// Create a structure over `nelems` elements, each in its own set
+    pub fn new(nelems: uint) -> UnionFind {
+        UnionFind{
+            parent: vec::from_fn(nelems, |i| i),
+            rank: vec::from_elem(nelems, 0),
+            nsets: nelems
+        }
+    }
+
+    /// The number of elements
+    pub fn len(&self) -> uint { self.parent.len() }
+
+    /// The number of distinct sets
+    pub fn set_count(&self) -> uint { self.nsets }
+
+    /// Add a new element in its own set, returning it
+    pub fn push_elem(&mut self) -> uint {
+        let elem = self.parent.len();
+        self.parent.push(elem);
+        self.rank.push(0);
+        self.nsets += 1;
+        elem
+    }
+
+    /// The representative of the set containing `x`, compressing the
+    /// path walked to it
+    pub fn find(&mut self, x: uint) -> uint {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut cur = x;
+        while self.parent[cur] != root {
+            let next = self.parent[cur];
+            self.parent[cur] = root;
+            cur = next;
+        }
+        root
+    }
+
+    /// Merge the sets containing `a` and `b`. Return true if they were
+    /// previously distinct.
+    pub fn union(&mut self, a: uint, b: uint) -> bool {
+        let ra = self.find(a);
+        let rb = self.find(b);
+        if ra == rb {
+            return false;
+        }
+        if self.rank[ra] < self.rank[rb] {
+            self.parent[ra] = rb;
+        } else if self.rank[ra] > self.rank[rb] {
+            self.parent[rb] = ra;
+        } else {
+            self.parent[rb] = ra;
+            self.rank[ra] += 1;
+        }
+        self.nsets -= 1;
+        true
+    }
+
+    /// Return true if `a` and `b` are in the same set
+    pub fn same_set(&mut self, a: uint, b: uint) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// The members of the set containing `x`, as a BitvSet
+    pub fn set_of(&mut self, x: uint) -> BitvSet {
+        let root = self.find(x);
+        let mut members = BitvSet::new();
+        for uint::range(0, self.parent.len()) |i| {
+            if self.find(i) == root {
+                members.insert(i);
+            }
+        }
+        members
+    }
+
+    /// All sets at once: a map from each representative to the BitvSet
+    /// of its members
+    pub fn sets(&mut self) -> SmallIntMap<BitvSet> {
+        let mut out = SmallIntMap::new();
+        for uint::range(0, self.parent.len()) |i| {
+            let root = self.find(i);
+            if !out.contains_key(&root) {
+                out.insert(root, BitvSet::new());
+            }
+            out.find_mut(&root).unwrap().insert(i);
+        }
+        out
+    }
+}
+
+/// Count the 1 bits in a word
+fn count_bits(w: uint) -> uint {
+    bitv::population_count(w)
+}
+
+/// A set implemented on top of a bit vector. This set is always a set of
+/// integers, and the space requirements are on the order of the highest
+/// valued integer in the set, at one bit per potential element.
 pub struct SmallIntSet {
-    priv map: SmallIntMap<()>
+    priv size: uint,
+    priv bits: BigBitv,
 }
 
 #[allow(missing_doc)]
 pub struct SmallIntSetIterator<'self> {
-    priv iter: SmallIntMapIterator<'self, ()>,
+    priv bits: &'self BigBitv,
+    priv idx: uint,
 }
 
 #[allow(missing_doc)]
 pub struct SmallIntSetRevIterator<'self> {
-    priv iter: SmallIntMapRevIterator<'self, ()>,
+    priv bits: &'self BigBitv,
+    priv idx: uint,
 }
 
 impl Container for SmallIntSet {
-    /// Return the number of elements in the map
-    fn len(&self) -> uint {
-        self.map.len()
-    }
+    /// Return the number of elements in the set
+    fn len(&self) -> uint { self.size }
 
-    /// Return true if the map contains no elements
-    fn is_empty(&self) -> bool { self.len() == 0 }
+    /// Return true if the set contains no elements
+    fn is_empty(&self) -> bool { self.size == 0 }
 }
 
 impl Mutable for SmallIntSet {
-    /// Clear the map, removing all key-value pairs.
-    fn clear(&mut self) { self.map.clear() }
+    /// Clear the set, removing all values.
+    fn clear(&mut self) {
+        for self.bits.each_storage |w| { *w = 0; }
+        self.size = 0;
+    }
 }
 
 impl Set<uint> for SmallIntSet {
     /// Return true if the set contains a value
-    fn contains(&self, value: &uint) -> bool { self.map.contains_key(value) }
+    fn contains(&self, value: &uint) -> bool {
+        *value < self.capacity() && self.bits.get(*value)
+    }
 
     /// Add a value to the set. Return true if the value was not already
     /// present in the set.
-    fn insert(&mut self, value: uint) -> bool { self.map.insert(value, ()) }
+    fn insert(&mut self, value: uint) -> bool {
+        if self.contains(&value) {
+            return false;
+        }
+        let nbits = self.capacity();
+        if value >= nbits {
+            let newsize = uint::max(value, nbits * 2) / uint::bits + 1;
+            assert!(newsize > self.bits.storage.len());
+            self.bits.storage.grow(newsize, &0);
+        }
+        self.size += 1;
+        self.bits.set(value, true);
+        return true;
+    }
 
     /// Remove a value from the set. Return true if the value was
     /// present in the set.
-    fn remove(&mut self, value: &uint) -> bool { self.map.remove(value) }
+    fn remove(&mut self, value: &uint) -> bool {
+        if !self.contains(value) {
+            return false;
+        }
+        self.size -= 1;
+        self.bits.set(*value, false);
+        return true;
+    }
 
     /// Return true if the set has no elements in common with `other`.
     /// This is equivalent to checking for an empty uintersection.
@@ -358,14 +902,7 @@ impl Set<uint> for SmallIntSet {
     fn symmetric_difference(&self,
                             other: &SmallIntSet,
                             f: &fn(&uint) -> bool) -> bool {
-        let len = cmp::max(self.map.v.len() ,other.map.v.len());
-
-        for uint::range(0, len) |i| {
-            if self.contains(&i) ^ other.contains(&i) {
-                if !f(&i) { return false; }
-            }
-        }
-        return true;
+        self.binop_each(other, |w1, w2| w1 ^ w2, f)
     }
 
     /// Visit the values representing the uintersection
@@ -375,32 +912,318 @@ impl Set<uint> for SmallIntSet {
 
     /// Visit the values representing the union
     fn union(&self, other: &SmallIntSet, f: &fn(&uint) -> bool) -> bool {
-        let len = cmp::max(self.map.v.len() ,other.map.v.len());
-
-        for uint::range(0, len) |i| {
-            if self.contains(&i) || other.contains(&i) {
-                if !f(&i) { return false; }
-            }
-        }
-        return true;
+        self.binop_each(other, |w1, w2| w1 | w2, f)
     }
 }
 
 impl SmallIntSet {
     /// Create an empty SmallIntSet
-    pub fn new() -> SmallIntSet { SmallIntSet{map: SmallIntMap::new()} }
+    pub fn new() -> SmallIntSet {
+        SmallIntSet{size: 0, bits: BigBitv::new(~[0])}
+    }
+
+    /// Create an empty SmallIntSet with storage preallocated for elements
+    /// up to (but not including) `nbits`. Useful when the maximum element
+    /// is known ahead of time, as it avoids regrowing during insertion.
+    pub fn with_capacity(nbits: uint) -> SmallIntSet {
+        let nwords = uint::max(1, uint::div_ceil(nbits, uint::bits));
+        SmallIntSet{size: 0, bits: BigBitv::new(vec::from_elem(nwords, 0))}
+    }
+
+    /// Flip the membership of `value` in one operation, returning the new
+    /// state: true if the value was inserted, false if it was removed.
+    /// Unlike a contains-then-insert/remove sequence this probes the
+    /// backing storage only once.
+    pub fn toggle(&mut self, value: uint) -> bool {
+        if value >= self.capacity() {
+            // a bit past the end is always absent, so it toggles on
+            self.insert(value);
+            return true;
+        }
+        let w = value / uint::bits;
+        let flag = 1 << (value % uint::bits);
+        let word = self.bits.storage[w] ^ flag;
+        self.bits.storage[w] = word;
+        if word & flag != 0 {
+            self.size += 1;
+            true
+        } else {
+            self.size -= 1;
+            false
+        }
+    }
+
+    /// Insert all values yielded by an iterator
+    pub fn extend<T: Iterator<uint>>(&mut self, iterator: &mut T) {
+        for iterator.advance |v| {
+            self.insert(v);
+        }
+    }
+
+    /// Create a set holding the keys of an existing map. This is the
+    /// migration path for users of the older map-backed representation.
+    pub fn from_map_keys<V>(map: &SmallIntMap<V>) -> SmallIntSet {
+        let mut set = SmallIntSet::new();
+        for map.each_key |&k| {
+            set.insert(k);
+        }
+        set
+    }
+
+    /// Create a SmallIntSet holding the same values as `set`, copying the
+    /// underlying storage a word at a time
+    pub fn from_bitv_set(set: &BitvSet) -> SmallIntSet {
+        let nwords = uint::max(1, set.capacity() / uint::bits);
+        let mut words = vec::from_elem(nwords, 0u);
+        for set.each_word |base, w| {
+            words[base / uint::bits] = w;
+        }
+        SmallIntSet{size: set.len(), bits: BigBitv::new(words)}
+    }
+
+    /// Convert this set to a BitvSet holding the same values, copying the
+    /// underlying storage a word at a time
+    pub fn to_bitv_set(&self) -> BitvSet {
+        BitvSet::from_bitv(bitv::from_words(copy self.bits.storage))
+    }
+
+    /// Returns the number of elements the set can hold without resizing.
+    /// Inserting any smaller element will not trigger a resizing.
+    pub fn capacity(&self) -> uint { self.bits.storage.len() * uint::bits }
 
     /// Visit all values in order
-    pub fn each(&self, f: &fn(&uint) -> bool) -> bool { self.map.each_key(f) }
+    pub fn each(&self, f: &fn(&uint) -> bool) -> bool {
+        for self.bits.storage.iter().enumerate().advance |(i, &w)| {
+            if !iterate_bits(i * uint::bits, w, |b| f(&b)) {
+                return false;
+            }
+        }
+        return true;
+    }
 
     /// Immutable external iterator
     pub fn iter<'a>(&'a self) -> SmallIntSetIterator<'a> {
-        SmallIntSetIterator{iter: self.map.iter()}
+        SmallIntSetIterator{bits: &self.bits, idx: 0}
     }
 
     /// Reversed immutable external iterator
     pub fn rev_iter<'a>(&'a self) -> SmallIntSetRevIterator<'a> {
-        SmallIntSetRevIterator{iter: self.map.rev_iter()}
+        SmallIntSetRevIterator{bits: &self.bits, idx: self.capacity()}
+    }
+
+    /// Returns the number of bytes of heap memory owned by this set
+    pub fn memory_usage(&self) -> uint {
+        self.bits.storage.capacity() * uint::bytes
+    }
+
+    /// Return true if the set is a subset of `other` and not equal to
+    /// it. The stored sizes rule most cases out without comparing words.
+    pub fn is_proper_subset(&self, other: &SmallIntSet) -> bool {
+        self.size < other.size && self.is_subset(other)
+    }
+
+    /// Return true if the set is a superset of `other` and not equal
+    /// to it
+    pub fn is_proper_superset(&self, other: &SmallIntSet) -> bool {
+        other.is_proper_subset(self)
+    }
+
+    /// Visit the set bits of a word-wise binary operation against `other`,
+    /// treating words past the end of either set as zero. Words that come
+    /// out all zero are skipped without probing the individual bits, so the
+    /// cost is proportional to the occupied portions of the two sets.
+    fn binop_each(&self, other: &SmallIntSet, op: &fn(uint, uint) -> uint,
+                  f: &fn(&uint) -> bool) -> bool {
+        let len1 = self.bits.storage.len();
+        let len2 = other.bits.storage.len();
+        for uint::range(0, uint::max(len1, len2)) |i| {
+            let w1 = if i < len1 { self.bits.storage[i] } else { 0 };
+            let w2 = if i < len2 { other.bits.storage[i] } else { 0 };
+            if !iterate_bits(i * uint::bits, op(w1, w2), |b| f(&b)) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    /// Count the result of a word-wise binary operation against `other`,
+    /// treating words past the end of either set as zero
+    fn binop_len(&self, other: &SmallIntSet,
+                 f: &fn(uint, uint) -> uint) -> uint {
+        let len1 = self.bits.storage.len();
+        let len2 = other.bits.storage.len();
+        let mut count = 0;
+        for uint::range(0, uint::max(len1, len2)) |i| {
+            let w1 = if i < len1 { self.bits.storage[i] } else { 0 };
+            let w2 = if i < len2 { other.bits.storage[i] } else { 0 };
+            count += count_bits(f(w1, w2));
+        }
+        count
+    }
+
+    /// Return the number of values the union of two sets would contain,
+    /// without visiting the individual values
+    pub fn union_len(&self, other: &SmallIntSet) -> uint {
+        self.binop_len(other, |w1, w2| w1 | w2)
+    }
+
+    /// Return the number of values the intersection of two sets would
+    /// contain, without visiting the individual values
+    pub fn intersection_len(&self, other: &SmallIntSet) -> uint {
+        self.binop_len(other, |w1, w2| w1 & w2)
+    }
+
+    /// Return the number of values the difference of two sets would
+    /// contain, without visiting the individual values
+    pub fn difference_len(&self, other: &SmallIntSet) -> uint {
+        self.binop_len(other, |w1, w2| w1 & !w2)
+    }
+
+    /// Return the number of values the symmetric difference of two sets
+    /// would contain, without visiting the individual values
+    pub fn symmetric_difference_len(&self, other: &SmallIntSet) -> uint {
+        self.binop_len(other, |w1, w2| w1 ^ w2)
+    }
+}
+
+impl BitCollection for SmallIntSet {
+    fn has_bit(&self, value: uint) -> bool {
+        self.contains(&value)
+    }
+
+    fn add_bit(&mut self, value: uint) -> bool {
+        self.insert(value)
+    }
+
+    fn remove_bit(&mut self, value: uint) -> bool {
+        self.remove(&value)
+    }
+
+    fn union_bits(&mut self, other: &SmallIntSet) -> bool {
+        let mut changed = false;
+        for other.each |&v| {
+            if self.insert(v) {
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn intersect_bits(&mut self, other: &SmallIntSet) -> bool {
+        let olen = other.bits.storage.len();
+        let mut size = 0;
+        let mut changed = false;
+        for self.bits.storage.mut_iter().enumerate().advance |(i, w)| {
+            let ow = if i < olen { other.bits.storage[i] } else { 0 };
+            let masked = *w & ow;
+            if masked != *w {
+                changed = true;
+                *w = masked;
+            }
+            size += count_bits(masked);
+        }
+        self.size = size;
+        changed
+    }
+
+    fn each_bit(&self, f: &fn(uint) -> bool) -> bool {
+        self.each(|&v| f(v))
+    }
+}
+
+impl<T: Iterator<uint>> FromIterator<uint, T> for SmallIntSet {
+    fn from_iterator(iterator: &mut T) -> SmallIntSet {
+        let mut set = SmallIntSet::new();
+        set.extend(iterator);
+        set
+    }
+}
+
+impl<S: serialize::Encoder> serialize::Encodable<S> for SmallIntSet {
+    /// Encodes as a sequence of the member values in increasing order
+    fn encode(&self, s: &mut S) {
+        do s.emit_seq(self.len()) |s| {
+            let mut idx = 0;
+            for self.each |&v| {
+                do s.emit_seq_elt(idx) |s| {
+                    s.emit_uint(v);
+                }
+                idx += 1;
+            }
+        }
+    }
+}
+
+impl<D: serialize::Decoder> serialize::Decodable<D> for SmallIntSet {
+    fn decode(d: &mut D) -> SmallIntSet {
+        do d.read_seq |d, len| {
+            let mut set = SmallIntSet::new();
+            for uint::range(0, len) |i| {
+                set.insert(d.read_seq_elt(i, |d| d.read_uint()));
+            }
+            set
+        }
+    }
+}
+
+impl<S: serialize::Encoder, V: serialize::Encodable<S>>
+        serialize::Encodable<S> for SmallIntMap<V> {
+    /// Encodes as a map keyed by the decimal renderings of the keys,
+    /// since interchange formats like JSON require string keys
+    fn encode(&self, s: &mut S) {
+        do s.emit_map(self.len()) |s| {
+            let mut idx = 0;
+            for self.each |&k, v| {
+                do s.emit_map_elt_key(idx) |s| {
+                    s.emit_str(k.to_str());
+                }
+                do s.emit_map_elt_val(idx) |s| {
+                    v.encode(s);
+                }
+                idx += 1;
+            }
+        }
+    }
+}
+
+impl<D: serialize::Decoder, V: serialize::Decodable<D>>
+        serialize::Decodable<D> for SmallIntMap<V> {
+    fn decode(d: &mut D) -> SmallIntMap<V> {
+        do d.read_map |d, len| {
+            let mut map = SmallIntMap::new();
+            for uint::range(0, len) |i| {
+                let key = do d.read_map_elt_key(i) |d| {
+                    match uint::from_str(d.read_str()) {
+                        Some(key) => key,
+                        None => fail!("non-numeric map key")
+                    }
+                };
+                let value = do d.read_map_elt_val(i) |d| {
+                    serialize::Decodable::decode(d)
+                };
+                map.insert(key, value);
+            }
+            map
+        }
+    }
+}
+
+impl<V: ToStr> ToStr for SmallIntMap<V> {
+    /// Renders the entries in increasing key order, `{3: x, 7: y}` style
+    fn to_str(&self) -> ~str {
+        let mut s = ~"{";
+        let mut first = true;
+        for self.each |&k, v| {
+            if !first {
+                s.push_str(", ");
+            }
+            first = false;
+            s.push_str(k.to_str());
+            s.push_str(": ");
+            s.push_str(v.to_str());
+        }
+        s.push_char('}');
+        s
     }
 }
 
@@ -408,7 +1231,21 @@ impl SmallIntSet {
 impl<'self> Iterator<uint> for SmallIntSetIterator<'self> {
     #[inline]
     fn next(&mut self) -> Option<uint> {
-        self.iter.next().map(|&(k,_)| k)
+        let nbits = self.bits.storage.len() * uint::bits;
+        while self.idx < nbits {
+            let w = self.bits.storage[self.idx / uint::bits];
+            if w == 0 {
+                // skip the rest of an all-zero word in one step
+                self.idx = (self.idx / uint::bits + 1) * uint::bits;
+                loop;
+            }
+            let bit = self.idx;
+            self.idx += 1;
+            if w & (1 << (bit % uint::bits)) != 0 {
+                return Some(bit);
+            }
+        }
+        None
     }
 }
 
@@ -416,7 +1253,19 @@ impl<'self> Iterator<uint> for SmallIntSetIterator<'self> {
 impl<'self> Iterator<uint> for SmallIntSetRevIterator<'self> {
     #[inline]
     fn next(&mut self) -> Option<uint> {
-        self.iter.next().map(|&(k,_)| k)
+        while self.idx > 0 {
+            let w = self.bits.storage[(self.idx - 1) / uint::bits];
+            if w == 0 {
+                // skip the rest of an all-zero word in one step
+                self.idx = ((self.idx - 1) / uint::bits) * uint::bits;
+                loop;
+            }
+            self.idx -= 1;
+            if w & (1 << (self.idx % uint::bits)) != 0 {
+                return Some(self.idx);
+            }
+        }
+        None
     }
 }
 
@@ -425,6 +1274,165 @@ mod tests {
 
     use super::SmallIntMap;
     use std::iterator::FromIterator;
+    use std::sys;
+    use std::uint;
+
+    #[test]
+    fn test_json() {
+        use json;
+        use serialize::{Encodable, Decodable};
+        use std::io;
+
+        let mut m = SmallIntMap::new();
+        m.insert(3, ~"x");
+        m.insert(7, ~"y");
+        let text = do io::with_str_writer |wr| {
+            let mut encoder = json::Encoder(wr);
+            m.encode(&mut encoder);
+        };
+        assert_eq!(text, ~"{\"3\":\"x\",\"7\":\"y\"}");
+        let mut decoder = json::Decoder(json::from_str(text).unwrap());
+        let decoded: SmallIntMap<~str> = Decodable::decode(&mut decoder);
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded.find(&3), Some(&~"x"));
+        assert_eq!(decoded.find(&7), Some(&~"y"));
+    }
+
+    #[test]
+    fn test_nth_key_and_key_rank() {
+        let mut m = SmallIntMap::new();
+        m.insert(2, 'a');
+        m.insert(5, 'b');
+        m.insert(9, 'c');
+        assert_eq!(m.nth_key(0), Some(2));
+        assert_eq!(m.nth_key(1), Some(5));
+        assert_eq!(m.nth_key(2), Some(9));
+        assert_eq!(m.nth_key(3), None);
+        assert_eq!(m.key_rank(0), 0);
+        assert_eq!(m.key_rank(2), 0);
+        assert_eq!(m.key_rank(3), 1);
+        assert_eq!(m.key_rank(9), 2);
+        assert_eq!(m.key_rank(1000), 3);
+        // rank and nth_key are inverses over present keys
+        for m.each_key |&k| {
+            assert_eq!(m.nth_key(m.key_rank(k)), Some(k));
+        }
+    }
+
+    #[test]
+    fn test_missing_keys_iter() {
+        let mut m = SmallIntMap::new();
+        m.insert(1, 'a');
+        m.insert(2, 'b');
+        m.insert(5, 'c');
+        // gaps inside the backing vector and keys past its end
+        let free: ~[uint] =
+            FromIterator::from_iterator(&mut m.missing_keys_iter(8));
+        assert_eq!(free, ~[0u, 3, 4, 6, 7]);
+        // a densely numbered prefix has no gaps
+        let free: ~[uint] =
+            FromIterator::from_iterator(&mut m.missing_keys_iter(0));
+        assert_eq!(free, ~[]);
+        m.insert(0, 'd');
+        m.insert(3, 'e');
+        m.insert(4, 'f');
+        let free: ~[uint] =
+            FromIterator::from_iterator(&mut m.missing_keys_iter(6));
+        assert_eq!(free, ~[]);
+    }
+
+    #[test]
+    fn test_mutate_range() {
+        let mut m = SmallIntMap::new();
+        for uint::range(0, 10) |i| {
+            if i % 3 != 0 {
+                m.insert(i, i);
+            }
+        }
+        let mut touched = ~[];
+        do m.mutate_range(2, 7) |k, v| {
+            touched.push(k);
+            *v += 100;
+        }
+        assert_eq!(touched, ~[2u, 4, 5]);
+        assert_eq!(m.find(&2), Some(&102));
+        assert_eq!(m.find(&5), Some(&105));
+        // entries outside the window are untouched
+        assert_eq!(m.find(&1), Some(&1));
+        assert_eq!(m.find(&7), Some(&7));
+        // a range past the end of the backing vector is fine
+        do m.mutate_range(8, 100) |_, v| {
+            *v = 0;
+        }
+        assert_eq!(m.find(&8), Some(&0));
+    }
+
+    #[test]
+    fn test_invert() {
+        let mut m = SmallIntMap::new();
+        m.insert(1, ~"a");
+        m.insert(3, ~"b");
+        m.insert(7, ~"a");
+        m.insert(9, ~"b");
+        let index = m.invert();
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.get(&~"a").to_str(), ~"{1, 7}");
+        assert_eq!(index.get(&~"b").to_str(), ~"{3, 9}");
+        let empty: SmallIntMap<~str> = SmallIntMap::new();
+        assert!(empty.invert().is_empty());
+    }
+
+    #[test]
+    fn test_swap_keys() {
+        let mut m = SmallIntMap::new();
+        m.insert(1, ~"one");
+        m.insert(4, ~"four");
+        m.swap_keys(1, 4);
+        assert_eq!(m.find(&1), Some(&~"four"));
+        assert_eq!(m.find(&4), Some(&~"one"));
+        // presence moves along with the value
+        m.swap_keys(4, 9);
+        assert!(!m.contains_key(&4));
+        assert_eq!(m.find(&9), Some(&~"one"));
+        // swapping two absent keys, in or out of range, is a no-op
+        m.swap_keys(2, 100);
+        assert!(!m.contains_key(&2));
+        assert!(!m.contains_key(&100));
+        m.swap_keys(9, 9);
+        assert_eq!(m.find(&9), Some(&~"one"));
+        assert_eq!(m.len(), 2);
+    }
+
+    #[test]
+    fn test_truncate_keys_above() {
+        let mut m = SmallIntMap::new();
+        m.insert(1, 'a');
+        m.insert(4, 'b');
+        m.insert(7, 'c');
+        m.insert(9, 'd');
+        // a cutoff past the end drops nothing
+        assert_eq!(m.truncate_keys_above(50), 0);
+        assert_eq!(m.len(), 4);
+        assert_eq!(m.truncate_keys_above(5), 2);
+        assert_eq!(m.len(), 2);
+        assert!(m.contains_key(&1));
+        assert!(m.contains_key(&4));
+        assert!(!m.contains_key(&7));
+        assert!(!m.contains_key(&9));
+        // the backing vector shrank, so reinsertion regrows it
+        assert!(m.insert(7, 'e'));
+        assert_eq!(m.truncate_keys_above(0), 3);
+        assert!(m.is_empty());
+    }
+
+    #[test]
+    fn test_to_str() {
+        let mut m = SmallIntMap::new();
+        assert_eq!(m.to_str(), ~"{}");
+        m.insert(7, 20);
+        m.insert(3, 10);
+        assert_eq!(m.to_str(), ~"{3: 10, 7: 20}");
+    }
 
     #[test]
     fn test_find_mut() {
@@ -498,6 +1506,14 @@ mod tests {
         assert!(map.find(&7).is_none());
     }
 
+    #[test]
+    fn test_memory_usage() {
+        let mut m = SmallIntMap::new();
+        assert_eq!(m.memory_usage(), 0);
+        assert!(m.insert(100, 1));
+        assert!(m.memory_usage() >= 101 * sys::size_of::<Option<int>>());
+    }
+
     #[test]
     fn test_swap() {
         let mut m = SmallIntMap::new();
@@ -535,12 +1551,310 @@ mod tests {
     }
 }
 
+#[cfg(test)]
+mod test_multimap {
+
+    use super::SmallIntMultiMap;
+
+    #[test]
+    fn test_basic() {
+        let mut m = SmallIntMultiMap::new();
+        assert!(m.is_empty());
+        assert_eq!(m.key_len(), 0);
+
+        m.insert(3, ~"a");
+        m.insert(3, ~"b");
+        m.insert(10, ~"c");
+        assert_eq!(m.len(), 3);
+        assert_eq!(m.key_len(), 2);
+        assert_eq!(m.get(&3), &[~"a", ~"b"]);
+        assert_eq!(m.get(&10), &[~"c"]);
+        assert!(m.get(&4).is_empty());
+        assert!(m.get(&1000).is_empty());
+    }
+
+    #[test]
+    fn test_remove_value() {
+        let mut m = SmallIntMultiMap::new();
+        m.insert(2, 10);
+        m.insert(2, 20);
+        m.insert(2, 10);
+
+        assert!(m.contains_value(&2, &10));
+        assert!(m.remove_value(&2, &10));
+        // only the first matching value goes
+        assert_eq!(m.get(&2), &[20, 10]);
+        assert!(m.remove_value(&2, &10));
+        assert!(!m.remove_value(&2, &10));
+        assert!(!m.remove_value(&99, &10));
+        assert_eq!(m.len(), 1);
+    }
+
+    #[test]
+    fn test_pop_key() {
+        let mut m = SmallIntMultiMap::new();
+        m.insert(5, 1);
+        m.insert(5, 2);
+        m.insert(6, 3);
+        assert_eq!(m.pop_key(&5), Some(~[1, 2]));
+        assert_eq!(m.pop_key(&5), None);
+        assert_eq!(m.pop_key(&50), None);
+        assert_eq!(m.len(), 1);
+        assert_eq!(m.key_len(), 1);
+    }
+
+    #[test]
+    fn test_each_group() {
+        let mut m = SmallIntMultiMap::new();
+        m.insert(7, 70);
+        m.insert(1, 10);
+        m.insert(7, 71);
+        let mut observed = ~[];
+        for m.each_group |&k, group| {
+            observed.push((k, group.to_owned()));
+        }
+        assert_eq!(observed, ~[(1u, ~[10]), (7u, ~[70, 71])]);
+    }
+
+    #[test]
+    fn test_each() {
+        let mut m = SmallIntMultiMap::new();
+        m.insert(4, 40);
+        m.insert(0, 0);
+        m.insert(4, 41);
+        let mut observed = ~[];
+        for m.each |&k, &v| {
+            observed.push((k, v));
+        }
+        assert_eq!(observed, ~[(0u, 0), (4u, 40), (4u, 41)]);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut m = SmallIntMultiMap::new();
+        m.insert(1, 1);
+        m.insert(2, 2);
+        m.clear();
+        assert!(m.is_empty());
+        assert!(m.get(&1).is_empty());
+        assert_eq!(m.key_len(), 0);
+    }
+}
+
+#[cfg(test)]
+mod test_map2 {
+
+    use super::SmallIntMap2;
+
+    #[test]
+    fn test_basic() {
+        let mut m = SmallIntMap2::new();
+        assert!(m.is_empty());
+        assert!(m.insert(2, 3, ~"a"));
+        assert!(m.insert(0, 0, ~"b"));
+        assert!(!m.insert(2, 3, ~"c"));
+        assert_eq!(m.len(), 2);
+        assert_eq!(m.find(2, 3), Some(&~"c"));
+        assert_eq!(m.find(0, 0), Some(&~"b"));
+        assert_eq!(m.find(3, 2), None);
+        assert_eq!(m.find(100, 100), None);
+        assert!(m.contains_key(2, 3));
+        assert!(!m.contains_key(2, 4));
+    }
+
+    #[test]
+    fn test_find_mut() {
+        let mut m = SmallIntMap2::new();
+        assert!(m.insert(1, 5, 10));
+        match m.find_mut(1, 5) {
+            Some(x) => *x = 99,
+            None => fail!()
+        }
+        assert_eq!(m.find(1, 5), Some(&99));
+        assert!(m.find_mut(5, 1).is_none());
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut m = SmallIntMap2::new();
+        assert!(m.insert(4, 4, 16));
+        assert_eq!(m.pop(4, 4), Some(16));
+        assert_eq!(m.pop(4, 4), None);
+        assert_eq!(m.pop(9, 9), None);
+        assert!(m.is_empty());
+    }
+
+    #[test]
+    fn test_each_row_major() {
+        let mut m = SmallIntMap2::new();
+        assert!(m.insert(1, 2, 12));
+        assert!(m.insert(0, 7, 7));
+        assert!(m.insert(1, 0, 10));
+        let mut observed = ~[];
+        for m.each |r, c, &v| {
+            observed.push((r, c, v));
+        }
+        assert_eq!(observed, ~[(0u, 7u, 7), (1u, 0u, 10), (1u, 2u, 12)]);
+    }
+
+    #[test]
+    fn test_row_and_col_iteration() {
+        let mut m = SmallIntMap2::new();
+        assert!(m.insert(2, 0, 1));
+        assert!(m.insert(2, 5, 2));
+        assert!(m.insert(0, 5, 3));
+        assert!(m.insert(3, 5, 4));
+
+        let mut row = ~[];
+        for m.each_in_row(2) |c, &v| {
+            row.push((c, v));
+        }
+        assert_eq!(row, ~[(0u, 1), (5u, 2)]);
+
+        let mut col = ~[];
+        for m.each_in_col(5) |r, &v| {
+            col.push((r, v));
+        }
+        assert_eq!(col, ~[(0u, 3), (2u, 2), (3u, 4)]);
+
+        // rows that were never touched iterate as empty
+        assert!(m.each_in_row(50, |_, _| fail!()));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut m = SmallIntMap2::new();
+        assert!(m.insert(1, 1, 1));
+        m.clear();
+        assert!(m.is_empty());
+        assert_eq!(m.find(1, 1), None);
+    }
+}
+
+#[cfg(test)]
+mod test_union_find {
+
+    use super::UnionFind;
+
+    #[test]
+    fn test_basic() {
+        let mut uf = UnionFind::new(6);
+        assert_eq!(uf.len(), 6);
+        assert_eq!(uf.set_count(), 6);
+        assert!(!uf.same_set(0, 1));
+
+        assert!(uf.union(0, 1));
+        assert!(uf.union(2, 3));
+        assert!(uf.union(0, 3));
+        assert!(!uf.union(1, 2));
+        assert_eq!(uf.set_count(), 3);
+
+        assert!(uf.same_set(1, 3));
+        assert!(!uf.same_set(1, 4));
+        assert_eq!(uf.find(1), uf.find(2));
+    }
+
+    #[test]
+    fn test_push_elem() {
+        let mut uf = UnionFind::new(2);
+        let e = uf.push_elem();
+        assert_eq!(e, 2);
+        assert_eq!(uf.set_count(), 3);
+        assert!(uf.union(e, 0));
+        assert!(uf.same_set(2, 0));
+    }
+
+    #[test]
+    fn test_set_of() {
+        let mut uf = UnionFind::new(5);
+        uf.union(0, 2);
+        uf.union(2, 4);
+        let evens = uf.set_of(4);
+        assert_eq!(evens.len(), 3);
+        assert!(evens.contains(&0));
+        assert!(evens.contains(&2));
+        assert!(evens.contains(&4));
+        assert!(!evens.contains(&1));
+    }
+
+    #[test]
+    fn test_sets() {
+        let mut uf = UnionFind::new(4);
+        uf.union(0, 1);
+        let all = uf.sets();
+        assert_eq!(all.len(), 3);
+        let mut total = 0;
+        for all.each |_, members| {
+            total += members.len();
+        }
+        assert_eq!(total, 4);
+    }
+}
+
 #[cfg(test)]
 mod test_set {
 
-    use super::SmallIntSet;
+    use super::{SmallIntMap, SmallIntSet};
+    use bitv::BitvSet;
     use std::iterator::FromIterator;
 
+    #[test]
+    fn test_basic() {
+        let mut s = SmallIntSet::new();
+        assert!(s.insert(3));
+        assert!(!s.insert(3));
+        assert!(s.contains(&3));
+        assert!(s.insert(400));
+        assert!(!s.insert(400));
+        assert!(s.contains(&400));
+        assert_eq!(s.len(), 2);
+        assert!(s.remove(&3));
+        assert!(!s.remove(&3));
+        assert_eq!(s.len(), 1);
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut s = SmallIntSet::new();
+        assert!(s.toggle(7));
+        assert!(s.contains(&7));
+        assert_eq!(s.len(), 1);
+        assert!(!s.toggle(7));
+        assert!(!s.contains(&7));
+        assert_eq!(s.len(), 0);
+        // toggling past the current capacity grows the set
+        assert!(s.toggle(900));
+        assert!(s.contains(&900));
+        assert_eq!(s.len(), 1);
+    }
+
+    #[test]
+    fn test_from_iterator() {
+        let values = ~[9u, 1, 5, 9, 200];
+        let s: SmallIntSet = FromIterator::from_iterator(&mut values.iter().transform(|&v| v));
+        assert_eq!(s.len(), 4);
+        for values.iter().advance |v| {
+            assert!(s.contains(v));
+        }
+
+        let mut t = SmallIntSet::with_capacity(201);
+        assert!(t.capacity() >= 201);
+        t.extend(&mut values.iter().transform(|&v| v));
+        assert_eq!(t.len(), 4);
+    }
+
+    #[test]
+    fn test_from_map_keys() {
+        let mut m = SmallIntMap::new();
+        assert!(m.insert(1, 'a'));
+        assert!(m.insert(500, 'b'));
+        let s = SmallIntSet::from_map_keys(&m);
+        assert_eq!(s.len(), 2);
+        assert!(s.contains(&1));
+        assert!(s.contains(&500));
+        assert!(!s.contains(&2));
+    }
+
     #[test]
     fn test_disjoint() {
         let mut xs = SmallIntSet::new();
@@ -591,6 +1905,28 @@ mod test_set {
         assert!(b.is_superset(&a));
     }
 
+    #[test]
+    fn test_proper_subset_and_superset() {
+        let mut a = SmallIntSet::new();
+        assert!(a.insert(0));
+        assert!(a.insert(5));
+
+        let mut b = SmallIntSet::new();
+        assert!(b.insert(0));
+        assert!(b.insert(5));
+
+        assert!(a.is_subset(&b));
+        assert!(!a.is_proper_subset(&b));
+        assert!(!b.is_proper_superset(&a));
+
+        assert!(b.insert(70));
+
+        assert!(a.is_proper_subset(&b));
+        assert!(b.is_proper_superset(&a));
+        assert!(!b.is_proper_subset(&a));
+        assert!(!a.is_proper_superset(&b));
+    }
+
     #[test]
     fn test_intersection() {
         let mut a = SmallIntSet::new();
@@ -695,6 +2031,57 @@ mod test_set {
         assert_eq!(i, expected.len());
     }
 
+    #[test]
+    fn test_bitv_set_conversions() {
+        let mut s = SmallIntSet::new();
+        assert!(s.insert(0));
+        assert!(s.insert(11));
+        assert!(s.insert(300));
+
+        let b = s.to_bitv_set();
+        assert_eq!(b.len(), 3);
+        assert!(b.contains(&0));
+        assert!(b.contains(&11));
+        assert!(b.contains(&300));
+
+        let t = SmallIntSet::from_bitv_set(&b);
+        assert_eq!(t.len(), 3);
+        assert!(t.contains(&0));
+        assert!(t.contains(&11));
+        assert!(t.contains(&300));
+        assert!(!t.contains(&12));
+
+        // and again via the constructors on BitvSet
+        let b2 = BitvSet::from_small_int_set(&t);
+        assert!(b2.contains(&300));
+        let u = b2.to_small_int_set();
+        assert_eq!(u.len(), 3);
+    }
+
+    #[test]
+    fn test_operation_lens() {
+        let mut a = SmallIntSet::new();
+        let mut b = SmallIntSet::new();
+
+        assert!(a.insert(1));
+        assert!(a.insert(3));
+        assert!(a.insert(5));
+        assert!(a.insert(200));
+
+        assert!(b.insert(3));
+        assert!(b.insert(5));
+        assert!(b.insert(14));
+
+        assert_eq!(a.union_len(&b), 5);
+        assert_eq!(b.union_len(&a), 5);
+        assert_eq!(a.intersection_len(&b), 2);
+        assert_eq!(b.intersection_len(&a), 2);
+        assert_eq!(a.difference_len(&b), 2);
+        assert_eq!(b.difference_len(&a), 1);
+        assert_eq!(a.symmetric_difference_len(&b), 3);
+        assert_eq!(b.symmetric_difference_len(&a), 3);
+    }
+
     #[test]
     fn test_iter() {
         let mut a = SmallIntSet::new();
@@ -714,4 +2101,50 @@ mod test_set {
         let b: ~[uint] = FromIterator::from_iterator(&mut a.rev_iter());
         assert_eq!(b, ~[5,3,1]);
     }
+
+    #[test]
+    fn test_json() {
+        use json;
+        use serialize::{Encodable, Decodable};
+        use std::io;
+
+        let mut s = SmallIntSet::new();
+        s.insert(0);
+        s.insert(44);
+        let text = do io::with_str_writer |wr| {
+            let mut encoder = json::Encoder(wr);
+            s.encode(&mut encoder);
+        };
+        assert_eq!(text, ~"[0,44]");
+        let mut decoder = json::Decoder(json::from_str(text).unwrap());
+        let decoded: SmallIntSet = Decodable::decode(&mut decoder);
+        assert_eq!(decoded.len(), 2);
+        assert!(decoded.contains(&0));
+        assert!(decoded.contains(&44));
+    }
+
+    #[test]
+    fn test_bit_collection() {
+        use bitv::BitCollection;
+
+        let mut a = SmallIntSet::new();
+        assert!(a.add_bit(2));
+        assert!(a.add_bit(70));
+        assert!(!a.add_bit(70));
+        let mut b = SmallIntSet::new();
+        b.add_bit(2);
+        b.add_bit(9);
+        assert!(a.union_bits(&b));
+        assert!(!a.union_bits(&b));
+        let mut observed = ~[];
+        for a.each_bit |v| {
+            observed.push(v);
+        }
+        assert_eq!(observed, ~[2u, 9, 70]);
+        assert!(a.intersect_bits(&b));
+        assert_eq!(a.len(), 2);
+        assert!(!a.has_bit(70));
+        assert!(a.remove_bit(9));
+        assert!(!a.remove_bit(9));
+    }
 }
@@ -0,0 +1,337 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A hierarchical sparse bitset for huge universes. Elements are grouped
+ * into fixed-size leaf blocks of packed words, and only populated blocks
+ * are allocated, held in an ordered map keyed by block number. Sets over
+ * universes of hundreds of millions of IDs use memory proportional to the
+ * number of populated regions, and iteration visits only allocated
+ * blocks, unlike `BitvSet` which allocates up to the maximum element.
+ */
+
+use bitv::iterate_bits;
+use treemap::TreeMap;
+
+use std::uint;
+use std::vec;
+
+/// Number of words in one leaf block
+static LEAF_WORDS: uint = 32;
+
+/// The sparse bitset type
+pub struct SparseBitvSet {
+    /// Populated leaf blocks, keyed by block number
+    priv blocks: TreeMap<uint, ~[uint]>,
+    /// The number of elements in the set
+    priv size: uint
+}
+
+/// The number of bits covered by one leaf block
+fn leaf_bits() -> uint { LEAF_WORDS * uint::bits }
+
+impl Container for SparseBitvSet {
+    /// Return the number of elements in the set
+    fn len(&self) -> uint { self.size }
+
+    /// Return true if the set contains no elements
+    fn is_empty(&self) -> bool { self.size == 0 }
+}
+
+impl Mutable for SparseBitvSet {
+    /// Clear the set, deallocating every leaf block
+    fn clear(&mut self) {
+        self.blocks.clear();
+        self.size = 0;
+    }
+}
+
+impl SparseBitvSet {
+    /// Create an empty SparseBitvSet; no leaf blocks are allocated until
+    /// the first insertion
+    pub fn new() -> SparseBitvSet {
+        SparseBitvSet{blocks: TreeMap::new(), size: 0}
+    }
+
+    /// The number of leaf blocks currently allocated
+    pub fn block_count(&self) -> uint { self.blocks.len() }
+
+    /// Return true if the set contains a value
+    pub fn contains(&self, value: &uint) -> bool {
+        let block = *value / leaf_bits();
+        let bit = *value % leaf_bits();
+        match self.blocks.find(&block) {
+            None => false,
+            Some(words) => {
+                words[bit / uint::bits] & (1 << (bit % uint::bits)) != 0
+            }
+        }
+    }
+
+    /// Add a value to the set, allocating its leaf block on first use.
+    /// Return true if the value was not already present in the set.
+    pub fn insert(&mut self, value: uint) -> bool {
+        let block = value / leaf_bits();
+        let bit = value % leaf_bits();
+        let flag = 1 << (bit % uint::bits);
+        let inserted = match self.blocks.find_mut(&block) {
+            Some(words) => {
+                if words[bit / uint::bits] & flag != 0 {
+                    false
+                } else {
+                    words[bit / uint::bits] |= flag;
+                    true
+                }
+            }
+            None => {
+                let mut words = vec::from_elem(LEAF_WORDS, 0u);
+                words[bit / uint::bits] = flag;
+                self.blocks.insert(block, words);
+                true
+            }
+        };
+        if inserted {
+            self.size += 1;
+        }
+        inserted
+    }
+
+    /// Remove a value from the set, deallocating its leaf block if it
+    /// becomes empty. Return true if the value was present in the set.
+    pub fn remove(&mut self, value: &uint) -> bool {
+        let block = *value / leaf_bits();
+        let bit = *value % leaf_bits();
+        let flag = 1 << (bit % uint::bits);
+        let mut now_empty = false;
+        let removed = match self.blocks.find_mut(&block) {
+            None => false,
+            Some(words) => {
+                if words[bit / uint::bits] & flag == 0 {
+                    false
+                } else {
+                    words[bit / uint::bits] &= !flag;
+                    now_empty = words.iter().all(|&w| w == 0);
+                    true
+                }
+            }
+        };
+        if removed {
+            self.size -= 1;
+            if now_empty {
+                self.blocks.remove(&block);
+            }
+        }
+        removed
+    }
+
+    /// Visit all values in order. Only populated blocks are examined, so
+    /// the cost is proportional to the population of the set.
+    pub fn each(&self, f: &fn(&uint) -> bool) -> bool {
+        for self.blocks.iter().advance |(&block, words)| {
+            let base = block * leaf_bits();
+            for words.iter().enumerate().advance |(i, &w)| {
+                if !iterate_bits(base + i * uint::bits, w, |b| f(&b)) {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+
+    /// Return true if the set has no elements in common with `other`
+    pub fn is_disjoint(&self, other: &SparseBitvSet) -> bool {
+        for self.each |v| {
+            if other.contains(v) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Return true if every element of the set is in `other`
+    pub fn is_subset(&self, other: &SparseBitvSet) -> bool {
+        for self.each |v| {
+            if !other.contains(v) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Return true if every element of `other` is in the set
+    pub fn is_superset(&self, other: &SparseBitvSet) -> bool {
+        other.is_subset(self)
+    }
+
+    /// The sorted block numbers populated in either of the two sets
+    fn merged_blocks(&self, other: &SparseBitvSet) -> ~[uint] {
+        let mut bases = ~[];
+        for self.blocks.each_key |&b| { bases.push(b); }
+        let mut merged = ~[];
+        let mut i = 0;
+        for other.blocks.each_key |&b| {
+            while i < bases.len() && bases[i] < b {
+                merged.push(bases[i]);
+                i += 1;
+            }
+            if i < bases.len() && bases[i] == b {
+                i += 1;
+            }
+            merged.push(b);
+        }
+        while i < bases.len() {
+            merged.push(bases[i]);
+            i += 1;
+        }
+        merged
+    }
+
+    /// Visit the set bits of a word-wise binary operation against `other`,
+    /// in order, touching only blocks populated in either set
+    fn binop_each(&self, other: &SparseBitvSet, op: &fn(uint, uint) -> uint,
+                  f: &fn(&uint) -> bool) -> bool {
+        for self.merged_blocks(other).iter().advance |&block| {
+            let w1 = self.blocks.find(&block);
+            let w2 = other.blocks.find(&block);
+            let base = block * leaf_bits();
+            for uint::range(0, LEAF_WORDS) |i| {
+                let a = match w1 { Some(ws) => ws[i], None => 0 };
+                let b = match w2 { Some(ws) => ws[i], None => 0 };
+                if !iterate_bits(base + i * uint::bits, op(a, b), |v| f(&v)) {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+
+    /// Visit the values representing the union, in order
+    pub fn union(&self, other: &SparseBitvSet, f: &fn(&uint) -> bool) -> bool {
+        self.binop_each(other, |a, b| a | b, f)
+    }
+
+    /// Visit the values representing the intersection, in order
+    pub fn intersection(&self, other: &SparseBitvSet,
+                        f: &fn(&uint) -> bool) -> bool {
+        self.binop_each(other, |a, b| a & b, f)
+    }
+
+    /// Visit the values representing the difference, in order
+    pub fn difference(&self, other: &SparseBitvSet,
+                      f: &fn(&uint) -> bool) -> bool {
+        self.binop_each(other, |a, b| a & !b, f)
+    }
+
+    /// Visit the values representing the symmetric difference, in order
+    pub fn symmetric_difference(&self, other: &SparseBitvSet,
+                                f: &fn(&uint) -> bool) -> bool {
+        self.binop_each(other, |a, b| a ^ b, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic() {
+        let mut s = SparseBitvSet::new();
+        assert!(s.is_empty());
+        assert_eq!(s.block_count(), 0);
+
+        assert!(s.insert(3));
+        assert!(!s.insert(3));
+        assert!(s.insert(400_000_000));
+        assert!(s.contains(&3));
+        assert!(s.contains(&400_000_000));
+        assert!(!s.contains(&4));
+        assert_eq!(s.len(), 2);
+        assert_eq!(s.block_count(), 2);
+
+        assert!(s.remove(&3));
+        assert!(!s.remove(&3));
+        assert_eq!(s.block_count(), 1);
+        assert_eq!(s.len(), 1);
+    }
+
+    #[test]
+    fn test_each_in_order() {
+        let mut s = SparseBitvSet::new();
+        assert!(s.insert(1_000_000));
+        assert!(s.insert(5));
+        assert!(s.insert(70));
+        let mut observed = ~[];
+        for s.each |&v| {
+            observed.push(v);
+        }
+        assert_eq!(observed, ~[5u, 70, 1_000_000]);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut s = SparseBitvSet::new();
+        assert!(s.insert(42));
+        assert!(s.insert(9_999_999));
+        s.clear();
+        assert!(s.is_empty());
+        assert_eq!(s.block_count(), 0);
+        assert!(!s.contains(&42));
+    }
+
+    #[test]
+    fn test_set_relations() {
+        let mut a = SparseBitvSet::new();
+        let mut b = SparseBitvSet::new();
+        assert!(a.insert(1));
+        assert!(a.insert(100_000_000));
+        assert!(b.insert(1));
+        assert!(b.insert(100_000_000));
+        assert!(b.insert(7));
+
+        assert!(a.is_subset(&b));
+        assert!(b.is_superset(&a));
+        assert!(!b.is_subset(&a));
+        assert!(!a.is_disjoint(&b));
+    }
+
+    #[test]
+    fn test_union_and_intersection() {
+        let mut a = SparseBitvSet::new();
+        let mut b = SparseBitvSet::new();
+        assert!(a.insert(1));
+        assert!(a.insert(50_000_000));
+        assert!(b.insert(2));
+        assert!(b.insert(50_000_000));
+
+        let mut union = ~[];
+        for a.union(&b) |&v| {
+            union.push(v);
+        }
+        assert_eq!(union, ~[1u, 2, 50_000_000]);
+
+        let mut inter = ~[];
+        for a.intersection(&b) |&v| {
+            inter.push(v);
+        }
+        assert_eq!(inter, ~[50_000_000u]);
+
+        let mut diff = ~[];
+        for a.difference(&b) |&v| {
+            diff.push(v);
+        }
+        assert_eq!(diff, ~[1u]);
+
+        let mut sym = ~[];
+        for a.symmetric_difference(&b) |&v| {
+            sym.push(v);
+        }
+        assert_eq!(sym, ~[1u, 2]);
+    }
+}
@@ -0,0 +1,405 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A radix trie keyed by uint, branching on four key bits per level from
+ * the most significant end. Memory is proportional to the number of
+ * entries rather than to the largest key, iteration is in key order for
+ * free, and successor queries need one root-to-leaf walk — the natural
+ * alternative to `SmallIntMap` when keys are sparse and huge. The
+ * `Map<uint, V>` interface matches the other integer-keyed maps.
+ */
+
+use std::uint;
+use std::util::{replace, swap};
+
+/// Key bits consumed per trie level
+static SHIFT: uint = 4;
+/// Trie fanout
+static SIZE: uint = 1 << SHIFT;
+static MASK: uint = SIZE - 1;
+
+enum Child<V> {
+    Internal(~TrieNode<V>),
+    External(uint, V),
+    Nothing
+}
+
+struct TrieNode<V> {
+    /// The number of non-Nothing children
+    count: uint,
+    children: [Child<V>, ..SIZE]
+}
+
+/// The bits of `n` examined at trie level `idx`
+#[inline]
+fn chunk(n: uint, idx: uint) -> uint {
+    let sh = uint::bits - (SHIFT * (idx + 1));
+    (n >> sh) & MASK
+}
+
+impl<V> TrieNode<V> {
+    fn new() -> TrieNode<V> {
+        TrieNode{
+            count: 0,
+            children: [Nothing, Nothing, Nothing, Nothing,
+                       Nothing, Nothing, Nothing, Nothing,
+                       Nothing, Nothing, Nothing, Nothing,
+                       Nothing, Nothing, Nothing, Nothing]
+        }
+    }
+
+    fn each<'a>(&'a self, f: &fn(&uint, &'a V) -> bool) -> bool {
+        for uint::range(0, SIZE) |idx| {
+            match self.children[idx] {
+                Internal(ref x) => if !x.each(|k, v| f(k, v)) {
+                    return false;
+                },
+                External(k, ref v) => if !f(&k, v) {
+                    return false;
+                },
+                Nothing => ()
+            }
+        }
+        return true;
+    }
+
+    /// The entry with the smallest key in this subtree
+    fn min_entry<'a>(&'a self) -> Option<(uint, &'a V)> {
+        for uint::range(0, SIZE) |idx| {
+            match self.children[idx] {
+                Internal(ref x) => return x.min_entry(),
+                External(k, ref v) => return Some((k, v)),
+                Nothing => ()
+            }
+        }
+        None
+    }
+
+    /// The entry with the smallest key strictly greater than `key`
+    fn successor<'a>(&'a self, key: uint,
+                     idx: uint) -> Option<(uint, &'a V)> {
+        let c = chunk(key, idx);
+        match self.children[c] {
+            Internal(ref x) => {
+                match x.successor(key, idx + 1) {
+                    Some(result) => return Some(result),
+                    None => ()
+                }
+            }
+            External(stored, ref value) => {
+                if stored > key {
+                    return Some((stored, value));
+                }
+            }
+            Nothing => ()
+        }
+        // no luck sharing a prefix; take the least entry of any
+        // later sibling
+        let mut i = c + 1;
+        while i < SIZE {
+            match self.children[i] {
+                Internal(ref x) => return x.min_entry(),
+                External(k, ref v) => return Some((k, v)),
+                Nothing => ()
+            }
+            i += 1;
+        }
+        None
+    }
+}
+
+fn insert<V>(count: &mut uint, child: &mut Child<V>, key: uint, value: V,
+             idx: uint) -> Option<V> {
+    let mut tmp = Nothing;
+    let ret;
+    swap(&mut tmp, child);
+
+    *child = match tmp {
+        External(stored_key, stored_value) => {
+            if stored_key == key {
+                ret = Some(stored_value);
+                External(stored_key, value)
+            } else {
+                // the slot is contested: push both entries down a level
+                let mut new = ~TrieNode::new();
+                insert(&mut new.count,
+                       &mut new.children[chunk(stored_key, idx)],
+                       stored_key, stored_value, idx + 1);
+                insert(&mut new.count,
+                       &mut new.children[chunk(key, idx)],
+                       key, value, idx + 1);
+                ret = None;
+                Internal(new)
+            }
+        }
+        Internal(x) => {
+            let mut x = x;
+            ret = insert(&mut x.count, &mut x.children[chunk(key, idx)],
+                         key, value, idx + 1);
+            Internal(x)
+        }
+        Nothing => {
+            *count += 1;
+            ret = None;
+            External(key, value)
+        }
+    };
+    return ret;
+}
+
+fn remove<V>(count: &mut uint, child: &mut Child<V>, key: uint,
+             idx: uint) -> Option<V> {
+    let (ret, emptied) = match *child {
+        External(stored, _) if stored == key => {
+            match replace(child, Nothing) {
+                External(_, value) => (Some(value), true),
+                _ => fail!()
+            }
+        }
+        External(*) => (None, false),
+        Internal(ref mut x) => {
+            let ret = remove(&mut x.count,
+                             &mut x.children[chunk(key, idx)],
+                             key, idx + 1);
+            (ret, x.count == 0)
+        }
+        Nothing => (None, false)
+    };
+    if emptied {
+        *child = Nothing;
+        *count -= 1;
+    }
+    ret
+}
+
+fn find_mut<'r, V>(child: &'r mut Child<V>, key: uint,
+                   idx: uint) -> Option<&'r mut V> {
+    match *child {
+        External(stored, ref mut value) if stored == key => Some(value),
+        External(*) => None,
+        Internal(ref mut x) => {
+            find_mut(&mut x.children[chunk(key, idx)], key, idx + 1)
+        }
+        Nothing => None
+    }
+}
+
+/// The radix trie map type
+pub struct TrieIntMap<V> {
+    priv root: TrieNode<V>,
+    priv length: uint
+}
+
+impl<V> Container for TrieIntMap<V> {
+    /// Return the number of elements in the map
+    fn len(&self) -> uint { self.length }
+
+    /// Return true if the map contains no elements
+    fn is_empty(&self) -> bool { self.length == 0 }
+}
+
+impl<V> Mutable for TrieIntMap<V> {
+    /// Clear the map, removing all values
+    fn clear(&mut self) {
+        self.root = TrieNode::new();
+        self.length = 0;
+    }
+}
+
+impl<V> Map<uint, V> for TrieIntMap<V> {
+    /// Return true if the map contains a value for the specified key
+    fn contains_key(&self, key: &uint) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// Return a reference to the value corresponding to the key
+    fn find<'a>(&'a self, key: &uint) -> Option<&'a V> {
+        let mut node: &'a TrieNode<V> = &self.root;
+        let mut idx = 0;
+        loop {
+            match node.children[chunk(*key, idx)] {
+                Internal(ref x) => node = &**x,
+                External(stored, ref value) => {
+                    if stored == *key {
+                        return Some(value);
+                    } else {
+                        return None;
+                    }
+                }
+                Nothing => return None
+            }
+            idx += 1;
+        }
+    }
+
+    /// Return a mutable reference to the value corresponding to the key
+    fn find_mut<'a>(&'a mut self, key: &uint) -> Option<&'a mut V> {
+        find_mut(&mut self.root.children[chunk(*key, 0)], *key, 1)
+    }
+
+    /// Insert a key-value pair into the map. An existing value for a
+    /// key is replaced by the new value. Return true if the key did
+    /// not already exist in the map.
+    fn insert(&mut self, key: uint, value: V) -> bool {
+        self.swap(key, value).is_none()
+    }
+
+    /// Remove a key-value pair from the map. Return true if the key
+    /// was present in the map, otherwise false.
+    fn remove(&mut self, key: &uint) -> bool {
+        self.pop(key).is_some()
+    }
+
+    /// Insert a key-value pair from the map. If the key already had a
+    /// value present in the map, that value is returned. Otherwise None
+    /// is returned.
+    fn swap(&mut self, key: uint, value: V) -> Option<V> {
+        let ret = insert(&mut self.root.count,
+                         &mut self.root.children[chunk(key, 0)],
+                         key, value, 1);
+        if ret.is_none() {
+            self.length += 1;
+        }
+        ret
+    }
+
+    /// Removes a key from the map, returning the value at the key if
+    /// the key was previously in the map.
+    fn pop(&mut self, key: &uint) -> Option<V> {
+        let ret = remove(&mut self.root.count,
+                         &mut self.root.children[chunk(*key, 0)],
+                         *key, 1);
+        if ret.is_some() {
+            self.length -= 1;
+        }
+        ret
+    }
+}
+
+impl<V> TrieIntMap<V> {
+    /// Create an empty TrieIntMap
+    pub fn new() -> TrieIntMap<V> {
+        TrieIntMap{root: TrieNode::new(), length: 0}
+    }
+
+    /// Visit all key-value pairs in key order
+    pub fn each<'a>(&'a self, f: &fn(&uint, &'a V) -> bool) -> bool {
+        self.root.each(f)
+    }
+
+    /// Visit all keys in order
+    pub fn each_key(&self, f: &fn(&uint) -> bool) -> bool {
+        self.each(|k, _| f(k))
+    }
+
+    /// Visit all values in key order
+    pub fn each_value<'a>(&'a self, f: &fn(&'a V) -> bool) -> bool {
+        self.each(|_, v| f(v))
+    }
+
+    /// The entry with the smallest key, if any
+    pub fn min<'a>(&'a self) -> Option<(uint, &'a V)> {
+        self.root.min_entry()
+    }
+
+    /// The entry with the smallest key strictly greater than `key`
+    pub fn successor<'a>(&'a self, key: uint) -> Option<(uint, &'a V)> {
+        self.root.successor(key, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::uint;
+
+    #[test]
+    fn test_basic() {
+        let mut m = TrieIntMap::new();
+        assert!(m.is_empty());
+        assert!(m.insert(5, ~"five"));
+        assert!(m.insert(uint::max_value - 5, ~"huge"));
+        assert!(m.insert(0, ~"zero"));
+        assert!(!m.insert(5, ~"FIVE"));
+        assert_eq!(m.len(), 3);
+        assert_eq!(m.find(&5), Some(&~"FIVE"));
+        assert_eq!(m.find(&1), None);
+        assert!(m.contains_key(&0));
+    }
+
+    #[test]
+    fn test_find_mut() {
+        let mut m = TrieIntMap::new();
+        assert!(m.insert(1_000_000, 10));
+        match m.find_mut(&1_000_000) {
+            Some(v) => *v += 1,
+            None => fail!()
+        }
+        assert_eq!(m.find(&1_000_000), Some(&11));
+        assert!(m.find_mut(&999_999).is_none());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut m = TrieIntMap::new();
+        assert!(m.insert(17, 1));
+        assert!(m.insert(16, 2));
+        assert!(m.remove(&17));
+        assert!(!m.remove(&17));
+        assert_eq!(m.find(&17), None);
+        assert_eq!(m.find(&16), Some(&2));
+        assert_eq!(m.pop(&16), Some(2));
+        assert!(m.is_empty());
+    }
+
+    #[test]
+    fn test_each_in_key_order() {
+        let mut m = TrieIntMap::new();
+        assert!(m.insert(0x10_0000, 'c'));
+        assert!(m.insert(3, 'a'));
+        assert!(m.insert(0x10_0001, 'd'));
+        assert!(m.insert(1000, 'b'));
+        let mut observed = ~[];
+        for m.each |&k, &v| {
+            observed.push((k, v));
+        }
+        assert_eq!(observed,
+                   ~[(3u, 'a'), (1000u, 'b'),
+                     (0x10_0000u, 'c'), (0x10_0001u, 'd')]);
+    }
+
+    #[test]
+    fn test_successor() {
+        let mut m = TrieIntMap::new();
+        assert!(m.insert(10, 'a'));
+        assert!(m.insert(4096, 'b'));
+        assert!(m.insert(4097, 'c'));
+
+        assert_eq!(m.min(), Some((10, &'a')));
+        assert_eq!(m.successor(0), Some((10, &'a')));
+        assert_eq!(m.successor(10), Some((4096, &'b')));
+        assert_eq!(m.successor(4096), Some((4097, &'c')));
+        assert_eq!(m.successor(4097), None);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut m = TrieIntMap::new();
+        assert!(m.insert(1, 1));
+        assert!(m.insert(2, 2));
+        m.clear();
+        assert!(m.is_empty());
+        assert_eq!(m.find(&1), None);
+        assert!(m.insert(1, 3));
+        assert_eq!(m.find(&1), Some(&3));
+    }
+}
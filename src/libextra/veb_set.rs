@@ -0,0 +1,485 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A van Emde Boas tree over uint keys. Insert, remove, contains,
+ * successor and predecessor all run in O(log log U) for a universe of
+ * size U. The universe is split recursively into sqrt(U) clusters of
+ * sqrt(U) keys each, bottoming out in single machine words; following
+ * the textbook construction, the minimum of a node is not stored in its
+ * clusters, which is what keeps updates to one recursive call. Space is
+ * proportional to the populated part of the universe, since clusters
+ * are allocated on first use.
+ */
+
+use std::uint;
+use std::vec;
+
+/// The van Emde Boas set type
+pub struct VebSet {
+    /// log2 of the universe size of this node
+    priv ubits: uint,
+    /// The smallest element, stored here and not in any cluster
+    priv min: Option<uint>,
+    /// The largest element (also present in a cluster, unless it is
+    /// the minimum)
+    priv max: Option<uint>,
+    /// The number of elements in this subtree
+    priv size: uint,
+    priv rep: VebRep
+}
+
+enum VebRep {
+    /// A universe of at most `uint::bits` keys, packed into one word
+    Leaf(uint),
+    /// Lazily allocated clusters, and a summary set over the indices of
+    /// the populated clusters
+    Node(~[Option<~VebSet>], ~VebSet)
+}
+
+/// log2 of the number of bits in a word
+fn word_log() -> uint {
+    let mut l = 0;
+    while (1 << l) < uint::bits { l += 1; }
+    l
+}
+
+/// The index of the lowest set bit of a nonzero word
+fn lowest_bit(w: uint) -> uint {
+    let mut i = 0;
+    while w & (1 << i) == 0 { i += 1; }
+    i
+}
+
+/// The index of the highest set bit of a nonzero word
+fn highest_bit(w: uint) -> uint {
+    let mut i = uint::bits - 1;
+    while w & (1 << i) == 0 { i -= 1; }
+    i
+}
+
+impl Container for VebSet {
+    /// Return the number of elements in the set
+    fn len(&self) -> uint { self.size }
+
+    /// Return true if the set contains no elements
+    fn is_empty(&self) -> bool { self.size == 0 }
+}
+
+impl Mutable for VebSet {
+    /// Clear the set, removing all values
+    fn clear(&mut self) {
+        *self = VebSet::new(self.ubits);
+    }
+}
+
+impl VebSet {
+    /// Create an empty set over the universe `[0, 2^ubits)`
+    pub fn new(ubits: uint) -> VebSet {
+        assert!(ubits >= 1 && ubits < uint::bits);
+        let rep = if ubits <= word_log() {
+            Leaf(0)
+        } else {
+            let lo_bits = ubits / 2;
+            let hi_bits = ubits - lo_bits;
+            Node(vec::from_fn(1 << hi_bits, |_| None),
+                 ~VebSet::new(hi_bits))
+        };
+        VebSet{ubits: ubits, min: None, max: None, size: 0, rep: rep}
+    }
+
+    /// The size of the universe
+    pub fn capacity(&self) -> uint { 1 << self.ubits }
+
+    /// The smallest element of the set, if any
+    pub fn min(&self) -> Option<uint> { self.min }
+
+    /// The largest element of the set, if any
+    pub fn max(&self) -> Option<uint> { self.max }
+
+    /// Return true if the set contains a value
+    pub fn contains(&self, value: &uint) -> bool {
+        let x = *value;
+        if x >= self.capacity() {
+            return false;
+        }
+        match self.min {
+            None => return false,
+            Some(m) if m == x => return true,
+            _ => ()
+        }
+        match self.rep {
+            Leaf(bits) => bits & (1 << x) != 0,
+            Node(ref clusters, _) => {
+                let lo_bits = self.ubits / 2;
+                match clusters[x >> lo_bits] {
+                    None => false,
+                    Some(ref c) => c.contains(&(x & ((1 << lo_bits) - 1)))
+                }
+            }
+        }
+    }
+
+    /// Add a value to the set. Return true if the value was not already
+    /// present in the set.
+    pub fn insert(&mut self, value: uint) -> bool {
+        assert!(value < self.capacity());
+        if self.contains(&value) {
+            false
+        } else {
+            self.insert_new(value);
+            true
+        }
+    }
+
+    /// Insert a value known to be absent
+    fn insert_new(&mut self, x: uint) {
+        self.size += 1;
+        match self.rep {
+            Leaf(ref mut bits) => {
+                *bits |= 1 << x;
+                match self.min {
+                    Some(m) if m < x => (),
+                    _ => self.min = Some(x)
+                }
+                match self.max {
+                    Some(m) if m > x => (),
+                    _ => self.max = Some(x)
+                }
+            }
+            Node(ref mut clusters, ref mut summary) => {
+                if self.min.is_none() {
+                    // an empty node absorbs its first element into min
+                    self.min = Some(x);
+                    self.max = Some(x);
+                    return;
+                }
+                let mut x = x;
+                if x < self.min.unwrap() {
+                    // displace the old minimum down into the clusters
+                    let m = self.min.unwrap();
+                    self.min = Some(x);
+                    x = m;
+                }
+                if x > self.max.unwrap() {
+                    self.max = Some(x);
+                }
+                let lo_bits = self.ubits / 2;
+                let h = x >> lo_bits;
+                let l = x & ((1 << lo_bits) - 1);
+                if clusters[h].is_none() {
+                    clusters[h] = Some(~VebSet::new(lo_bits));
+                    summary.insert_new(h);
+                }
+                clusters[h].get_mut_ref().insert_new(l);
+            }
+        }
+    }
+
+    /// Remove a value from the set. Return true if the value was
+    /// present in the set.
+    pub fn remove(&mut self, value: &uint) -> bool {
+        if self.contains(value) {
+            self.remove_present(*value);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Remove a value known to be present
+    fn remove_present(&mut self, x: uint) {
+        self.size -= 1;
+        match self.rep {
+            Leaf(ref mut bits) => {
+                *bits &= !(1 << x);
+                if *bits == 0 {
+                    self.min = None;
+                    self.max = None;
+                } else {
+                    self.min = Some(lowest_bit(*bits));
+                    self.max = Some(highest_bit(*bits));
+                }
+            }
+            Node(ref mut clusters, ref mut summary) => {
+                if self.min == self.max {
+                    self.min = None;
+                    self.max = None;
+                    return;
+                }
+                let lo_bits = self.ubits / 2;
+                let mut x = x;
+                if Some(x) == self.min {
+                    // promote the smallest clustered element to be the
+                    // new minimum, and remove it from its cluster instead
+                    let h = summary.min.unwrap();
+                    let l = clusters[h].get_ref().min.unwrap();
+                    x = (h << lo_bits) | l;
+                    self.min = Some(x);
+                }
+                let h = x >> lo_bits;
+                clusters[h].get_mut_ref()
+                           .remove_present(x & ((1 << lo_bits) - 1));
+                if clusters[h].get_ref().min.is_none() {
+                    clusters[h] = None;
+                    summary.remove_present(h);
+                }
+                if Some(x) == self.max {
+                    match summary.max {
+                        None => self.max = self.min,
+                        Some(hs) => {
+                            let ls = clusters[hs].get_ref().max.unwrap();
+                            self.max = Some((hs << lo_bits) | ls);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The smallest element strictly greater than `x`, if any; `x` may
+    /// lie outside the universe
+    pub fn successor(&self, x: uint) -> Option<uint> {
+        if x >= self.capacity() - 1 {
+            // nothing above x fits in the universe
+            return None;
+        }
+        match self.rep {
+            Leaf(bits) => {
+                if x + 1 >= uint::bits {
+                    return None;
+                }
+                let above = bits & (uint::max_value << (x + 1));
+                if above == 0 { None } else { Some(lowest_bit(above)) }
+            }
+            Node(ref clusters, ref summary) => {
+                match self.min {
+                    Some(m) if x < m => return Some(m),
+                    _ => ()
+                }
+                let lo_bits = self.ubits / 2;
+                let h = x >> lo_bits;
+                let l = x & ((1 << lo_bits) - 1);
+                match clusters[h] {
+                    Some(ref c) if c.max.is_some() && l < c.max.unwrap() => {
+                        // the successor lives in the same cluster as x
+                        return Some((h << lo_bits) | c.successor(l).unwrap());
+                    }
+                    _ => ()
+                }
+                match summary.successor(h) {
+                    None => None,
+                    Some(hs) => {
+                        let c = clusters[hs].get_ref();
+                        Some((hs << lo_bits) | c.min.unwrap())
+                    }
+                }
+            }
+        }
+    }
+
+    /// The largest element strictly less than `x`, if any; `x` may lie
+    /// outside the universe
+    pub fn predecessor(&self, x: uint) -> Option<uint> {
+        if x >= self.capacity() {
+            // every element lies below x
+            return self.max;
+        }
+        match self.rep {
+            Leaf(bits) => {
+                if x == 0 {
+                    return None;
+                }
+                let below = bits & ((1 << x) - 1);
+                if below == 0 { None } else { Some(highest_bit(below)) }
+            }
+            Node(ref clusters, ref summary) => {
+                match self.max {
+                    Some(m) if x > m => return Some(m),
+                    _ => ()
+                }
+                let lo_bits = self.ubits / 2;
+                let h = x >> lo_bits;
+                let l = x & ((1 << lo_bits) - 1);
+                match clusters[h] {
+                    Some(ref c) if c.min.is_some() && l > c.min.unwrap() => {
+                        // the predecessor lives in the same cluster as x
+                        return Some((h << lo_bits) |
+                                    c.predecessor(l).unwrap());
+                    }
+                    _ => ()
+                }
+                match summary.predecessor(h) {
+                    Some(hp) => {
+                        let c = clusters[hp].get_ref();
+                        Some((hp << lo_bits) | c.max.unwrap())
+                    }
+                    None => {
+                        // the only remaining candidate is the unclustered
+                        // minimum of this node
+                        match self.min {
+                            Some(m) if x > m => Some(m),
+                            _ => None
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Visit all values in order
+    pub fn each(&self, f: &fn(&uint) -> bool) -> bool {
+        let mut cur = self.min;
+        loop {
+            match cur {
+                None => return true,
+                Some(v) => {
+                    if !f(&v) {
+                        return false;
+                    }
+                    cur = self.successor(v);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic() {
+        let mut s = VebSet::new(16);
+        assert!(s.is_empty());
+        assert_eq!(s.capacity(), 1 << 16);
+
+        assert!(s.insert(100));
+        assert!(!s.insert(100));
+        assert!(s.insert(5));
+        assert!(s.insert(60_000));
+        assert_eq!(s.len(), 3);
+        assert!(s.contains(&5));
+        assert!(s.contains(&100));
+        assert!(s.contains(&60_000));
+        assert!(!s.contains(&99));
+
+        assert!(s.remove(&100));
+        assert!(!s.remove(&100));
+        assert!(!s.contains(&100));
+        assert_eq!(s.len(), 2);
+    }
+
+    #[test]
+    fn test_min_max() {
+        let mut s = VebSet::new(10);
+        assert_eq!(s.min(), None);
+        assert_eq!(s.max(), None);
+        assert!(s.insert(500));
+        assert!(s.insert(3));
+        assert!(s.insert(900));
+        assert_eq!(s.min(), Some(3));
+        assert_eq!(s.max(), Some(900));
+        assert!(s.remove(&3));
+        assert!(s.remove(&900));
+        assert_eq!(s.min(), Some(500));
+        assert_eq!(s.max(), Some(500));
+    }
+
+    #[test]
+    fn test_successor_predecessor() {
+        let mut s = VebSet::new(16);
+        let elems = [2u, 40, 41, 1000, 1024, 40_000];
+        for elems.iter().advance |&e| {
+            assert!(s.insert(e));
+        }
+
+        assert_eq!(s.successor(0), Some(2));
+        assert_eq!(s.successor(2), Some(40));
+        assert_eq!(s.successor(41), Some(1000));
+        assert_eq!(s.successor(1000), Some(1024));
+        assert_eq!(s.successor(40_000), None);
+
+        assert_eq!(s.predecessor(2), None);
+        assert_eq!(s.predecessor(40), Some(2));
+        assert_eq!(s.predecessor(1024), Some(1000));
+        assert_eq!(s.predecessor(65_535), Some(40_000));
+    }
+
+    #[test]
+    fn test_queries_past_the_universe() {
+        // capacity rounds up to a power of two, so thresholds a little
+        // above the universe are ordinary range-query inputs
+        let mut node = VebSet::new(12);
+        assert!(node.insert(9));
+        assert!(node.insert(4000));
+        assert_eq!(node.successor(5000), None);
+        assert_eq!(node.successor(node.capacity() - 1), None);
+        assert_eq!(node.predecessor(5000), Some(4000));
+        assert_eq!(node.predecessor(node.capacity()), Some(4000));
+
+        let mut leaf = VebSet::new(5);
+        assert!(leaf.insert(3));
+        assert_eq!(leaf.successor(100), None);
+        assert_eq!(leaf.predecessor(100), Some(3));
+
+        let empty = VebSet::new(12);
+        assert_eq!(empty.successor(100_000), None);
+        assert_eq!(empty.predecessor(100_000), None);
+    }
+
+    #[test]
+    fn test_each_in_order() {
+        let mut s = VebSet::new(12);
+        assert!(s.insert(9));
+        assert!(s.insert(4000));
+        assert!(s.insert(0));
+        assert!(s.insert(77));
+        let mut observed = ~[];
+        for s.each |&v| {
+            observed.push(v);
+        }
+        assert_eq!(observed, ~[0u, 9, 77, 4000]);
+    }
+
+    #[test]
+    fn test_exhaustive_small_universe() {
+        // insert every third element, then check every query against the
+        // obvious answers
+        let mut s = VebSet::new(8);
+        let mut i = 0;
+        while i < 256 {
+            assert!(s.insert(i));
+            i += 3;
+        }
+        i = 256;
+        for 256u.times {
+            i -= 1;
+            assert_eq!(s.contains(&i), i % 3 == 0);
+            let succ = s.successor(i);
+            if i < 255 - (255 % 3) {
+                assert_eq!(succ, Some(i - i % 3 + 3));
+            } else {
+                assert_eq!(succ, None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut s = VebSet::new(14);
+        assert!(s.insert(1));
+        assert!(s.insert(10_000));
+        s.clear();
+        assert!(s.is_empty());
+        assert!(!s.contains(&1));
+        assert_eq!(s.successor(0), None);
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*!
+ * A wavelet tree over a sequence of small integers with a bounded
+ * alphabet. The sequence is recursively partitioned by symbol value, one
+ * rank/select bit vector per node, giving `access`, `rank` and `select`
+ * over any symbol in time proportional to the bit width of the alphabet.
+ */
+
+use bitv;
+use bitv::Bitv;
+use rs_bitv::RsBitv;
+
+/// One node of the tree, covering the symbol range [lo, hi)
+struct WaveletNode {
+    /// The lowest symbol this node covers
+    lo: uint,
+    /// One past the highest symbol this node covers
+    hi: uint,
+    /// For internal nodes, bit i is set when the i-th sequence element in
+    /// this node belongs to the upper half of the range. For leaves the
+    /// vector is all zeros and only its length is meaningful.
+    bits: RsBitv,
+    left: Option<~WaveletNode>,
+    right: Option<~WaveletNode>
+}
+
+/// The wavelet tree type
+pub struct WaveletTree {
+    priv root: ~WaveletNode,
+    priv length: uint,
+    priv sigma: uint
+}
+
+fn build(seq: &[uint], lo: uint, hi: uint) -> ~WaveletNode {
+    if hi - lo <= 1 {
+        return ~WaveletNode{
+            lo: lo,
+            hi: hi,
+            bits: RsBitv::from_bitv(&Bitv::new(seq.len(), false)),
+            left: None,
+            right: None
+        };
+    }
+    let mid = (lo + hi) / 2;
+    let bitv = bitv::from_fn(seq.len(), |i| seq[i] >= mid);
+    let mut left_seq = ~[];
+    let mut right_seq = ~[];
+    for seq.iter().advance |&s| {
+        if s >= mid {
+            right_seq.push(s);
+        } else {
+            left_seq.push(s);
+        }
+    }
+    ~WaveletNode{
+        lo: lo,
+        hi: hi,
+        bits: RsBitv::from_bitv(&bitv),
+        left: Some(build(left_seq, lo, mid)),
+        right: Some(build(right_seq, mid, hi))
+    }
+}
+
+fn select_node(node: &WaveletNode, symbol: uint, k: uint) -> Option<uint> {
+    if node.hi - node.lo <= 1 {
+        return if k < node.bits.len() { Some(k) } else { None };
+    }
+    let mid = (node.lo + node.hi) / 2;
+    if symbol < mid {
+        match select_node(&**node.left.get_ref(), symbol, k) {
+            None => None,
+            Some(pos) => node.bits.select0(pos)
+        }
+    } else {
+        match select_node(&**node.right.get_ref(), symbol, k) {
+            None => None,
+            Some(pos) => node.bits.select(pos)
+        }
+    }
+}
+
+impl WaveletTree {
+    /// Build a wavelet tree over `seq`, whose symbols must all be smaller
+    /// than `sigma`
+    pub fn new(seq: &[uint], sigma: uint) -> WaveletTree {
+        assert!(sigma > 0);
+        for seq.iter().advance |&s| {
+            assert!(s < sigma);
+        }
+        WaveletTree{
+            root: build(seq, 0, sigma),
+            length: seq.len(),
+            sigma: sigma
+        }
+    }
+
+    /// The length of the indexed sequence
+    pub fn len(&self) -> uint { self.length }
+
+    /// The alphabet bound the tree was built with
+    pub fn sigma(&self) -> uint { self.sigma }
+
+    /// The symbol at position `i` of the sequence
+    pub fn access(&self, i: uint) -> uint {
+        assert!(i < self.length);
+        let mut node: &WaveletNode = &*self.root;
+        let mut idx = i;
+        loop {
+            if node.hi - node.lo <= 1 {
+                return node.lo;
+            }
+            if node.bits.get(idx) {
+                idx = node.bits.rank(idx);
+                node = &**node.right.get_ref();
+            } else {
+                idx = node.bits.rank0(idx);
+                node = &**node.left.get_ref();
+            }
+        }
+    }
+
+    /// The number of occurrences of `symbol` strictly before position `i`
+    pub fn rank(&self, symbol: uint, i: uint) -> uint {
+        assert!(i <= self.length);
+        if symbol >= self.sigma {
+            return 0;
+        }
+        let mut node: &WaveletNode = &*self.root;
+        let mut idx = i;
+        loop {
+            if node.hi - node.lo <= 1 {
+                return idx;
+            }
+            let mid = (node.lo + node.hi) / 2;
+            if symbol >= mid {
+                idx = node.bits.rank(idx);
+                node = &**node.right.get_ref();
+            } else {
+                idx = node.bits.rank0(idx);
+                node = &**node.left.get_ref();
+            }
+        }
+    }
+
+    /// The position of the `k`-th occurrence of `symbol` (zero-based), or
+    /// None if the symbol occurs fewer than `k + 1` times
+    pub fn select(&self, symbol: uint, k: uint) -> Option<uint> {
+        if symbol >= self.sigma {
+            return None;
+        }
+        select_node(&*self.root, symbol, k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::uint;
+
+    static SEQ: &'static [uint] = &[3u, 1, 4, 1, 5, 2, 6, 5, 3, 5, 0, 7];
+
+    #[test]
+    fn test_access() {
+        let wt = WaveletTree::new(SEQ, 8);
+        assert_eq!(wt.len(), SEQ.len());
+        for uint::range(0, SEQ.len()) |i| {
+            assert_eq!(wt.access(i), SEQ[i]);
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        let wt = WaveletTree::new(SEQ, 8);
+        for uint::range(0, 8) |symbol| {
+            let mut naive = 0;
+            for uint::range(0, SEQ.len()) |i| {
+                assert_eq!(wt.rank(symbol, i), naive);
+                if SEQ[i] == symbol { naive += 1; }
+            }
+            assert_eq!(wt.rank(symbol, SEQ.len()), naive);
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let wt = WaveletTree::new(SEQ, 8);
+        for uint::range(0, 8) |symbol| {
+            let mut k = 0;
+            for uint::range(0, SEQ.len()) |i| {
+                if SEQ[i] == symbol {
+                    assert_eq!(wt.select(symbol, k), Some(i));
+                    k += 1;
+                }
+            }
+            assert_eq!(wt.select(symbol, k), None);
+        }
+    }
+
+    #[test]
+    fn test_empty_sequence() {
+        let wt = WaveletTree::new([], 4);
+        assert_eq!(wt.len(), 0);
+        assert_eq!(wt.rank(2, 0), 0);
+        assert_eq!(wt.select(2, 0), None);
+    }
+
+    #[test]
+    fn test_singleton_alphabet() {
+        let wt = WaveletTree::new([0u, 0, 0], 1);
+        assert_eq!(wt.access(1), 0);
+        assert_eq!(wt.rank(0, 3), 3);
+        assert_eq!(wt.select(0, 2), Some(2));
+    }
+}